@@ -0,0 +1,230 @@
+//! Crash-safe writes for vault notes, images and the config file.
+//!
+//! Content is written to a temporary file in the target's directory, synced,
+//! and then moved into place, so a crash or a full disk never leaves a
+//! truncated note behind. Permissions and extended attributes of the file
+//! being replaced are carried over.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Replaces (or creates) `path` with `contents` atomically.
+/// Symlinks are followed, so the link itself stays intact.
+pub fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = resolve_symlink(path)?;
+    let existing = fs::metadata(&path).ok();
+
+    let (temp_path, mut temp) = create_temp_file(&path)?;
+    let result = (|| {
+        temp.write_all(contents)?;
+        temp.sync_all()?;
+        drop(temp);
+
+        if let Some(metadata) = &existing {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+            copy_xattrs(&path, &temp_path);
+        }
+
+        fs::rename(&temp_path, &path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    sync_parent(&path);
+    Ok(())
+}
+
+/// Creates `path` with `contents`, failing with `ErrorKind::AlreadyExists`
+/// instead of replacing a file that appeared in the meantime.
+pub fn create_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let (temp_path, mut temp) = create_temp_file(path)?;
+    let result = (|| {
+        temp.write_all(contents)?;
+        temp.sync_all()?;
+        drop(temp);
+
+        match fs::hard_link(&temp_path, path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+            // Dateisysteme ohne Hardlinks (z.B. exFAT): direkt exklusiv anlegen
+            Err(_) => {
+                let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+                file.write_all(contents)?;
+                file.sync_all()
+            }
+        }
+    })();
+
+    let _ = fs::remove_file(&temp_path);
+    result?;
+
+    sync_parent(path);
+    Ok(())
+}
+
+fn resolve_symlink(path: &Path) -> io::Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path),
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no parent"))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    loop {
+        // Punkt-Präfix: Obsidian und der Vault-Index ignorieren versteckte Dateien
+        let temp_path = dir.join(format!(
+            ".{}.{}-{}.tmp",
+            name,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn copy_xattrs(from: &Path, to: &Path) {
+    if !xattr::SUPPORTED_PLATFORM {
+        return;
+    }
+
+    let Ok(names) = xattr::list(from) else {
+        return;
+    };
+    for name in names {
+        if let Ok(Some(value)) = xattr::get(from, &name) {
+            if let Err(e) = xattr::set(to, &name, &value) {
+                log::debug!("Could not copy extended attribute: {}", e);
+            }
+        }
+    }
+}
+
+fn sync_parent(path: &Path) {
+    // Nur Unix kann Verzeichnisse öffnen und syncen
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        crate::test_support::temp_dir("atomic")
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn replaces_content_without_leaving_temp_files() {
+        let dir = temp_dir();
+        let path = dir.join("note.md");
+
+        write_file(&path, b"first").unwrap();
+        write_file(&path, b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(entries(&dir), vec!["note.md"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn preserves_permissions_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir();
+        let target = dir.join("target.md");
+        let link = dir.join("link.md");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_file(&link, b"new").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(
+            fs::metadata(&target).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn preserves_extended_attributes() {
+        let dir = temp_dir();
+        let path = dir.join("note.md");
+        fs::write(&path, "old").unwrap();
+        if xattr::set(&path, "user.collector.test", b"kept").is_err() {
+            // Dateisystem ohne xattr-Unterstützung
+            let _ = fs::remove_dir_all(dir);
+            return;
+        }
+
+        write_file(&path, b"new").unwrap();
+
+        assert_eq!(
+            xattr::get(&path, "user.collector.test").unwrap(),
+            Some(b"kept".to_vec())
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn create_file_never_replaces_existing_notes() {
+        let dir = temp_dir();
+        let path = dir.join("note.md");
+
+        create_file(&path, b"first").unwrap();
+        let err = create_file(&path, b"second").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert_eq!(entries(&dir), vec!["note.md"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,18 +1,29 @@
-use chrono::Local;
-use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
-
+use chrono::{DateTime, Days, Duration, FixedOffset, Local, NaiveDate, Timelike};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write;
+use crate::capture_undo::WrittenCapture;
+use crate::frontmatter::{self, PropertyValue};
 use crate::log_safety::{redact_path, summarize_text_len};
-use crate::settings::Settings;
+use crate::note_encoding;
+use crate::sections::{self, Anchor, HeadingSpec};
+use crate::settings::{CollisionPolicy, EntryStyle, InsertPosition, Period, Settings};
+use crate::tagging;
+use crate::task_dates;
+use crate::template;
 
 #[derive(Debug)]
 pub struct CaptureResult {
     #[allow(dead_code)]
     pub success: bool,
+    #[allow(dead_code)]
     pub message: String,
+    pub written: WrittenCapture,
 }
 
+#[cfg(test)]
 fn generate_header(template: &str) -> String {
     generate_header_with_time(template, Local::now())
 }
@@ -24,59 +35,195 @@ fn generate_header_with_time<Tz: chrono::TimeZone>(
 where
     Tz::Offset: std::fmt::Display,
 {
-    // 12h-Zeitwerte vorbereiten
-    let hour_12 = dt.format("%I").to_string();
-    let hour_12_nopad = if hour_12.starts_with('0') {
-        hour_12[1..].to_string()
-    } else {
-        hour_12.clone()
+    template::format_date(template, &dt)
+}
+
+/// Formats a capture as one entry in `style`. Follow-up lines are indented or
+/// quoted so a multi-line capture stays one list item or one callout.
+fn format_entry_with_time<Tz: chrono::TimeZone>(
+    style: EntryStyle,
+    text: &str,
+    settings: &Settings,
+    dt: chrono::DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let time = || template::format_date(&settings.entry_time_format, &dt);
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or_default();
+
+    let (mut entry, continuation) = match style {
+        EntryStyle::Header => {
+            let header = generate_header_with_time(&settings.entry_header, dt.clone());
+            return format!("{}\n{}\n", header, text);
+        }
+        EntryStyle::Bullet => (format!("- {} {}\n", time(), first), "  "),
+        EntryStyle::Task => {
+            let first = task_dates::apply(first, settings.task_date_format, dt.naive_local());
+            (format!("- [ ] {}\n", first), "  ")
+        }
+        EntryStyle::Quote => (quote_line(first), "> "),
+        EntryStyle::Callout => (format!("> [!note] {}\n{}", time(), quote_line(first)), "> "),
     };
-    let ampm_lower = dt.format("%P").to_string();
-    let ampm_upper = dt.format("%p").to_string();
-
-    // Token → Wert Zuordnung.
-    // Längere Token zuerst, damit z.B. "hh" vor "h" matched.
-    let tokens: &[(&str, String)] = &[
-        ("YYYY", dt.format("%Y").to_string()),
-        ("MM", dt.format("%m").to_string()),
-        ("DD", dt.format("%d").to_string()),
-        ("HH", dt.format("%H").to_string()),
-        ("hh", hour_12),
-        ("mm", dt.format("%M").to_string()),
-        ("ss", dt.format("%S").to_string()),
-        ("h", hour_12_nopad),
-        ("a", ampm_lower),
-        ("A", ampm_upper),
-    ];
-
-    // Single-Pass: jedes Token wird nur im Original-Template erkannt,
-    // sodass eingesetzte Werte (z.B. "pm" enthält 'a') nie erneut ersetzt werden.
-    let mut result = String::with_capacity(template.len() * 2);
-    let mut remaining = template;
-    while !remaining.is_empty() {
-        if let Some((tok, val)) = tokens.iter().find(|(tok, _)| remaining.starts_with(tok)) {
-            result.push_str(val);
-            remaining = &remaining[tok.len()..];
+
+    for line in lines {
+        if line.trim().is_empty() {
+            // Leere Zeilen ohne Einrückung, im Zitat bleibt nur ">"
+            entry.push_str(continuation.trim_end());
         } else {
-            // Safety: wir nehmen ein UTF-8-Zeichen, nicht nur ein Byte.
-            let ch = remaining.chars().next().unwrap();
-            result.push(ch);
-            remaining = &remaining[ch.len_utf8()..];
+            entry.push_str(continuation);
+            entry.push_str(line);
         }
+        entry.push('\n');
+    }
+
+    entry
+}
+
+fn quote_line(line: &str) -> String {
+    if line.trim().is_empty() {
+        ">\n".to_string()
+    } else {
+        format!("> {}\n", line)
+    }
+}
+
+/// Vault-relative path of the daily note for `note_time`, see `daily_note_time`.
+pub fn build_daily_note_path<Tz: chrono::TimeZone>(
+    settings: &Settings,
+    note_time: &DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    build_dated_note_path(
+        &settings.daily_note_folder,
+        &settings.daily_note_format,
+        note_time,
+    )
+}
+
+/// `dt` in the configured time zone, or the system's if none is set.
+pub fn local_time(settings: &Settings, dt: &DateTime<Local>) -> DateTime<FixedOffset> {
+    match settings.time_zone.parse::<chrono_tz::Tz>() {
+        Ok(tz) => dt.with_timezone(&tz).fixed_offset(),
+        Err(_) => dt.fixed_offset(),
+    }
+}
+
+/// The time a capture is written into daily and periodic notes with: its clock
+/// time in the configured time zone, on the day before if it was made before
+/// `day_starts_at`. Paths, templates and entry headers are rendered with it.
+pub fn daily_note_time(
+    settings: &Settings,
+    captured_at: &DateTime<Local>,
+) -> DateTime<FixedOffset> {
+    let local = local_time(settings, captured_at);
+    // Wanduhr statt Zeitspanne: an Umstellungstagen hat ein Tag 23 oder 25 Stunden
+    if local.hour() < settings.day_starts_at {
+        local - Duration::days(1)
+    } else {
+        local
+    }
+}
+
+/// The day of a daily note given as `YYYY-MM-DD`, `today`, `yesterday`,
+/// `tomorrow` or an offset from `today` like `-2d`, `+1w` or `3 days ago`.
+pub fn parse_daily_note_date(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.trim().to_lowercase();
+    let date = match input.as_str() {
+        "today" => Some(today),
+        "yesterday" => today.checked_sub_days(Days::new(1)),
+        "tomorrow" => today.checked_add_days(Days::new(1)),
+        _ => NaiveDate::parse_from_str(&input, "%Y-%m-%d")
+            .ok()
+            .or_else(|| {
+                day_offset(&input)
+                    .and_then(|days| today.checked_add_signed(chrono::Duration::days(days)))
+            }),
+    };
+    date.ok_or_else(|| {
+        format!(
+            "Unknown date '{}', use YYYY-MM-DD, \"yesterday\" or an offset like \"-2d\"",
+            input
+        )
+    })
+}
+
+/// Days in `-2d`, `+1w`, `3 days` or `2 days ago`.
+fn day_offset(input: &str) -> Option<i64> {
+    let (amount, ago) = match input.strip_suffix(" ago") {
+        Some(amount) => (amount, true),
+        None => (input, false),
+    };
+    let (count, unit) = amount.split_at(amount.find(|c: char| c.is_ascii_alphabetic())?);
+    let count = i64::from(count.trim().parse::<i32>().ok()?);
+    let days = match unit {
+        "d" | "day" | "days" => count,
+        "w" | "week" | "weeks" => count * 7,
+        _ => return None,
+    };
+    match ago {
+        true if count < 0 => None,
+        true => Some(-days),
+        false => Some(days),
+    }
+}
+
+/// Like `daily_note_time`, but on `date` (`YYYY-MM-DD`). For another day than
+/// the capture's own, the entry header and entry time show
+/// `other_day_entry_time` instead of the time, if set.
+pub fn at_daily_note_date(
+    settings: &mut Settings,
+    date: &str,
+    captured_at: &DateTime<Local>,
+) -> Result<DateTime<FixedOffset>, String> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid daily note date '{}': {}", date, e))?;
+    let note_time = daily_note_time(settings, captured_at);
+    if date == note_time.date_naive() {
+        return Ok(note_time);
+    }
+
+    if !settings.other_day_entry_time.is_empty() {
+        let placeholder = &settings.other_day_entry_time;
+        settings.entry_header = template::replace_time(&settings.entry_header, placeholder);
+        settings.entry_time_format =
+            template::replace_time(&settings.entry_time_format, placeholder);
     }
-    result
+
+    let days = (date - note_time.date_naive()).num_days();
+    note_time
+        .checked_add_signed(Duration::days(days))
+        .ok_or_else(|| format!("Invalid daily note date '{}'", date))
 }
 
-pub fn build_daily_note_path(settings: &Settings) -> String {
-    let now = Local::now();
+/// Vault-relative path of the week, month, quarter or year note containing
+/// `note_time`, see `daily_note_time`.
+pub fn build_periodic_note_path<Tz: chrono::TimeZone>(
+    period: Period,
+    settings: &Settings,
+    note_time: &DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let note = settings.periodic_note(period);
+    build_dated_note_path(&note.folder, &note.format, note_time)
+}
 
-    let filename = settings
-        .daily_note_format
-        .replace("YYYY", &now.format("%Y").to_string())
-        .replace("MM", &now.format("%m").to_string())
-        .replace("DD", &now.format("%d").to_string());
+fn build_dated_note_path<Tz: chrono::TimeZone>(
+    folder: &str,
+    format: &str,
+    dt: &chrono::DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let filename = template::format_date(format, dt);
 
-    let mut path = parse_daily_note_path(&settings.daily_note_folder);
+    let mut path = template::format_path(folder, dt);
 
     if !path.is_empty() && !path.ends_with('/') {
         path.push('/');
@@ -92,17 +239,12 @@ pub fn build_daily_note_path(settings: &Settings) -> String {
 }
 
 #[allow(dead_code)]
-pub fn parse_daily_note_path(template: &str) -> String {
-    let now = Local::now();
-
-    template
-        .replace("YYYY", &now.format("%Y").to_string())
-        .replace("MM", &now.format("%m").to_string())
-        .replace("DD", &now.format("%d").to_string())
+pub fn parse_daily_note_path(template: &str, settings: &Settings) -> String {
+    template::format_path(template, &daily_note_time(settings, &Local::now()))
 }
 
-pub fn build_note_relative_path(settings: &Settings) -> String {
-    let filename = generate_filename_from_template(&settings.note_filename_template);
+pub fn build_note_relative_path(settings: &Settings, dt: &DateTime<Local>) -> String {
+    let filename = generate_filename_from_template(&settings.note_filename_template, dt);
     let notes_folder = settings.notes_folder.trim_end_matches('/');
 
     if notes_folder.is_empty() {
@@ -112,10 +254,14 @@ pub fn build_note_relative_path(settings: &Settings) -> String {
     }
 }
 
-pub fn build_note_relative_path_from_title(title: &str, settings: &Settings) -> String {
+pub fn build_note_relative_path_from_title(
+    title: &str,
+    settings: &Settings,
+    dt: &DateTime<Local>,
+) -> String {
     let sanitized = sanitize_note_title(title);
     let filename = if sanitized.is_empty() {
-        generate_filename_from_template(&settings.note_filename_template)
+        generate_filename_from_template(&settings.note_filename_template, dt)
     } else if sanitized.ends_with(".md") {
         sanitized
     } else {
@@ -144,94 +290,443 @@ fn sanitize_note_title(title: &str) -> String {
         .to_string()
 }
 
-pub fn save_note_at_path(
+/// Where `save_as_note` writes after applying the collision policy.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NoteTarget {
+    Create(PathBuf),
+    Append(PathBuf),
+}
+
+const MAX_COLLISION_SUFFIX: u32 = 999;
+
+/// Applies `policy` to a note path built by `build_note_relative_path` or
+/// `build_note_relative_path_from_title` (resolved inside the vault).
+pub fn resolve_note_collision(
+    file_path: &Path,
+    policy: CollisionPolicy,
+) -> Result<NoteTarget, String> {
+    if !file_path.exists() {
+        return Ok(NoteTarget::Create(file_path.to_path_buf()));
+    }
+
+    match policy {
+        CollisionPolicy::Append => Ok(NoteTarget::Append(file_path.to_path_buf())),
+        CollisionPolicy::Error => Err(format!(
+            "Note already exists: {}",
+            file_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        )),
+        CollisionPolicy::Suffix => {
+            let stem = file_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // Wie Obsidian: "Titel 1.md", "Titel 2.md", ...
+            (1..=MAX_COLLISION_SUFFIX)
+                .map(|n| file_path.with_file_name(format!("{} {}.md", stem, n)))
+                .find(|candidate| !candidate.exists())
+                .map(NoteTarget::Create)
+                .ok_or_else(|| format!("Too many notes named {}", stem))
+        }
+    }
+}
+
+/// Appends a capture to an existing note, separated by a blank line.
+pub fn append_note_content(
     content: &str,
     file_path: &Path,
     filename: &str,
+) -> Result<CaptureResult, String> {
+    let note =
+        note_encoding::read(file_path).map_err(|e| format!("Cannot read note file: {}", e))?;
+    let existing = &note.content;
+
+    // Nur anhängen, nichts Bestehendes kürzen: so bleibt der Eintrag rückgängig machbar
+    let mut appended = String::new();
+    if !existing.trim().is_empty() && !sections::ends_with_blank_line(existing) {
+        appended.push_str(if existing.ends_with('\n') {
+            "\n"
+        } else {
+            "\n\n"
+        });
+    }
+    appended.push_str(content);
+    if !content.ends_with('\n') {
+        appended.push('\n');
+    }
+
+    let updated = format!("{}{}", existing, appended);
+    let (updated, range) = note.match_line_endings(&updated, existing.len()..updated.len());
+    atomic_write::write_file(file_path, &note.encode(&updated))
+        .map_err(|e| format!("Failed to write note file: {}", e))?;
+
+    log::info!(
+        "Appended to existing note (file={})",
+        redact_path(file_path)
+    );
+
+    Ok(CaptureResult {
+        success: true,
+        message: format!("Appended to note: {}", filename),
+        written: WrittenCapture::inserted(file_path, &updated, range),
+    })
+}
+
+/// Writes a new note to `target`, which `resolve_note_collision` chose for
+/// `requested`. If a note appeared at `target` in the meantime, the collision
+/// policy is applied again (e.g. `Title 2.md` instead of `Title 1.md`) rather
+/// than failing the save.
+pub fn save_new_note(
+    content: &str,
+    requested: &Path,
+    target: NoteTarget,
     settings: &Settings,
+    captured_at: &DateTime<Local>,
 ) -> Result<CaptureResult, String> {
+    let mut target = target;
+    loop {
+        let file_path = match target {
+            NoteTarget::Create(path) => path,
+            NoteTarget::Append(path) => {
+                return append_note_content(content, &path, &file_name(&path));
+            }
+        };
+
+        match save_note_at_path(content, &file_path, settings, captured_at) {
+            Err(e)
+                if e.kind() == std::io::ErrorKind::AlreadyExists
+                    && settings.note_collision_policy != CollisionPolicy::Error =>
+            {
+                log::info!(
+                    "Note created in the meantime, resolving again (file={})",
+                    redact_path(&file_path)
+                );
+                target = resolve_note_collision(requested, settings.note_collision_policy)?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(format!("Note already exists: {}", file_name(&file_path)));
+            }
+            Err(e) => return Err(format!("Failed to write note file: {}", e)),
+            Ok(result) => return Ok(result),
+        }
+    }
+}
+
+fn save_note_at_path(
+    content: &str,
+    file_path: &Path,
+    settings: &Settings,
+    captured_at: &DateTime<Local>,
+) -> std::io::Result<CaptureResult> {
     if let Some(parent) = file_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create notes directory: {}", e))?;
+        fs::create_dir_all(parent)?;
     }
 
-    let final_content = if !settings.note_template.is_empty() {
-        format!("{}\n\n{}", settings.note_template, content)
-    } else {
-        content.to_string()
-    };
+    let title = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let properties = note_properties(settings, &title, captured_at);
+    let final_content = frontmatter::compose_note(&settings.note_template, content, &properties);
 
-    fs::write(&file_path, final_content)
-        .map_err(|e| format!("Failed to write note file: {}", e))?;
+    atomic_write::create_file(file_path, final_content.as_bytes())?;
 
     log::info!("Note saved (file={})", redact_path(&file_path));
 
     Ok(CaptureResult {
         success: true,
-        message: format!("Note saved: {}", filename),
+        message: format!("Note saved: {}", file_name(file_path)),
+        written: WrittenCapture::created(file_path, &final_content),
     })
 }
 
-fn generate_filename_from_template(template: &str) -> String {
-    let now = Local::now();
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The frontmatter properties configured for new notes, values expanded.
+fn note_properties<'a>(
+    settings: &'a Settings,
+    title: &str,
+    captured_at: &DateTime<Local>,
+) -> Vec<(&'a str, PropertyValue)> {
+    let config = &settings.note_properties;
+    let expand = |value: &String| template::expand_note_template(value.trim(), title, captured_at);
+
+    let mut properties = Vec::new();
+    if config.created {
+        let created = template::format_date(&config.created_format, captured_at);
+        properties.push(("created", PropertyValue::Text(created)));
+    }
+    let tags = config
+        .tags
+        .iter()
+        .filter_map(|tag| tagging::normalize_tag(tag))
+        .collect();
+    properties.push(("tags", PropertyValue::List(tags)));
+    let aliases = config
+        .aliases
+        .iter()
+        .map(expand)
+        .filter(|alias| !alias.is_empty())
+        .collect();
+    properties.push(("aliases", PropertyValue::List(aliases)));
+    properties.extend(config.custom.iter().map(|property| {
+        (
+            property.key.trim(),
+            PropertyValue::Text(expand(&property.value)),
+        )
+    }));
+    properties
+}
+
+fn generate_filename_from_template(template: &str, dt: &DateTime<Local>) -> String {
+    // Hinweis: Doppelpunkte in Filename-Templates vermeiden
+    // (macOS erlaubt keine Doppelpunkte in Dateinamen).
+    let mut filename = template::format_date(template, dt);
+    if !filename.ends_with(".md") {
+        filename.push_str(".md");
+    }
+
+    filename
+}
+
+/// Creates a missing daily or periodic note (and its folders) from `template`,
+/// expanding the core template variables. Returns `false` if the note already existed.
+pub fn create_note_from_template<Tz: chrono::TimeZone>(
+    file_path: &Path,
+    template: Option<&str>,
+    dt: &DateTime<Tz>,
+) -> Result<bool, String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if file_path.exists() {
+        return Ok(false);
+    }
+
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create note directory: {}", e))?;
+    }
+
+    let title = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let content = template
+        .map(|template| template::expand_note_template(template, &title, dt))
+        .unwrap_or_default();
+
+    // Eine parallel angelegte Note wird nicht überschrieben
+    match atomic_write::create_file(file_path, content.as_bytes()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(format!("Failed to create note: {}", e)),
+    }
+
+    log::info!(
+        "Note created from template (file={})",
+        redact_path(file_path)
+    );
+    Ok(true)
+}
+
+/// `note_time` comes from `daily_note_time` or `at_daily_note_date`.
+pub fn append_to_daily_note<Tz: chrono::TimeZone>(
+    captured_text: &str,
+    file_path: &Path,
+    settings: &Settings,
+    note_time: &DateTime<Tz>,
+) -> Result<WrittenCapture, String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let heading = parse_heading(&settings.daily_note_heading)?.map(Anchor::Heading);
+    append_entry(
+        captured_text,
+        file_path,
+        heading.as_ref(),
+        settings.daily_note_insert_position,
+        settings.daily_note_entry_style,
+        settings,
+        note_time,
+    )
+}
+
+/// `note_time` comes from `daily_note_time`.
+pub fn append_to_periodic_note<Tz: chrono::TimeZone>(
+    captured_text: &str,
+    file_path: &Path,
+    period: Period,
+    settings: &Settings,
+    note_time: &DateTime<Tz>,
+) -> Result<WrittenCapture, String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let note = settings.periodic_note(period);
+    let heading = parse_heading(&note.heading)?.map(Anchor::Heading);
+    append_entry(
+        captured_text,
+        file_path,
+        heading.as_ref(),
+        note.insert_position,
+        note.entry_style,
+        settings,
+        note_time,
+    )
+}
+
+pub fn append_to_note(
+    captured_text: &str,
+    file_path: &Path,
+    anchor: Option<&Anchor>,
+    settings: &Settings,
+    captured_at: &DateTime<Local>,
+) -> Result<WrittenCapture, String> {
+    append_entry(
+        captured_text,
+        file_path,
+        anchor,
+        settings.append_to_note_insert_position,
+        settings.append_to_note_entry_style,
+        settings,
+        &local_time(settings, captured_at),
+    )
+}
+
+/// The note path and anchor of an Append to Note target. The anchor comes from
+/// the path (`Project.md#^inbox`, `Project.md#Log`) or from `heading`, not both.
+/// Like in Obsidian links, `.md` may be omitted.
+pub fn note_anchor(path: &str, heading: Option<&str>) -> Result<(String, Option<Anchor>), String> {
+    let (note, from_path) = sections::split_anchor(path.trim())?;
+    let heading = parse_heading(heading.unwrap_or_default())?;
+    let anchor = match (from_path, heading) {
+        (Some(_), Some(_)) => {
+            return Err(
+                "Give the heading either in the note path or separately, not both".to_string(),
+            )
+        }
+        (Some(anchor), None) => Some(anchor),
+        (None, heading) => heading.map(Anchor::Heading),
+    };
 
-    // 12h-Zeitwerte vorbereiten
-    let hour_12 = now.format("%I").to_string();
-    let hour_12_nopad = if hour_12.starts_with('0') {
-        hour_12[1..].to_string()
+    let note = if note.ends_with(".md") {
+        note.to_string()
     } else {
-        hour_12.clone()
+        format!("{}.md", note)
     };
-    let ampm_lower = now.format("%P").to_string();
-    let ampm_upper = now.format("%p").to_string();
+    Ok((note, anchor))
+}
 
-    // Token → Wert Zuordnung.
-    // Längere Token zuerst, damit z.B. "hh" vor "h" matched.
-    // WICHTIG: Die Token-Namen selbst enthalten keine problematischen Zeichen,
-    // aber Nutzer sollten Doppelpunkte in Filename-Templates vermeiden
-    // (macOS erlaubt keine Doppelpunkte in Dateinamen).
-    let tokens: &[(&str, String)] = &[
-        ("YYYY", now.format("%Y").to_string()),
-        ("MM", now.format("%m").to_string()),
-        ("DD", now.format("%d").to_string()),
-        ("HH", now.format("%H").to_string()),
-        ("hh", hour_12),
-        ("mm", now.format("%M").to_string()),
-        ("ss", now.format("%S").to_string()),
-        ("h", hour_12_nopad),
-        ("a", ampm_lower),
-        ("A", ampm_upper),
-    ];
-
-    // Single-Pass: jedes Token wird nur im Original-Template erkannt,
-    // sodass eingesetzte Werte (z.B. "pm" enthält 'a') nie erneut ersetzt werden.
-    let mut result = String::with_capacity(template.len() * 2);
-    let mut remaining = template;
-    while !remaining.is_empty() {
-        if let Some((tok, val)) = tokens.iter().find(|(tok, _)| remaining.starts_with(tok)) {
-            result.push_str(val);
-            remaining = &remaining[tok.len()..];
-        } else {
-            // Safety: wir nehmen ein UTF-8-Zeichen, nicht nur ein Byte.
-            let ch = remaining.chars().next().unwrap();
-            result.push(ch);
-            remaining = &remaining[ch.len_utf8()..];
+fn parse_heading(value: &str) -> Result<Option<HeadingSpec>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+
+    HeadingSpec::parse(value)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid heading: {}", value.trim()))
+}
+
+/// Places `entry` in `content` according to the target's heading and position.
+/// Quote and callout entries are kept apart from neighbouring blocks.
+/// Returns the new content and the byte range of the inserted text.
+fn insert_entry(
+    content: &str,
+    heading: Option<&HeadingSpec>,
+    position: InsertPosition,
+    style: EntryStyle,
+    entry: &str,
+) -> (String, Range<usize>) {
+    let offset = match (heading, position) {
+        (Some(heading), InsertPosition::Append) => {
+            sections::section_insert_offset(content, heading)
         }
+        (Some(heading), InsertPosition::Prepend) => sections::heading_body_offset(content, heading),
+        (None, InsertPosition::Append) => Some(content.len()),
+        (None, InsertPosition::Prepend) => Some(sections::body_start_offset(content)),
+    };
+
+    match (offset, heading) {
+        (Some(offset), _) => insert_at_offset(content, offset, style, entry),
+        (None, Some(heading)) => sections::insert_under_heading(content, heading, entry),
+        (None, None) => sections::insert_at(content, content.len(), entry),
     }
+}
 
-    let mut filename = result;
-    if !filename.ends_with(".md") {
-        filename.push_str(".md");
+fn insert_at_offset(
+    content: &str,
+    offset: usize,
+    style: EntryStyle,
+    entry: &str,
+) -> (String, Range<usize>) {
+    if is_block_style(style) {
+        sections::insert_block_at(content, offset, entry)
+    } else {
+        sections::insert_at(content, offset, entry)
     }
+}
 
-    filename
+/// Places the capture at the block with `^id`. In a list it becomes a list
+/// item (a bullet unless the style is already a list style), indented to match.
+fn insert_at_block(
+    content: &str,
+    id: &str,
+    position: InsertPosition,
+    style: EntryStyle,
+    entry: impl Fn(EntryStyle) -> String,
+) -> Result<(String, Range<usize>), String> {
+    let block = sections::find_block(content, id)
+        .ok_or_else(|| format!("Block ^{} not found in note", id))?;
+    let offset = match position {
+        InsertPosition::Append => block.append_offset,
+        InsertPosition::Prepend => block.prepend_offset,
+    };
+
+    Ok(match block.list_indent {
+        Some(indent) => {
+            let style = match style {
+                EntryStyle::Bullet | EntryStyle::Task => style,
+                _ => EntryStyle::Bullet,
+            };
+            let item = entry(style)
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        "\n".to_string()
+                    } else {
+                        format!("{}{}\n", indent, line)
+                    }
+                })
+                .collect::<String>();
+            sections::insert_at(content, offset, &item)
+        }
+        None => insert_at_offset(content, offset, style, &entry(style)),
+    })
+}
+
+fn is_block_style(style: EntryStyle) -> bool {
+    matches!(style, EntryStyle::Quote | EntryStyle::Callout)
 }
 
-pub fn append_to_daily_note(
+fn append_entry<Tz: chrono::TimeZone>(
     captured_text: &str,
     file_path: &Path,
+    anchor: Option<&Anchor>,
+    position: InsertPosition,
+    style: EntryStyle,
     settings: &Settings,
-) -> Result<(), String> {
+    captured_at: &DateTime<Tz>,
+) -> Result<WrittenCapture, String>
+where
+    Tz::Offset: std::fmt::Display,
+{
     if captured_text.trim().is_empty() {
         return Err("Nothing to append".to_string());
     }
@@ -249,78 +744,35 @@ pub fn append_to_daily_note(
         ));
     }
 
-    let header = generate_header(&settings.entry_header);
+    let entry = |style| format_entry_with_time(style, captured_text, settings, captured_at.clone());
 
-    let entry = format!(
-        "{}
-{}
-",
-        header, captured_text
-    );
-
-    let needs_leading_newline = {
-        let mut check_file =
-            File::open(&file_path).map_err(|e| format!("Cannot open daily note: {}", e))?;
+    let name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
 
-        let file_size = check_file
-            .metadata()
-            .map_err(|e| format!("Cannot read file metadata: {}", e))?
-            .len();
-
-        if file_size == 0 {
-            false
-        } else {
-            let seek_pos = if file_size >= 2 { file_size - 2 } else { 0 };
-            check_file
-                .seek(SeekFrom::Start(seek_pos))
-                .map_err(|e| format!("Cannot set file position: {}", e))?;
-
-            let mut check_buffer = [0u8; 2];
-            let bytes_read = check_file
-                .read(&mut check_buffer)
-                .map_err(|e| format!("Cannot read file: {}", e))?;
-
-            match bytes_read {
-                2 => !(check_buffer == [0x0D, 0x0A] || check_buffer[1] == 0x0A),
-                1 => check_buffer[0] != 0x0A,
-                _ => true,
-            }
+    // Lesen, einfügen und atomar ersetzen: kein halb geschriebener Eintrag bei Absturz
+    let note =
+        note_encoding::read(file_path).map_err(|e| format!("Cannot read {}: {}", name, e))?;
+    let (updated, range) = match anchor {
+        Some(Anchor::Block(id)) => insert_at_block(&note.content, id, position, style, entry)?,
+        Some(Anchor::Heading(heading)) => {
+            insert_entry(&note.content, Some(heading), position, style, &entry(style))
         }
+        None => insert_entry(&note.content, None, position, style, &entry(style)),
     };
+    // Zeilenenden und Kodierung der Note übernehmen
+    let (updated, range) = note.match_line_endings(&updated, range);
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open(file_path)
-        .map_err(|e| format!("Cannot open daily note: {}", e))?;
-
-    if needs_leading_newline {
-        file.write_all(
-            b"
-",
-        )
-        .map_err(|e| format!("Cannot write to file: {}", e))?;
-    }
-
-    file.write_all(entry.as_bytes())
+    atomic_write::write_file(file_path, &note.encode(&updated))
         .map_err(|e| format!("Cannot write to file: {}", e))?;
 
-    file.sync_all()
-        .map_err(|e| format!("Cannot sync file: {}", e))?;
-
     log::info!(
-        "Successfully appended to daily note (file={})",
-        redact_path(&file_path)
+        "Successfully inserted entry (file={}, position={:?})",
+        redact_path(file_path),
+        position
     );
-    Ok(())
-}
-
-pub fn append_to_note(
-    captured_text: &str,
-    file_path: &Path,
-    settings: &Settings,
-) -> Result<(), String> {
-    append_to_daily_note(captured_text, file_path, settings)
+    Ok(WrittenCapture::inserted(file_path, &updated, range))
 }
 
 #[cfg(test)]
@@ -381,7 +833,7 @@ mod tests {
 
     #[test]
     fn test_parse_daily_note_path() {
-        let path = parse_daily_note_path("Tagebuch/YYYY/YYYY-MM-DD.md");
+        let path = parse_daily_note_path("Tagebuch/YYYY/YYYY-MM-DD.md", &Settings::default());
         assert!(path.contains("/"));
         assert!(path.ends_with(".md"));
         assert!(!path.contains("YYYY"));
@@ -395,7 +847,7 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_daily_note_path(&settings);
+        let path = build_daily_note_path(&settings, &Local::now());
         assert!(path.starts_with("Journal/Notes/"));
         assert!(path.ends_with(".md"));
         assert!(!path.contains("YYYY"));
@@ -403,6 +855,144 @@ mod tests {
         assert!(!path.contains("DD"));
     }
 
+    #[test]
+    fn test_parse_daily_note_date() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let parse = |input| parse_daily_note_date(input, today).unwrap().to_string();
+
+        assert_eq!(parse("Yesterday"), "2026-02-28");
+        assert_eq!(parse("today"), "2026-03-01");
+        assert_eq!(parse("tomorrow"), "2026-03-02");
+        assert_eq!(parse("-2d"), "2026-02-27");
+        assert_eq!(parse("+1w"), "2026-03-08");
+        assert_eq!(parse("3 days ago"), "2026-02-26");
+        assert_eq!(parse(" 2025-12-24 "), "2025-12-24");
+        assert!(parse_daily_note_date("last year", today).is_err());
+        assert!(parse_daily_note_date("-2 days ago", today).is_err());
+    }
+
+    #[test]
+    fn test_daily_note_of_another_day() {
+        use chrono::TimeZone;
+        let captured_at = Local.with_ymd_and_hms(2026, 3, 2, 8, 30, 0).unwrap();
+        let mut settings = Settings {
+            daily_note_folder: "Journal".to_string(),
+            daily_note_format: "YYYY-MM-DD".to_string(),
+            entry_header: "#### dddd HH:mm".to_string(),
+            other_day_entry_time: "--:--".to_string(),
+            ..Default::default()
+        };
+
+        let same = at_daily_note_date(&mut settings, "2026-03-02", &captured_at).unwrap();
+        assert_eq!(same, captured_at);
+        assert_eq!(settings.entry_header, "#### dddd HH:mm");
+
+        let dt = at_daily_note_date(&mut settings, "2026-03-01", &captured_at).unwrap();
+        assert_eq!(
+            build_daily_note_path(&settings, &dt),
+            "Journal/2026-03-01.md"
+        );
+        assert_eq!(
+            format_entry_with_time(EntryStyle::Header, "late", &settings, dt),
+            "#### Sunday --:--\nlate\n"
+        );
+        assert!(at_daily_note_date(&mut settings, "yesterday", &captured_at).is_err());
+    }
+
+    #[test]
+    fn test_daily_note_time_in_time_zone() {
+        use chrono::{TimeZone, Utc};
+        let at = |h, m| {
+            Utc.with_ymd_and_hms(2026, 3, 28, h, m, 0)
+                .unwrap()
+                .with_timezone(&Local)
+        };
+        let mut settings = Settings {
+            daily_note_folder: String::new(),
+            daily_note_format: "YYYY-MM-DD".to_string(),
+            time_zone: "Europe/Berlin".to_string(),
+            ..Default::default()
+        };
+        let path = |settings: &Settings, dt| {
+            build_daily_note_path(settings, &daily_note_time(settings, &dt))
+        };
+
+        // 23:30 UTC ist in Berlin schon der nächste Tag, in New York noch derselbe
+        assert_eq!(path(&settings, at(23, 30)), "2026-03-29.md");
+        settings.time_zone = "America/New_York".to_string();
+        assert_eq!(path(&settings, at(23, 30)), "2026-03-28.md");
+        assert_eq!(
+            format_entry_with_time(
+                EntryStyle::Header,
+                "x",
+                &settings,
+                daily_note_time(&settings, &at(23, 30))
+            ),
+            "#### 19:30\nx\n"
+        );
+    }
+
+    #[test]
+    fn test_day_starts_at_across_dst_changes() {
+        use chrono::{TimeZone, Utc};
+        let utc = |m, d, h, min| {
+            Utc.with_ymd_and_hms(2026, m, d, h, min, 0)
+                .unwrap()
+                .with_timezone(&Local)
+        };
+        let settings = Settings {
+            daily_note_folder: String::new(),
+            daily_note_format: "YYYY-MM-DD".to_string(),
+            entry_header: "#### HH:mm".to_string(),
+            time_zone: "Europe/Berlin".to_string(),
+            day_starts_at: 3,
+            ..Default::default()
+        };
+        let note = |dt| {
+            let note_time = daily_note_time(&settings, &dt);
+            (
+                build_daily_note_path(&settings, &note_time),
+                generate_header_with_time(&settings.entry_header, note_time),
+            )
+        };
+
+        // 29. März: 02:00 MEZ springt auf 03:00 MESZ, der Tag beginnt nach der Wanduhr
+        assert_eq!(
+            note(utc(3, 29, 0, 30)),
+            ("2026-03-28.md".into(), "#### 01:30".into())
+        );
+        assert_eq!(
+            note(utc(3, 29, 1, 30)),
+            ("2026-03-29.md".into(), "#### 03:30".into())
+        );
+
+        // 25. Oktober: 02:30 gibt es zweimal, beide gehören noch zum Vortag
+        assert_eq!(
+            note(utc(10, 25, 0, 30)),
+            ("2026-10-24.md".into(), "#### 02:30".into())
+        );
+        assert_eq!(
+            note(utc(10, 25, 1, 30)),
+            ("2026-10-24.md".into(), "#### 02:30".into())
+        );
+        assert_eq!(
+            note(utc(10, 25, 2, 0)),
+            ("2026-10-25.md".into(), "#### 03:00".into())
+        );
+
+        let mut other_day = settings.clone();
+        let yesterday =
+            at_daily_note_date(&mut other_day, "2026-10-23", &utc(10, 25, 0, 30)).unwrap();
+        assert_eq!(
+            build_daily_note_path(&other_day, &yesterday),
+            "2026-10-23.md"
+        );
+        assert_eq!(
+            generate_header_with_time(&other_day.entry_header, yesterday),
+            "#### 02:30"
+        );
+    }
+
     #[test]
     fn test_build_daily_note_path_no_trailing_slash() {
         let settings = Settings {
@@ -411,7 +1001,7 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_daily_note_path(&settings);
+        let path = build_daily_note_path(&settings, &Local::now());
         assert!(path.starts_with("Tagebuch/"));
         assert!(path.ends_with(".md"));
     }
@@ -424,11 +1014,47 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_daily_note_path(&settings);
+        let path = build_daily_note_path(&settings, &Local::now());
         assert!(path.ends_with(".md"));
         assert!(!path.starts_with("/"));
     }
 
+    #[test]
+    fn test_build_periodic_note_paths() {
+        use chrono::TimeZone;
+        // Sonntag, 31. Dezember 2023: gehört schon zur Woche 1 von 2024
+        let dt = chrono::Utc.with_ymd_and_hms(2023, 12, 31, 9, 0, 0).unwrap();
+        let settings = Settings {
+            weekly_note: crate::settings::PeriodicNote {
+                folder: "Reviews/gggg".to_string(),
+                format: "gggg-[W]ww".to_string(),
+                template: String::new(),
+                entry_style: EntryStyle::Bullet,
+                heading: String::new(),
+                insert_position: InsertPosition::Append,
+            },
+            ..Default::default()
+        };
+
+        let paths: Vec<String> = Period::ALL
+            .iter()
+            .map(|period| {
+                let note = settings.periodic_note(*period);
+                build_dated_note_path(&note.folder, &note.format, &dt)
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "Reviews/2024/2024-W01.md",
+                "2023-12.md",
+                "2023-Q4.md",
+                "2023.md"
+            ]
+        );
+        assert!(build_periodic_note_path(Period::Year, &settings, &Local::now()).ends_with(".md"));
+    }
+
     #[test]
     fn test_build_note_relative_path_from_title() {
         let settings = Settings {
@@ -436,7 +1062,7 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_note_relative_path_from_title("My Title", &settings);
+        let path = build_note_relative_path_from_title("My Title", &settings, &Local::now());
         assert_eq!(path, "Notes/My Title.md");
     }
 
@@ -447,7 +1073,8 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_note_relative_path_from_title(r#"My:/\*?"<>| Title"#, &settings);
+        let path =
+            build_note_relative_path_from_title(r#"My:/\*?"<>| Title"#, &settings, &Local::now());
         assert_eq!(path, "Notes/My Title.md");
     }
 
@@ -459,11 +1086,474 @@ mod tests {
             ..Default::default()
         };
 
-        let path = build_note_relative_path_from_title("///", &settings);
+        let path = build_note_relative_path_from_title("///", &settings, &Local::now());
         assert!(path.ends_with(".md"));
         assert_ne!(path, ".md");
     }
 
+    fn temp_note(name: &str, content: &str) -> std::path::PathBuf {
+        let dir = crate::test_support::temp_dir("capture");
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_append_to_daily_note_under_heading() {
+        let path = temp_note("daily.md", "## Log\n- a\n\n## Inbox\n- idea\n");
+        let settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            daily_note_heading: "## Log".to_string(),
+            ..Default::default()
+        };
+
+        append_to_daily_note("hello", &path, &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Log\n- a\nEntry\nhello\n\n## Inbox\n- idea\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_append_to_periodic_note_under_heading_newest_first() {
+        let path = temp_note("week.md", "## Log\n- a\n\n## Review\n");
+        let mut settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            ..Default::default()
+        };
+        settings.weekly_note.heading = "## Log".to_string();
+        settings.weekly_note.insert_position = InsertPosition::Prepend;
+
+        append_to_periodic_note("hello", &path, Period::Week, &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Log\nEntry\nhello\n- a\n\n## Review\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_append_to_note_creates_missing_heading() {
+        let path = temp_note("note.md", "# Project\nintro");
+        let settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            ..Default::default()
+        };
+
+        let (_, anchor) = note_anchor("note.md", Some("## Inbox")).unwrap();
+        append_to_note("hello", &path, anchor.as_ref(), &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Project\nintro\n\n## Inbox\nEntry\nhello\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_append_to_note_at_block_reference() {
+        let path = temp_note("note.md", "# Project\n- Inbox ^inbox\n\t- older\n- Done\n");
+        let settings = Settings {
+            append_to_note_entry_style: EntryStyle::Task,
+            ..Default::default()
+        };
+
+        let (note, anchor) = note_anchor("Project#^inbox", None).unwrap();
+        assert_eq!(note, "Project.md");
+        append_to_note(
+            "call Bob\nabout X",
+            &path,
+            anchor.as_ref(),
+            &settings,
+            &Local::now(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Project\n- Inbox ^inbox\n\t- older\n\t- [ ] call Bob\n\t  about X\n- Done\n"
+        );
+
+        // Eintragsstile, die keine Listen sind, werden zu Aufzählungspunkten
+        let settings = Settings {
+            append_to_note_entry_style: EntryStyle::Callout,
+            entry_time_format: "[09:00]".to_string(),
+            ..Default::default()
+        };
+        append_to_note("new", &path, anchor.as_ref(), &settings, &Local::now()).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("\t  about X\n\t- 09:00 new\n- Done\n"));
+
+        let missing = Anchor::Block("nope".to_string());
+        assert!(append_to_note("x", &path, Some(&missing), &settings, &Local::now()).is_err());
+        assert!(note_anchor("Project.md#^inbox", Some("## Log")).is_err());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_append_to_note_without_heading_appends_at_end() {
+        let path = temp_note("note.md", "## Log\n- a\n\n## Inbox");
+        let settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            ..Default::default()
+        };
+
+        append_to_note("hello", &path, None, &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Log\n- a\n\n## Inbox\nEntry\nhello\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_save_note_merges_frontmatter_properties() {
+        use crate::settings::{NoteProperties, NoteProperty};
+        use chrono::TimeZone;
+
+        let dir = temp_note("unused.md", "").parent().unwrap().to_path_buf();
+        let path = dir.join("Idea.md");
+        let settings = Settings {
+            note_template: "---\ntags: inbox\n---".to_string(),
+            note_properties: NoteProperties {
+                created: true,
+                created_format: "YYYY-MM-DD".to_string(),
+                tags: vec!["#dev".to_string()],
+                aliases: vec!["{{title}}".to_string()],
+                custom: vec![NoteProperty {
+                    key: "type".to_string(),
+                    value: "capture: {{date}}".to_string(),
+                }],
+            },
+            ..Default::default()
+        };
+        let captured_at = Local.with_ymd_and_hms(2026, 10, 17, 9, 30, 0).unwrap();
+
+        let content = "---\nstatus: open\n---\nBody";
+        save_new_note(
+            content,
+            &path,
+            NoteTarget::Create(path.clone()),
+            &settings,
+            &captured_at,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\ntags: [inbox, dev]\nstatus: open\ncreated: 2026-10-17\naliases: [Idea]\n\
+             type: \"capture: 2026-10-17\"\n---\n\nBody"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_create_daily_note_from_template() {
+        let dir = temp_note("unused.md", "").parent().unwrap().to_path_buf();
+        let path = dir.join("Journal/2024/2024-03-15.md");
+
+        let created =
+            create_note_from_template(&path, Some("# {{title}}\n\n## Log\n"), &Local::now())
+                .unwrap();
+        assert!(created);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# 2024-03-15\n\n## Log\n"
+        );
+
+        // Bestehende Note bleibt unverändert
+        assert!(!create_note_from_template(&path, Some("other"), &Local::now()).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# 2024-03-15\n\n## Log\n"
+        );
+
+        let empty = dir.join("2024-03-16.md");
+        assert!(create_note_from_template(&empty, None, &Local::now()).unwrap());
+        append_to_daily_note(
+            "hello",
+            &empty,
+            &Settings {
+                entry_header: "[Entry]".to_string(),
+                ..Default::default()
+            },
+            &Local::now(),
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&empty).unwrap(), "Entry\nhello\n");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_note_collision_policies() {
+        let path = temp_note("note-2024-03-15-1405.md", "first\n");
+        let dir = path.parent().unwrap().to_path_buf();
+
+        let target = resolve_note_collision(&path, CollisionPolicy::Suffix).unwrap();
+        assert_eq!(
+            target,
+            NoteTarget::Create(dir.join("note-2024-03-15-1405 1.md"))
+        );
+        fs::write(dir.join("note-2024-03-15-1405 1.md"), "").unwrap();
+        assert_eq!(
+            resolve_note_collision(&path, CollisionPolicy::Suffix).unwrap(),
+            NoteTarget::Create(dir.join("note-2024-03-15-1405 2.md"))
+        );
+
+        assert!(resolve_note_collision(&path, CollisionPolicy::Error).is_err());
+        assert_eq!(
+            resolve_note_collision(&path, CollisionPolicy::Append).unwrap(),
+            NoteTarget::Append(path.clone())
+        );
+
+        let fresh = dir.join("My Title.md");
+        assert_eq!(
+            resolve_note_collision(&fresh, CollisionPolicy::Error).unwrap(),
+            NoteTarget::Create(fresh)
+        );
+
+        append_note_content("second", &path, "note.md").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\n\nsecond\n");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_save_new_note_moves_on_when_target_was_taken() {
+        let path = temp_note("Idea.md", "first\n");
+        let dir = path.parent().unwrap().to_path_buf();
+        let mut settings = Settings {
+            note_template: String::new(),
+            note_collision_policy: CollisionPolicy::Suffix,
+            ..Default::default()
+        };
+
+        // Zwischen Auflösen und Schreiben legt jemand "Idea 1.md" an
+        let target = resolve_note_collision(&path, CollisionPolicy::Suffix).unwrap();
+        fs::write(dir.join("Idea 1.md"), "other\n").unwrap();
+        let result = save_new_note("second", &path, target, &settings, &Local::now()).unwrap();
+
+        assert_eq!(result.message, "Note saved: Idea 2.md");
+        assert_eq!(
+            fs::read_to_string(dir.join("Idea 1.md")).unwrap(),
+            "other\n"
+        );
+        assert!(fs::read_to_string(dir.join("Idea 2.md"))
+            .unwrap()
+            .contains("second"));
+
+        settings.note_collision_policy = CollisionPolicy::Error;
+        let taken = NoteTarget::Create(dir.join("Idea 1.md"));
+        assert_eq!(
+            save_new_note("third", &path, taken, &settings, &Local::now()).unwrap_err(),
+            "Note already exists: Idea 1.md"
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_undo_restores_note_after_capture() {
+        let original = "## Log\n- a\n\n## Inbox\n- idea";
+        let path = temp_note("daily.md", original);
+        let settings = Settings {
+            daily_note_heading: "## Tasks".to_string(),
+            daily_note_entry_style: EntryStyle::Callout,
+            ..Default::default()
+        };
+
+        let written = append_to_daily_note("hello", &path, &settings, &Local::now()).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("## Tasks"));
+        crate::capture_undo::undo(&written).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        let result = append_note_content("second", &path, "daily.md").unwrap();
+        crate::capture_undo::undo(&result.written).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_format_entry_styles() {
+        use chrono::TimeZone;
+        let dt = chrono::Utc.with_ymd_and_hms(2024, 3, 15, 9, 5, 0).unwrap();
+        let settings = Settings {
+            entry_header: "#### HH:mm".to_string(),
+            ..Default::default()
+        };
+        let text = "first\nsecond\n\nthird";
+        let format = |style| format_entry_with_time(style, text, &settings, dt);
+
+        assert_eq!(
+            format(EntryStyle::Header),
+            "#### 09:05\nfirst\nsecond\n\nthird\n"
+        );
+        assert_eq!(
+            format(EntryStyle::Bullet),
+            "- 09:05 first\n  second\n\n  third\n"
+        );
+        assert_eq!(
+            format(EntryStyle::Task),
+            "- [ ] first\n  second\n\n  third\n"
+        );
+        assert_eq!(
+            format_entry_with_time(EntryStyle::Task, "call Bob tomorrow", &settings, dt),
+            "- [ ] call Bob tomorrow\n"
+        );
+        let tasks = Settings {
+            task_date_format: crate::settings::TaskDateFormat::Tasks,
+            ..settings.clone()
+        };
+        assert_eq!(
+            format_entry_with_time(EntryStyle::Task, "call Bob tomorrow", &tasks, dt),
+            "- [ ] call Bob 📅 2024-03-16\n"
+        );
+        assert_eq!(format(EntryStyle::Quote), "> first\n> second\n>\n> third\n");
+        assert_eq!(
+            format(EntryStyle::Callout),
+            "> [!note] 09:05\n> first\n> second\n>\n> third\n"
+        );
+    }
+
+    #[test]
+    fn test_append_callouts_stay_separate_blocks() {
+        let path = temp_note("daily.md", "## Log\n");
+        let settings = Settings {
+            entry_time_format: "[T]".to_string(),
+            daily_note_entry_style: EntryStyle::Callout,
+            ..Default::default()
+        };
+
+        append_to_daily_note("a", &path, &settings, &Local::now()).unwrap();
+        append_to_daily_note("b", &path, &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Log\n\n> [!note] T\n> a\n\n> [!note] T\n> b\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_insert_entry_prepend_after_frontmatter() {
+        let content = "---\ntags: daily\n---\n#### 08:00\nold\n";
+        let (result, range) = insert_entry(
+            content,
+            None,
+            InsertPosition::Prepend,
+            EntryStyle::Header,
+            "new\n",
+        );
+        assert_eq!(result, "---\ntags: daily\n---\nnew\n#### 08:00\nold\n");
+        assert_eq!(&result[range], "new\n");
+
+        let (result, _) = insert_entry(
+            "old\n",
+            None,
+            InsertPosition::Prepend,
+            EntryStyle::Header,
+            "new\n",
+        );
+        assert_eq!(result, "new\nold\n");
+
+        // Frontmatter ohne abschließenden Zeilenumbruch
+        let (result, _) = insert_entry(
+            "---\na: 1\n---",
+            None,
+            InsertPosition::Prepend,
+            EntryStyle::Header,
+            "new\n",
+        );
+        assert_eq!(result, "---\na: 1\n---\nnew\n");
+    }
+
+    #[test]
+    fn test_insert_entry_prepend_under_heading() {
+        let content = "# Day\n## Log\n- 08:00\n## Inbox\n";
+        let heading = HeadingSpec::parse("## Log").unwrap();
+        let (result, _) = insert_entry(
+            content,
+            Some(&heading),
+            InsertPosition::Prepend,
+            EntryStyle::Bullet,
+            "- 09:00\n",
+        );
+        assert_eq!(result, "# Day\n## Log\n- 09:00\n- 08:00\n## Inbox\n");
+
+        let missing = HeadingSpec::parse("## Tasks").unwrap();
+        let (result, _) = insert_entry(
+            content,
+            Some(&missing),
+            InsertPosition::Prepend,
+            EntryStyle::Task,
+            "- t\n",
+        );
+        assert!(result.ends_with("## Inbox\n\n## Tasks\n- t\n"));
+    }
+
+    #[test]
+    fn test_append_to_daily_note_prepend_keeps_crlf_terminated_frontmatter() {
+        let path = temp_note("daily.md", "---\r\ndate: x\r\n---\r\nold\r\n");
+        let settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            daily_note_insert_position: InsertPosition::Prepend,
+            ..Default::default()
+        };
+
+        append_to_daily_note("hello", &path, &settings, &Local::now()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\r\ndate: x\r\n---\r\nEntry\r\nhello\r\nold\r\n"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_append_keeps_line_endings_and_encoding() {
+        let crlf = temp_note("crlf.md", "## Log\r\n- a\r\n\r\n## Next\r\n");
+        let settings = Settings {
+            entry_header: "[Entry]".to_string(),
+            daily_note_heading: "## Log".to_string(),
+            ..Default::default()
+        };
+        append_to_daily_note("two\nlines", &crlf, &settings, &Local::now()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&crlf).unwrap(),
+            "## Log\r\n- a\r\nEntry\r\ntwo\r\nlines\r\n\r\n## Next\r\n"
+        );
+
+        let utf16 = crlf.with_file_name("utf16.md");
+        let note = note_encoding::NoteText {
+            content: String::new(),
+            encoding: note_encoding::Encoding::Utf16Le,
+            line_ending: note_encoding::LineEnding::CrLf,
+        };
+        fs::write(&utf16, note.encode("Grüße\r\n")).unwrap();
+        let written = append_note_content("neu", &utf16, "utf16.md")
+            .unwrap()
+            .written;
+        let bytes = fs::read(&utf16).unwrap();
+        let decoded = note_encoding::NoteText::decode(&bytes).unwrap();
+        assert_eq!(decoded.encoding, note_encoding::Encoding::Utf16Le);
+        assert_eq!(decoded.content, "Grüße\r\n\r\nneu\r\n");
+        crate::capture_undo::undo(&written).unwrap();
+        assert_eq!(fs::read(&utf16).unwrap(), note.encode("Grüße\r\n"));
+
+        let latin1 = crlf.with_file_name("latin1.md");
+        fs::write(&latin1, b"Gr\xfc\xdfe\n").unwrap();
+        let error = append_to_daily_note("x", &latin1, &settings, &Local::now()).unwrap_err();
+        assert!(error.contains("not UTF-8"), "{}", error);
+        assert_eq!(fs::read(&latin1).unwrap(), b"Gr\xfc\xdfe\n");
+
+        let _ = fs::remove_dir_all(crlf.parent().unwrap());
+    }
+
     #[test]
     fn test_time_tokens_morning_0905() {
         use chrono::TimeZone;
@@ -0,0 +1,365 @@
+//! Local, append-only history of captures: when, where to, in which style and
+//! how long — and the text itself unless `capture_history_store_text` is off.
+//!
+//! Stored as JSON lines in the app data directory. Notes are recorded by their
+//! vault-relative path, never as absolute paths.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::atomic_write;
+use crate::log_safety::{redact_path_str, summarize_text_len};
+use crate::settings::{EntryStyle, Settings};
+
+const HISTORY_FILE: &str = "capture_history.jsonl";
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch.
+    pub captured_at_ms: i64,
+    /// Vault-relative path of the note the capture went to.
+    pub note: String,
+    /// `None` for Save as Note, which writes the text as is.
+    pub entry_style: Option<EntryStyle>,
+    pub chars: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl HistoryEntry {
+    fn matches(&self, terms: &[String], since_ms: Option<i64>, until_ms: Option<i64>) -> bool {
+        if since_ms.is_some_and(|since| self.captured_at_ms < since)
+            || until_ms.is_some_and(|until| self.captured_at_ms >= until)
+        {
+            return false;
+        }
+
+        let note = self.note.to_lowercase();
+        let text = self.text.as_deref().unwrap_or_default().to_lowercase();
+        terms
+            .iter()
+            .all(|term| note.contains(term) || text.contains(term))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CaptureHistory {
+    path: Option<PathBuf>,
+    lock: Mutex<()>,
+}
+
+impl CaptureHistory {
+    pub fn history_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("collector").join(HISTORY_FILE))
+    }
+
+    pub fn load() -> Self {
+        match Self::history_path() {
+            Ok(path) => Self::open(path),
+            Err(e) => {
+                log::warn!("Capture history is disabled: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Adds one capture, honouring the history switches in `settings`.
+    pub fn record(
+        &self,
+        settings: &Settings,
+        note: &str,
+        entry_style: Option<EntryStyle>,
+        text: &str,
+        captured_at: &DateTime<Local>,
+    ) -> Result<(), String> {
+        let Some(path) = self
+            .path
+            .as_ref()
+            .filter(|_| settings.capture_history_enabled)
+        else {
+            return Ok(());
+        };
+
+        let entry = HistoryEntry {
+            captured_at_ms: captured_at.timestamp_millis(),
+            note: note.to_string(),
+            entry_style,
+            chars: summarize_text_len(text),
+            text: settings
+                .capture_history_store_text
+                .then(|| text.to_string()),
+        };
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write capture history: {}", e))?;
+
+        log::debug!(
+            "Capture recorded in history (note={})",
+            redact_path_str(note)
+        );
+        Ok(())
+    }
+
+    /// The newest entries first, skipping `offset` of them.
+    pub fn list(&self, offset: usize, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+
+    /// Newest entries whose note path or text contains every word of `query`
+    /// (case-insensitive), optionally limited to `since_ms..until_ms`.
+    pub fn search(
+        &self,
+        query: &str,
+        since_ms: Option<i64>,
+        until_ms: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter(|entry| entry.matches(&terms, since_ms, until_ms))
+            .take(limit)
+            .collect())
+    }
+
+    /// Drops entries past the retention period and, with text storage switched
+    /// off, the texts kept so far. Returns the number of removed entries.
+    pub fn prune(&self, settings: &Settings, now_ms: i64) -> Result<usize, String> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let entries = self.read_entries()?;
+        let before = entries.len();
+        let cutoff = (settings.capture_history_retention_days > 0)
+            .then(|| now_ms - i64::from(settings.capture_history_retention_days) * MS_PER_DAY);
+
+        let kept: Vec<HistoryEntry> = entries
+            .iter()
+            .filter(|entry| cutoff.is_none_or(|cutoff| entry.captured_at_ms >= cutoff))
+            .cloned()
+            .map(|mut entry| {
+                if !settings.capture_history_store_text {
+                    entry.text = None;
+                }
+                entry
+            })
+            .collect();
+
+        if kept == entries {
+            return Ok(0);
+        }
+
+        let mut content = String::new();
+        for entry in &kept {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        atomic_write::write_file(path, content.as_bytes())
+            .map_err(|e| format!("Failed to rewrite capture history: {}", e))?;
+
+        Ok(before - kept.len())
+    }
+
+    fn entries(&self) -> Result<Vec<HistoryEntry>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.read_entries()
+    }
+
+    /// Oldest first. Unreadable lines (e.g. a write cut off by a crash) are skipped.
+    fn read_entries(&self) -> Result<Vec<HistoryEntry>, String> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read capture history: {}", e)),
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_history() -> CaptureHistory {
+        let dir = crate::test_support::temp_dir("history");
+        CaptureHistory::open(dir.join(HISTORY_FILE))
+    }
+
+    fn cleanup(history: &CaptureHistory) {
+        let _ = fs::remove_dir_all(history.path.as_ref().unwrap().parent().unwrap());
+    }
+
+    fn day(d: u32, h: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn lists_newest_first_and_searches_text_and_note() {
+        let history = temp_history();
+        let settings = Settings::default();
+        history
+            .record(
+                &settings,
+                "Journal/2024-03-12.md",
+                Some(EntryStyle::Bullet),
+                "Buy the Kestrel router",
+                &day(12, 9),
+            )
+            .unwrap();
+        history
+            .record(
+                &settings,
+                "Notes/Router.md",
+                None,
+                "firmware notes",
+                &day(13, 9),
+            )
+            .unwrap();
+
+        let listed = history.list(0, 10).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].note, "Notes/Router.md");
+        assert_eq!(listed[1].chars, 22);
+        assert_eq!(history.list(1, 10).unwrap(), listed[1..]);
+
+        let found = history.search("kestrel ROUTER", None, None, 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].note, "Journal/2024-03-12.md");
+
+        // "router" steckt in beiden, der Zeitraum grenzt auf Dienstag ein
+        let tuesday = history
+            .search(
+                "router",
+                Some(day(12, 0).timestamp_millis()),
+                Some(day(13, 0).timestamp_millis()),
+                10,
+            )
+            .unwrap();
+        assert_eq!(tuesday, found);
+
+        cleanup(&history);
+    }
+
+    #[test]
+    fn respects_history_switches() {
+        let history = temp_history();
+        let no_text = Settings {
+            capture_history_store_text: false,
+            ..Default::default()
+        };
+        history
+            .record(&no_text, "Inbox.md", None, "secret", &day(12, 9))
+            .unwrap();
+
+        let disabled = Settings {
+            capture_history_enabled: false,
+            ..Default::default()
+        };
+        history
+            .record(&disabled, "Inbox.md", None, "ignored", &day(12, 10))
+            .unwrap();
+
+        let entries = history.list(0, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, None);
+        assert_eq!(entries[0].chars, 6);
+        assert!(history.search("secret", None, None, 10).unwrap().is_empty());
+
+        cleanup(&history);
+    }
+
+    #[test]
+    fn prune_applies_retention_and_strips_text() {
+        let history = temp_history();
+        let settings = Settings {
+            capture_history_retention_days: 7,
+            ..Default::default()
+        };
+        history
+            .record(&settings, "Old.md", None, "old", &day(1, 9))
+            .unwrap();
+        history
+            .record(&settings, "New.md", None, "new", &day(14, 9))
+            .unwrap();
+
+        let now = day(15, 9).timestamp_millis();
+        assert_eq!(history.prune(&settings, now).unwrap(), 1);
+        assert_eq!(history.prune(&settings, now).unwrap(), 0);
+
+        let no_text = Settings {
+            capture_history_store_text: false,
+            ..settings
+        };
+        history.prune(&no_text, now).unwrap();
+        let entries = history.list(0, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].note, "New.md");
+        assert_eq!(entries[0].text, None);
+
+        cleanup(&history);
+    }
+
+    #[test]
+    fn skips_torn_lines() {
+        let history = temp_history();
+        history
+            .record(&Settings::default(), "A.md", None, "a", &day(12, 9))
+            .unwrap();
+        let path = history.path.clone().unwrap();
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("{\"captured_at_ms\":17");
+        fs::write(&path, content).unwrap();
+
+        assert_eq!(history.list(0, 10).unwrap().len(), 1);
+
+        cleanup(&history);
+    }
+}
@@ -0,0 +1,533 @@
+//! Captures that could not be written because the vault was unreachable
+//! (unmounted network share, iCloud Drive not available yet).
+//!
+//! They are kept in `capture_queue.json` next to the config file and replayed
+//! later with their original capture time, so an entry still lands in the
+//! daily note of the day it was captured, with that time in its header.
+
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write;
+use crate::duplicates::Duplicate;
+use crate::log_safety::redact_path;
+use crate::settings::{EntryStyle, Period, Settings};
+use crate::source_app::SourceApp;
+
+const QUEUE_FILE: &str = "capture_queue.json";
+
+/// Where a queued capture is written once the vault is back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureTarget {
+    DailyNote {
+        /// `YYYY-MM-DD` of another day's note; without it the note of the
+        /// capture time.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        date: Option<String>,
+    },
+    PeriodicNote {
+        period: Period,
+    },
+    Note {
+        path: String,
+        heading: Option<String>,
+    },
+    NewNote {
+        title: Option<String>,
+    },
+    /// Sent on by a capture rule, written with the rule's entry style and,
+    /// for the daily note, its heading.
+    Routed {
+        target: Box<CaptureTarget>,
+        #[serde(default)]
+        heading: Option<String>,
+        #[serde(default)]
+        entry_style: Option<EntryStyle>,
+    },
+}
+
+impl CaptureTarget {
+    /// The target with a daily note, also one chosen by a capture rule,
+    /// replaced by the note of `date`. Other targets have no date, so the
+    /// capture is refused instead of silently written elsewhere.
+    pub fn on_date(self, date: &str) -> Result<Self, String> {
+        match self {
+            CaptureTarget::DailyNote { .. } => Ok(CaptureTarget::DailyNote {
+                date: Some(date.to_string()),
+            }),
+            CaptureTarget::Routed {
+                target,
+                heading,
+                entry_style,
+            } => Ok(CaptureTarget::Routed {
+                target: Box::new(target.on_date(date)?),
+                heading,
+                entry_style,
+            }),
+            _ => Err(format!(
+                "Can't capture for {}: a capture rule sends this text to a note without a date",
+                date
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedCapture {
+    pub id: u64,
+    pub target: CaptureTarget,
+    pub text: String,
+    /// Milliseconds since the Unix epoch.
+    pub captured_at_ms: i64,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: String,
+    /// Writing failed although the vault was reachable (e.g. an invalid path),
+    /// so only a manual retry tries again.
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceApp>,
+}
+
+impl QueuedCapture {
+    pub fn captured_at(&self) -> DateTime<Local> {
+        Local
+            .timestamp_millis_opt(self.captured_at_ms)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+}
+
+/// What a capture command did with the text.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Delivery {
+    /// `duplicate` is set when the duplicate action is `warn`; `note` is
+    /// the file name of a note created by Save as Note.
+    Written {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duplicate: Option<Duplicate>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+    Queued {
+        pending: usize,
+    },
+    /// Not written, the same text went to the same target just before.
+    Skipped {
+        duplicate: Duplicate,
+    },
+}
+
+/// What happened when a queued capture was written again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replayed {
+    Delivered,
+    /// The vault was gone again; retried later.
+    Offline(String),
+    /// Failed with the vault reachable; kept, but not retried automatically.
+    Failed(String),
+}
+
+/// The persisted queue. Without a config directory it only lives in memory.
+#[derive(Debug, Default)]
+pub struct CaptureQueue {
+    path: Option<PathBuf>,
+    items: Vec<QueuedCapture>,
+    /// Never reused, so a stale id from the UI cannot remove another capture.
+    next_id: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueFile {
+    next_id: u64,
+    items: Vec<QueuedCapture>,
+}
+
+impl CaptureQueue {
+    pub fn queue_path() -> Result<PathBuf, String> {
+        Ok(Settings::config_path()?.with_file_name(QUEUE_FILE))
+    }
+
+    pub fn load() -> Self {
+        match Self::queue_path() {
+            Ok(path) => Self::open(path),
+            Err(e) => {
+                log::warn!("Capture queue is not persisted: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Opens the queue file. An unreadable file is moved aside instead of
+    /// being overwritten, so queued captures are never dropped silently.
+    pub fn open(path: PathBuf) -> Self {
+        let file = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                let backup = path.with_extension("json.corrupt");
+                log::error!(
+                    "Capture queue unreadable, moved to {}: {}",
+                    redact_path(&backup),
+                    e
+                );
+                if let Err(e) = fs::rename(&path, &backup) {
+                    log::warn!("Failed to move capture queue aside: {}", e);
+                }
+                QueueFile::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => QueueFile::default(),
+            Err(e) => {
+                log::error!("Failed to read capture queue: {}", e);
+                QueueFile::default()
+            }
+        };
+
+        if !file.items.is_empty() {
+            log::info!("{} queued captures waiting for the vault", file.items.len());
+        }
+
+        let max_id = file.items.iter().map(|item| item.id).max().unwrap_or(0);
+        Self {
+            path: Some(path),
+            items: file.items,
+            next_id: file.next_id.max(max_id + 1),
+        }
+    }
+
+    pub fn items(&self) -> &[QueuedCapture] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Queues a capture that failed with `error`. Returns the number of pending captures.
+    pub fn push(
+        &mut self,
+        target: CaptureTarget,
+        text: &str,
+        captured_at: &DateTime<Local>,
+        source: Option<&SourceApp>,
+        error: &str,
+    ) -> Result<usize, String> {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        self.items.push(QueuedCapture {
+            id,
+            target,
+            text: text.to_string(),
+            captured_at_ms: captured_at.timestamp_millis(),
+            attempts: 1,
+            last_error: error.to_string(),
+            failed: false,
+            source: source.cloned(),
+        });
+
+        if let Err(e) = self.save() {
+            self.items.pop();
+            return Err(e);
+        }
+        Ok(self.items.len())
+    }
+
+    /// Drops a queued capture without writing it. Returns `false` if `id` is unknown.
+    pub fn remove(&mut self, id: u64) -> Result<bool, String> {
+        let before = self.items.len();
+        self.items.retain(|item| item.id != id);
+        if self.items.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// The captures to write again, oldest first. Failed ones only on `retry_failed`.
+    pub fn due(&self, retry_failed: bool) -> Vec<QueuedCapture> {
+        self.items
+            .iter()
+            .filter(|item| retry_failed || !item.failed)
+            .cloned()
+            .collect()
+    }
+
+    /// Applies the outcomes of writing `due` captures: delivered ones are
+    /// removed, the others keep their place with the new error. Ids that were
+    /// discarded in the meantime are ignored. Returns the number delivered.
+    pub fn record(&mut self, outcomes: &[(u64, Replayed)]) -> Result<usize, String> {
+        let before = self.items.len();
+
+        self.items.retain_mut(|item| {
+            let Some((_, outcome)) = outcomes.iter().find(|(id, _)| *id == item.id) else {
+                return true;
+            };
+            match outcome {
+                Replayed::Delivered => {
+                    log::info!("Delivered queued capture (id={})", item.id);
+                    return false;
+                }
+                Replayed::Offline(error) => {
+                    item.last_error = error.clone();
+                    item.failed = false;
+                }
+                Replayed::Failed(error) => {
+                    log::warn!("Queued capture failed, not retrying (id={})", item.id);
+                    item.last_error = error.clone();
+                    item.failed = true;
+                }
+            }
+            item.attempts += 1;
+            true
+        });
+
+        self.save()?;
+        Ok(before - self.items.len())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let file = QueueFile {
+            next_id: self.next_id,
+            items: self.items.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize capture queue: {}", e))?;
+        atomic_write::write_file(path, json.as_bytes())
+            .map_err(|e| format!("Failed to write capture queue: {}", e))
+    }
+}
+
+/// True if the vault folder itself is gone, e.g. an unmounted share.
+/// Other failures (invalid settings, a bad heading) are not worth queueing.
+pub fn vault_unreachable(vault_path: &str) -> bool {
+    !vault_path.trim().is_empty() && !Path::new(vault_path).is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_queue_path() -> PathBuf {
+        crate::test_support::temp_dir("queue").join(QUEUE_FILE)
+    }
+
+    #[test]
+    fn queued_captures_survive_a_restart() {
+        let path = temp_queue_path();
+        let captured_at = Local.with_ymd_and_hms(2024, 3, 15, 14, 5, 0).unwrap();
+
+        let mut queue = CaptureQueue::open(path.clone());
+        let pending = queue
+            .push(
+                CaptureTarget::Note {
+                    path: "Projects/Alpha.md".to_string(),
+                    heading: Some("## Log".to_string()),
+                },
+                "idea",
+                &captured_at,
+                Some(&SourceApp {
+                    app: "Safari".to_string(),
+                    window: String::new(),
+                }),
+                "Vault not found",
+            )
+            .unwrap();
+        assert_eq!(pending, 1);
+
+        let reopened = CaptureQueue::open(path.clone());
+        assert_eq!(reopened.items(), queue.items());
+        assert_eq!(reopened.items()[0].captured_at(), captured_at);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn replay_removes_delivered_and_keeps_failed_captures() {
+        let path = temp_queue_path();
+        let now = Local::now();
+        let mut queue = CaptureQueue::open(path.clone());
+        queue
+            .push(
+                CaptureTarget::DailyNote { date: None },
+                "first",
+                &now,
+                None,
+                "offline",
+            )
+            .unwrap();
+        queue
+            .push(
+                CaptureTarget::PeriodicNote {
+                    period: Period::Week,
+                },
+                "second",
+                &now,
+                None,
+                "offline",
+            )
+            .unwrap();
+        queue
+            .push(
+                CaptureTarget::NewNote { title: None },
+                "third",
+                &now,
+                None,
+                "offline",
+            )
+            .unwrap();
+
+        let due = queue.due(false);
+        let seen: Vec<&str> = due.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(seen, vec!["first", "second", "third"]);
+
+        let outcomes = vec![
+            (due[0].id, Replayed::Delivered),
+            (due[1].id, Replayed::Offline("still offline".to_string())),
+            (due[2].id, Replayed::Failed("invalid path".to_string())),
+        ];
+        assert_eq!(queue.record(&outcomes).unwrap(), 1);
+
+        let mut remaining = CaptureQueue::open(path.clone());
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining.items()[0].text, "second");
+        assert_eq!(remaining.items()[0].attempts, 2);
+        assert_eq!(remaining.items()[0].last_error, "still offline");
+        assert!(remaining.items()[1].failed);
+
+        // Endgültig gescheiterte nur bei manuellem Wiederholen
+        let due: Vec<String> = remaining.due(false).into_iter().map(|i| i.text).collect();
+        assert_eq!(due, vec!["second"]);
+        assert_eq!(remaining.due(true).len(), 2);
+
+        // Inzwischen verworfene Einträge bleiben verworfen
+        assert!(remaining.remove(3).unwrap());
+        assert_eq!(remaining.record(&[(3, Replayed::Delivered)]).unwrap(), 0);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    fn due_id(queue: &CaptureQueue, text: &str) -> u64 {
+        queue
+            .items()
+            .iter()
+            .find(|item| item.text == text)
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let path = temp_queue_path();
+        let now = Local::now();
+        let mut queue = CaptureQueue::open(path.clone());
+        let target = CaptureTarget::DailyNote { date: None };
+        queue
+            .push(target.clone(), "a", &now, None, "offline")
+            .unwrap();
+        queue
+            .push(target.clone(), "b", &now, None, "offline")
+            .unwrap();
+        assert!(queue.remove(2).unwrap());
+        queue
+            .push(target.clone(), "c", &now, None, "offline")
+            .unwrap();
+        assert_eq!(due_id(&queue, "c"), 3);
+
+        let mut reopened = CaptureQueue::open(path.clone());
+        assert!(reopened.remove(3).unwrap());
+        reopened.push(target, "d", &now, None, "offline").unwrap();
+        assert_eq!(due_id(&reopened, "d"), 4);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn daily_note_target_with_date() {
+        let today: CaptureTarget = serde_json::from_str(r#"{"kind":"daily_note"}"#).unwrap();
+        assert_eq!(today, CaptureTarget::DailyNote { date: None });
+
+        let routed = CaptureTarget::Routed {
+            target: Box::new(today),
+            heading: Some("## Log".to_string()),
+            entry_style: None,
+        };
+        let Ok(CaptureTarget::Routed { target, .. }) = routed.on_date("2026-10-16") else {
+            panic!("still routed");
+        };
+        assert_eq!(
+            serde_json::to_string(&target).unwrap(),
+            r#"{"kind":"daily_note","date":"2026-10-16"}"#
+        );
+
+        let note = CaptureTarget::Routed {
+            target: Box::new(CaptureTarget::Note {
+                path: "Ideas.md".to_string(),
+                heading: None,
+            }),
+            heading: None,
+            entry_style: None,
+        };
+        assert!(note.on_date("2026-10-16").is_err());
+        let weekly = CaptureTarget::PeriodicNote {
+            period: Period::Week,
+        };
+        assert!(weekly.on_date("2026-10-16").is_err());
+    }
+
+    #[test]
+    fn unreadable_queue_file_is_moved_aside() {
+        let path = temp_queue_path();
+        fs::write(&path, "{not json").unwrap();
+
+        let mut queue = CaptureQueue::open(path.clone());
+        assert!(queue.is_empty());
+        assert_eq!(
+            fs::read_to_string(path.with_extension("json.corrupt")).unwrap(),
+            "{not json"
+        );
+
+        queue
+            .push(
+                CaptureTarget::DailyNote { date: None },
+                "text",
+                &Local::now(),
+                None,
+                "offline",
+            )
+            .unwrap();
+        assert!(queue.remove(1).unwrap());
+        assert!(!queue.remove(1).unwrap());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn only_a_missing_vault_folder_counts_as_unreachable() {
+        let path = temp_queue_path();
+        let dir = path.parent().unwrap();
+
+        assert!(!vault_unreachable(""));
+        assert!(!vault_unreachable(&dir.to_string_lossy()));
+        assert!(vault_unreachable(&dir.join("unmounted").to_string_lossy()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,172 @@
+//! Undo for the most recent capture.
+//!
+//! Every capture reports exactly what it wrote. Undo removes those bytes (or
+//! the note it created) again, but only while the file is still byte for byte
+//! what the capture left behind — edits made since are never thrown away.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write;
+use crate::log_safety::redact_path;
+use crate::note_encoding;
+use crate::note_version::content_hash;
+
+/// What a capture wrote. Hashes are SHA-256 hex of the written bytes and of
+/// the whole file right after the capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WrittenCapture {
+    /// `len` bytes inserted at byte `offset` of an existing note.
+    Inserted {
+        path: PathBuf,
+        offset: usize,
+        len: usize,
+        hash: String,
+        file_hash: String,
+    },
+    /// A note that did not exist before the capture.
+    Created { path: PathBuf, file_hash: String },
+}
+
+impl WrittenCapture {
+    pub fn inserted(path: &Path, content: &str, range: std::ops::Range<usize>) -> Self {
+        WrittenCapture::Inserted {
+            path: path.to_path_buf(),
+            offset: range.start,
+            len: range.len(),
+            hash: content_hash(&content[range]),
+            file_hash: content_hash(content),
+        }
+    }
+
+    pub fn created(path: &Path, content: &str) -> Self {
+        WrittenCapture::Created {
+            path: path.to_path_buf(),
+            file_hash: content_hash(content),
+        }
+    }
+
+    /// Marks the note as created by the capture, so undo deletes it.
+    pub fn into_created(self) -> Self {
+        match self {
+            WrittenCapture::Inserted {
+                path, file_hash, ..
+            } => WrittenCapture::Created { path, file_hash },
+            created => created,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            WrittenCapture::Inserted { path, .. } | WrittenCapture::Created { path, .. } => path,
+        }
+    }
+}
+
+/// Reverts `written` if its file is unchanged since the capture.
+pub fn undo(written: &WrittenCapture) -> Result<String, String> {
+    let path = written.path();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let note = note_encoding::read(path).map_err(|e| format!("Cannot read {}: {}", name, e))?;
+    let content = &note.content;
+
+    let unchanged = match written {
+        WrittenCapture::Inserted { file_hash, .. } | WrittenCapture::Created { file_hash, .. } => {
+            content_hash(content) == *file_hash
+        }
+    };
+    if !unchanged {
+        return Err(format!(
+            "{} was changed after the capture, nothing was undone",
+            name
+        ));
+    }
+
+    match written {
+        WrittenCapture::Created { .. } => {
+            fs::remove_file(path).map_err(|e| format!("Cannot delete {}: {}", name, e))?;
+            log::info!("Undo: removed created note (file={})", redact_path(path));
+            Ok(format!("Removed {}", name))
+        }
+        WrittenCapture::Inserted {
+            offset, len, hash, ..
+        } => {
+            let range = *offset..offset + len;
+            if content.get(range.clone()).map(content_hash).as_ref() != Some(hash) {
+                return Err(format!("Capture not found in {}, nothing was undone", name));
+            }
+
+            let mut reverted = content.clone();
+            reverted.replace_range(range, "");
+            atomic_write::write_file(path, &note.encode(&reverted))
+                .map_err(|e| format!("Cannot write {}: {}", name, e))?;
+            log::info!("Undo: removed captured entry (file={})", redact_path(path));
+            Ok(format!("Removed capture from {}", name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        crate::test_support::temp_dir("undo")
+    }
+
+    #[test]
+    fn removes_inserted_entry() {
+        let dir = temp_dir();
+        let path = dir.join("daily.md");
+        let content = "## Log\n- 09:00 new\n## Inbox\n";
+        fs::write(&path, content).unwrap();
+
+        let written = WrittenCapture::inserted(&path, content, 7..19);
+        assert_eq!(undo(&written).unwrap(), "Removed capture from daily.md");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "## Log\n## Inbox\n");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn refuses_when_file_changed_since() {
+        let dir = temp_dir();
+        let path = dir.join("daily.md");
+        let content = "## Log\n- 09:00 new\n";
+        fs::write(&path, content).unwrap();
+        let written = WrittenCapture::inserted(&path, content, 7..19);
+
+        fs::write(&path, "## Log\n- 09:00 new\n- 09:05 typed in Obsidian\n").unwrap();
+
+        assert!(undo(&written)
+            .unwrap_err()
+            .contains("changed after the capture"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## Log\n- 09:00 new\n- 09:05 typed in Obsidian\n"
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn deletes_created_note_only_if_untouched() {
+        let dir = temp_dir();
+        let path = dir.join("Idea.md");
+        fs::write(&path, "idea\n").unwrap();
+        let written = WrittenCapture::created(&path, "idea\n");
+
+        fs::write(&path, "idea, refined\n").unwrap();
+        assert!(undo(&written).is_err());
+        assert!(path.exists());
+
+        fs::write(&path, "idea\n").unwrap();
+        assert_eq!(undo(&written).unwrap(), "Removed Idea.md");
+        assert!(!path.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,143 @@
+//! Detection of captures that were just written to the same target, e.g. by
+//! pressing the save shortcut twice.
+//!
+//! Only a hash of the normalized text is kept, in memory, for the most recent
+//! captures.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::capture_queue::CaptureTarget;
+use crate::note_version::content_hash;
+use crate::settings::{DuplicateAction, Settings};
+
+/// Captures remembered across all targets.
+const MAX_REMEMBERED: usize = 50;
+
+/// A recent capture the new one repeats.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Duplicate {
+    pub seconds_ago: u64,
+}
+
+#[derive(Debug)]
+struct RecentCapture {
+    target: CaptureTarget,
+    hash: String,
+    /// Milliseconds since the Unix epoch.
+    captured_at_ms: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct RecentCaptures {
+    entries: VecDeque<RecentCapture>,
+}
+
+impl RecentCaptures {
+    /// The newest of the last `duplicate_capture_entries` captures to `target`
+    /// that has the same text and is at most `duplicate_capture_window_secs` old.
+    /// Always `None` when duplicates are allowed.
+    pub fn find(
+        &self,
+        settings: &Settings,
+        target: &CaptureTarget,
+        text: &str,
+        now_ms: i64,
+    ) -> Option<Duplicate> {
+        if settings.duplicate_capture_action == DuplicateAction::Allow {
+            return None;
+        }
+
+        let hash = content_hash(&normalize(text));
+        let window_ms = i64::from(settings.duplicate_capture_window_secs) * 1000;
+
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.target == *target)
+            .take(settings.duplicate_capture_entries as usize)
+            .filter(|entry| now_ms - entry.captured_at_ms <= window_ms)
+            .find(|entry| entry.hash == hash)
+            .map(|entry| Duplicate {
+                seconds_ago: (now_ms - entry.captured_at_ms).max(0) as u64 / 1000,
+            })
+    }
+
+    pub fn record(&mut self, target: &CaptureTarget, text: &str, now_ms: i64) {
+        if self.entries.len() == MAX_REMEMBERED {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(RecentCapture {
+            target: target.clone(),
+            hash: content_hash(&normalize(text)),
+            captured_at_ms: now_ms,
+        });
+    }
+}
+
+/// Whitespace differences (trailing newline, double spaces) don't make a
+/// capture different.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Period;
+
+    #[test]
+    fn finds_repeated_text_within_window() {
+        let settings = Settings {
+            duplicate_capture_window_secs: 60,
+            duplicate_capture_entries: 2,
+            ..Settings::default()
+        };
+        let daily = CaptureTarget::DailyNote { date: None };
+        let weekly = CaptureTarget::PeriodicNote {
+            period: Period::Week,
+        };
+        let mut recent = RecentCaptures::default();
+        recent.record(&daily, "Call  Anna\n", 1_000);
+
+        assert_eq!(
+            recent.find(&settings, &daily, " Call Anna", 4_500),
+            Some(Duplicate { seconds_ago: 3 })
+        );
+        assert_eq!(recent.find(&settings, &daily, "call anna", 4_500), None);
+        assert_eq!(recent.find(&settings, &weekly, "Call Anna", 4_500), None);
+        assert_eq!(recent.find(&settings, &daily, "Call Anna", 62_000), None);
+        let allow = Settings {
+            duplicate_capture_action: DuplicateAction::Allow,
+            ..settings.clone()
+        };
+        assert_eq!(recent.find(&allow, &daily, "Call Anna", 4_500), None);
+
+        // Nur die letzten zwei Einträge dieses Ziels zählen
+        recent.record(&weekly, "other", 2_000);
+        recent.record(&daily, "second", 2_000);
+        assert!(recent.find(&settings, &daily, "Call Anna", 3_000).is_some());
+        recent.record(&daily, "third", 2_500);
+        assert_eq!(recent.find(&settings, &daily, "Call Anna", 3_000), None);
+    }
+
+    #[test]
+    fn forgets_oldest_captures() {
+        let settings = Settings::default();
+        let mut recent = RecentCaptures::default();
+        for i in 0..=MAX_REMEMBERED {
+            let target = CaptureTarget::Note {
+                path: format!("{}.md", i),
+                heading: None,
+            };
+            recent.record(&target, "text", 0);
+        }
+
+        let first = CaptureTarget::Note {
+            path: "0.md".to_string(),
+            heading: None,
+        };
+        assert_eq!(recent.entries.len(), MAX_REMEMBERED);
+        assert_eq!(recent.find(&settings, &first, "text", 0), None);
+    }
+}
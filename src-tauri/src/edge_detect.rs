@@ -1,10 +1,10 @@
-use std::process::Command;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::RwLock;
 
 use crate::settings::Settings;
+use crate::source_app::{SourceApp, SourceAppProvider};
 
 const WINDOW_CLOSE_COOLDOWN_MS: u64 = 500;
 const EDGE_POLL_INTERVAL_MS: u64 = 50;
@@ -217,12 +217,22 @@ impl EdgeDetector {
                         continue;
                     }
 
-                    if is_frontmost_app_excluded(&settings.edge_excluded_apps) {
+                    let frontmost = if settings.edge_excluded_apps.is_empty() {
+                        None
+                    } else {
+                        app.state::<crate::AppState>().source_app.frontmost()
+                    };
+
+                    if is_frontmost_app_excluded(&settings.edge_excluded_apps, frontmost.as_ref()) {
                         trigger_start = None;
                         trigger_target = None;
                         continue;
                     }
 
+                    if target == EdgeTarget::Capture {
+                        crate::remember_capture_source(&app).await;
+                    }
+
                     if let Err(error) = app.emit(target.event_name(), ()) {
                         log::warn!(
                             "Failed to emit edge event {}: {}",
@@ -273,34 +283,15 @@ fn modifiers_match(_required: &[String]) -> bool {
     true
 }
 
-fn is_frontmost_app_excluded(excluded_apps: &[String]) -> bool {
-    if excluded_apps.is_empty() {
-        return false;
-    }
-
-    let frontmost = match get_frontmost_app() {
-        Some(app) => app,
+fn is_frontmost_app_excluded(excluded_apps: &[String], frontmost: Option<&SourceApp>) -> bool {
+    let frontmost = match frontmost {
+        Some(source) => source.app.trim(),
         None => return false,
     };
 
     excluded_apps
         .iter()
-        .any(|excluded| excluded.trim().eq_ignore_ascii_case(&frontmost))
-}
-
-fn get_frontmost_app() -> Option<String> {
-    let output = Command::new("osascript")
-        .arg("-e")
-        .arg(r#"tell application "System Events" to get name of first process where frontmost is true"#)
-        .output()
-        .ok()?;
-
-    let app_name = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if app_name.is_empty() {
-        None
-    } else {
-        Some(app_name)
-    }
+        .any(|excluded| excluded.trim().eq_ignore_ascii_case(frontmost))
 }
 
 /// Get current mouse position using macOS Core Graphics
@@ -0,0 +1,221 @@
+//! One capture written to several targets at once, e.g. the daily note and a
+//! project note.
+//!
+//! Either every target gets the capture or none does: all targets are checked
+//! before the first write, and when a write fails, the targets written so far
+//! are undone again.
+
+use serde::Serialize;
+
+use crate::capture_queue::CaptureTarget;
+use crate::capture_undo::{self, WrittenCapture};
+
+/// What happened to one target of a multi-target capture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Written {
+        path: String,
+    },
+    /// Written, then removed again because another target failed.
+    RolledBack {
+        path: String,
+    },
+    /// Written, but could not be removed again after another target failed.
+    RollbackFailed {
+        path: String,
+        error: String,
+    },
+    Failed {
+        error: String,
+    },
+    /// Not written because another target failed.
+    NotWritten,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TargetResult {
+    pub target: CaptureTarget,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// Checks every target with `check`, then writes them in order with `write`.
+/// Nothing is written if any check fails; if a write fails, the targets
+/// written before it are undone, newest first.
+pub fn capture_all(
+    targets: &[CaptureTarget],
+    check: impl Fn(&CaptureTarget) -> Result<(), String>,
+    mut write: impl FnMut(&CaptureTarget) -> Result<WrittenCapture, String>,
+) -> Vec<TargetResult> {
+    let checks: Vec<Result<(), String>> = targets.iter().map(check).collect();
+    if checks.iter().any(Result::is_err) {
+        let outcomes = checks.into_iter().map(|check| match check {
+            Ok(()) => Outcome::NotWritten,
+            Err(error) => Outcome::Failed { error },
+        });
+        return results(targets, outcomes);
+    }
+
+    let mut written = Vec::new();
+    for target in targets {
+        match write(target) {
+            Ok(capture) => written.push(capture),
+            Err(error) => {
+                log::warn!(
+                    "Multi-target capture failed, undoing {} written targets",
+                    written.len()
+                );
+                let failed = Outcome::Failed { error };
+                let remaining = targets.len() - written.len() - 1;
+                // Rückwärts, damit zwei Einträge in derselben Notiz beide entfernt werden
+                let mut undone: Vec<Outcome> = written.iter().rev().map(roll_back).collect();
+                undone.reverse();
+                let outcomes = undone
+                    .into_iter()
+                    .chain([failed])
+                    .chain(std::iter::repeat_n(Outcome::NotWritten, remaining));
+                return results(targets, outcomes);
+            }
+        }
+    }
+
+    let outcomes = written.iter().map(|capture| Outcome::Written {
+        path: display_path(capture),
+    });
+    results(targets, outcomes)
+}
+
+fn roll_back(capture: &WrittenCapture) -> Outcome {
+    let path = display_path(capture);
+    match capture_undo::undo(capture) {
+        Ok(_) => Outcome::RolledBack { path },
+        Err(error) => Outcome::RollbackFailed { path, error },
+    }
+}
+
+fn display_path(capture: &WrittenCapture) -> String {
+    capture.path().to_string_lossy().to_string()
+}
+
+fn results(
+    targets: &[CaptureTarget],
+    outcomes: impl Iterator<Item = Outcome>,
+) -> Vec<TargetResult> {
+    targets
+        .iter()
+        .cloned()
+        .zip(outcomes)
+        .map(|(target, outcome)| TargetResult { target, outcome })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn temp_dir() -> PathBuf {
+        crate::test_support::temp_dir("fan-out")
+    }
+
+    fn note(path: &str) -> CaptureTarget {
+        CaptureTarget::Note {
+            path: path.to_string(),
+            heading: None,
+        }
+    }
+
+    /// Appends `- text` to the note, or creates it; fails for `Broken.md`.
+    fn append(dir: &Path, target: &CaptureTarget) -> Result<WrittenCapture, String> {
+        let CaptureTarget::Note { path, .. } = target else {
+            return Err("not a note".to_string());
+        };
+        if path == "Broken.md" {
+            return Err("disk full".to_string());
+        }
+        let file = dir.join(path);
+        let before = fs::read_to_string(&file).unwrap_or_default();
+        let content = format!("{}- text\n", before);
+        fs::write(&file, &content).unwrap();
+        Ok(if before.is_empty() {
+            WrittenCapture::created(&file, &content)
+        } else {
+            WrittenCapture::inserted(&file, &content, before.len()..content.len())
+        })
+    }
+
+    #[test]
+    fn failed_check_writes_nothing() {
+        let dir = temp_dir();
+        let targets = vec![note("Daily.md"), note("../Outside.md")];
+
+        let results = capture_all(
+            &targets,
+            |target| match target {
+                CaptureTarget::Note { path, .. } if path.starts_with("..") => {
+                    Err("outside the vault".to_string())
+                }
+                _ => Ok(()),
+            },
+            |target| append(&dir, target),
+        );
+
+        assert_eq!(results[0].outcome, Outcome::NotWritten);
+        assert_eq!(
+            results[1].outcome,
+            Outcome::Failed {
+                error: "outside the vault".to_string()
+            }
+        );
+        assert!(!dir.join("Daily.md").exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn failed_write_rolls_back_written_targets() {
+        let dir = temp_dir();
+        fs::write(dir.join("Daily.md"), "# Log\n").unwrap();
+        let targets = vec![
+            note("Daily.md"),
+            note("Project.md"),
+            note("Daily.md"),
+            note("Broken.md"),
+            note("Later.md"),
+        ];
+
+        let results = capture_all(&targets, |_| Ok(()), |target| append(&dir, target));
+
+        let statuses: Vec<&str> = results
+            .iter()
+            .map(|result| match result.outcome {
+                Outcome::RolledBack { .. } => "rolled_back",
+                Outcome::Failed { .. } => "failed",
+                Outcome::NotWritten => "not_written",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                "rolled_back",
+                "rolled_back",
+                "rolled_back",
+                "failed",
+                "not_written"
+            ]
+        );
+        assert_eq!(fs::read_to_string(dir.join("Daily.md")).unwrap(), "# Log\n");
+        assert!(!dir.join("Project.md").exists());
+        assert!(!dir.join("Later.md").exists());
+
+        let json = serde_json::to_value(&results[3]).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["error"], "disk full");
+        assert_eq!(json["target"]["path"], "Broken.md");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
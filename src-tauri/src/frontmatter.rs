@@ -0,0 +1,617 @@
+//! The YAML frontmatter at the top of a note or note template: small edits,
+//! and merging the properties of new notes. Only the touched keys are
+//! rewritten; everything else stays byte for byte.
+
+use chrono::{NaiveDate, NaiveTime};
+
+/// Splits `content` into the frontmatter lines (without the `---` fences) and
+/// the rest after the closing fence. `None` without frontmatter. Fences are
+/// whole `---` lines; `----` or `---foo` are not.
+fn split(content: &str) -> Option<(&str, &str)> {
+    let body = content.strip_prefix("---")?;
+    let body = body
+        .strip_prefix('\n')
+        .or_else(|| body.strip_prefix("\r\n"))?;
+
+    let mut start = 0;
+    for line in body.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == "---" {
+            return Some((&body[..start], &body[start + 3..]));
+        }
+        start += line.len();
+    }
+    None
+}
+
+/// The line ending of the frontmatter in `content`, `\r\n` or `\n`.
+fn newline(content: &str) -> &'static str {
+    if content.starts_with("---\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Adds `fields` to the frontmatter of `content`, creating it if there is none.
+/// Values are written as double-quoted YAML strings.
+pub fn add_fields(content: &str, fields: &[(&str, &str)]) -> String {
+    let newline = newline(content);
+    let lines: String = fields
+        .iter()
+        .map(|(key, value)| {
+            // JSON-Strings sind gültige YAML-Skalare in doppelten Anführungszeichen
+            let value = serde_json::to_string(value).unwrap_or_default();
+            format!("{}: {}{}", key, value, newline)
+        })
+        .collect();
+
+    match split(content) {
+        Some((front, rest)) => format!("---{}{}{}---{}", newline, front, lines, rest),
+        None if content.is_empty() => format!("---\n{}---", lines),
+        None => format!("---\n{}---\n{}", lines, content),
+    }
+}
+
+/// A property written into new notes by Save as Note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyValue {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// Properties whose values are merged instead of replaced.
+const LIST_KEYS: &[&str] = &["tags", "aliases", "cssclasses"];
+
+/// A top-level property with all of its lines (block list items, comments).
+#[derive(Debug, Clone)]
+struct Property {
+    key: String,
+    lines: Vec<String>,
+}
+
+impl Property {
+    fn new(key: &str, line: String) -> Self {
+        Self {
+            key: key.to_string(),
+            lines: vec![line],
+        }
+    }
+
+    fn inline_value(&self) -> &str {
+        self.lines[0]
+            .split_once(':')
+            .map(|(_, value)| value.trim())
+            .unwrap_or_default()
+    }
+
+    fn block_items(&self) -> usize {
+        self.lines[1..]
+            .iter()
+            .take_while(|line| line.trim_start().starts_with("- "))
+            .count()
+    }
+
+    fn is_list(&self) -> bool {
+        LIST_KEYS.contains(&self.key.as_str())
+            || self.inline_value().starts_with('[')
+            || (self.inline_value().is_empty() && self.block_items() > 0)
+    }
+
+    /// The values of a list property; a scalar counts as one value (for
+    /// `tags` also several, separated by spaces or commas).
+    fn values(&self) -> Vec<String> {
+        let value = self.inline_value();
+        if value.is_empty() {
+            return self.lines[1..=self.block_items()]
+                .iter()
+                .map(|line| clean_value(&self.key, line.trim_start().trim_start_matches("- ")))
+                .collect();
+        }
+
+        let items = match value.strip_prefix('[') {
+            Some(inner) => {
+                let inner = inner.strip_suffix(']').unwrap_or(inner);
+                let separators: &[char] = if self.key == "tags" {
+                    &[',', ' ']
+                } else {
+                    &[',']
+                };
+                split_unquoted(inner, separators)
+            }
+            None if self.key == "tags" => split_unquoted(value, &[',', ' ']),
+            None => vec![value],
+        };
+        items
+            .into_iter()
+            .map(|item| clean_value(&self.key, item))
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// Adds the `values` not yet there. Block lists get new items, anything
+    /// else becomes an inline list. Returns whether something was added.
+    fn merge(&mut self, values: &[String]) -> bool {
+        let mut existing = self.values();
+        let new: Vec<String> = values
+            .iter()
+            .map(|value| clean_value(&self.key, value))
+            .fold(Vec::new(), |mut new, value| {
+                let known = existing.iter().chain(new.iter());
+                if !value.is_empty() && !known.into_iter().any(|e| e.eq_ignore_ascii_case(&value)) {
+                    new.push(value);
+                }
+                new
+            });
+        if new.is_empty() {
+            return false;
+        }
+
+        let items = self.block_items();
+        if self.inline_value().is_empty() && items > 0 {
+            let first = &self.lines[1];
+            let indent = first[..first.len() - first.trim_start().len()].to_string();
+            let lines = new
+                .iter()
+                .map(|value| format!("{}- {}", indent, yaml_scalar(value)));
+            self.lines.splice(items + 1..items + 1, lines);
+        } else {
+            existing.extend(new);
+            self.lines = vec![list_line(&self.key, &existing)];
+        }
+        true
+    }
+}
+
+/// Splits frontmatter into its top-level properties. Lines before the first
+/// key (comments) are kept as a property without key.
+fn parse(front: &str) -> Vec<Property> {
+    let mut properties: Vec<Property> = Vec::new();
+    for line in front.lines() {
+        let top_level = !line.starts_with([' ', '\t', '-', '#']) && !line.trim().is_empty();
+        match line.split_once(':').filter(|_| top_level) {
+            Some((key, _)) => properties.push(Property::new(key.trim(), line.to_string())),
+            None => match properties.last_mut() {
+                Some(property) => property.lines.push(line.to_string()),
+                None => properties.push(Property::new("", line.to_string())),
+            },
+        }
+    }
+    properties
+}
+
+fn render(properties: &[Property], newline: &str) -> String {
+    let lines: String = properties
+        .iter()
+        .flat_map(|property| property.lines.iter())
+        .map(|line| format!("{}{}", line, newline))
+        .collect();
+    format!("---{}{}---", newline, lines)
+}
+
+fn find<'a>(properties: &'a mut [Property], key: &str) -> Option<&'a mut Property> {
+    properties.iter_mut().find(|property| property.key == key)
+}
+
+/// Adds `tags` (without `#`) to the `tags` property of `content`, skipping
+/// tags that are already there. Scalar and inline values become an inline
+/// list, block lists get new items.
+pub fn merge_tags(content: &str, tags: &[String]) -> String {
+    if tags.is_empty() {
+        return content.to_string();
+    }
+
+    let (mut properties, rest) = match split(content) {
+        Some((front, rest)) => (parse(front), rest.to_string()),
+        None => (Vec::new(), prefix_newline(content)),
+    };
+    match find(&mut properties, "tags") {
+        Some(property) => {
+            if !property.merge(tags) {
+                return content.to_string();
+            }
+        }
+        None => properties.push(Property::new("tags", list_line("tags", tags))),
+    }
+
+    format!("{}{}", render(&properties, newline(content)), rest)
+}
+
+/// The content of a new note: one frontmatter block with the properties of
+/// `template`, those typed at the top of `content` and `generated` ones.
+///
+/// Properties typed in the capture replace the template's, generated ones are
+/// only added where missing; `tags`, `aliases` and other lists are merged.
+/// Template keys keep their order, new keys follow in the order given.
+pub fn compose_note(template: &str, content: &str, generated: &[(&str, PropertyValue)]) -> String {
+    let (mut properties, template_rest) = match split(template) {
+        Some((front, rest)) => (parse(front), rest.to_string()),
+        None => (Vec::new(), prefix_newline(template)),
+    };
+    // Nur als Frontmatter lesen, wenn es wie Properties aussieht, nicht wie Trennlinien
+    let typed = split(content)
+        .map(|(front, rest)| (parse(front), rest))
+        .filter(|(typed, _)| typed.iter().all(|property| !property.key.is_empty()));
+    let (typed, body) = match typed {
+        Some((typed, rest)) => {
+            let body = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'));
+            (typed, body.unwrap_or(rest))
+        }
+        None => (Vec::new(), content),
+    };
+    let newline = match split(template) {
+        Some(_) => newline(template),
+        None => newline(content),
+    };
+
+    for property in typed {
+        match find(&mut properties, &property.key) {
+            Some(existing) if existing.is_list() || property.is_list() => {
+                existing.merge(&property.values());
+            }
+            Some(existing) => *existing = property,
+            None => properties.push(property),
+        }
+    }
+
+    for (key, value) in generated {
+        match (find(&mut properties, key), value) {
+            (Some(existing), PropertyValue::List(values)) => {
+                existing.merge(values);
+            }
+            (Some(_), PropertyValue::Text(_)) => {}
+            (None, PropertyValue::List(values)) if values.is_empty() => {}
+            (None, PropertyValue::List(values)) => {
+                properties.push(Property::new(key, list_line(key, values)))
+            }
+            (None, PropertyValue::Text(value)) => properties.push(Property::new(
+                key,
+                format!("{}: {}", key, yaml_scalar(value)),
+            )),
+        }
+    }
+
+    let head = if properties.is_empty() {
+        template.to_string()
+    } else {
+        format!("{}{}", render(&properties, newline), template_rest)
+    };
+    match (head.is_empty(), template.is_empty()) {
+        (true, _) => body.to_string(),
+        (false, true) => format!("{}{}{}", head, newline, body),
+        (false, false) => format!("{}{}{}{}", head, newline, newline, body),
+    }
+}
+
+fn list_line(key: &str, values: &[String]) -> String {
+    let items: Vec<String> = values.iter().map(|value| yaml_flow_scalar(value)).collect();
+    format!("{}: [{}]", key, items.join(", "))
+}
+
+/// `value` as a YAML scalar: plain where YAML reads it back unchanged (or as
+/// the number or boolean it spells), double-quoted otherwise.
+pub fn yaml_scalar(value: &str) -> String {
+    const INDICATORS: &[char] = &[
+        '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@',
+        '`',
+    ];
+    let reserved = matches!(
+        value.to_ascii_lowercase().as_str(),
+        "yes" | "no" | "on" | "off" | "y" | "n" | "null" | "~"
+    );
+    // YAML liest auch 0x1F, 1_000, 09:30 oder .inf als Zahl: nur einfache
+    // Dezimalzahlen und Daten bleiben ohne Anführungszeichen
+    let number_like = value.starts_with('+')
+        || value
+            .trim_start_matches('-')
+            .starts_with(|c: char| c.is_ascii_digit() || c == '.');
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && !reserved
+        && (is_plain_decimal(value)
+            || is_date(value)
+            || (!number_like && !value.starts_with(INDICATORS)))
+        && !value.contains(": ")
+        && !value.contains(" #")
+        && !value.ends_with(':')
+        && !value.contains(|c: char| c.is_control());
+
+    if plain {
+        value.to_string()
+    } else {
+        // JSON-Strings sind gültige YAML-Skalare in doppelten Anführungszeichen
+        serde_json::to_string(value).unwrap_or_default()
+    }
+}
+
+/// `0`, `-12` or `3.5`: a number YAML reads back exactly as written.
+fn is_plain_decimal(value: &str) -> bool {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+
+    digits(integer) && (integer == "0" || !integer.starts_with('0')) && fraction.is_none_or(digits)
+}
+
+/// `2026-10-17` or `2026-10-17T09:30`, which Obsidian shows as a date.
+fn is_date(value: &str) -> bool {
+    let date = value.get(..10).unwrap_or_default();
+    let time = &value[date.len()..];
+    date.bytes().enumerate().all(|(i, b)| {
+        if i == 4 || i == 7 {
+            b == b'-'
+        } else {
+            b.is_ascii_digit()
+        }
+    }) && NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+        && (time.is_empty()
+            || NaiveTime::parse_from_str(time, "T%H:%M").is_ok()
+            || NaiveTime::parse_from_str(time, "T%H:%M:%S").is_ok())
+}
+
+/// Like `yaml_scalar`, for an item of an inline `[a, b]` list.
+fn yaml_flow_scalar(value: &str) -> String {
+    if value.contains([',', '[', ']', '{', '}']) {
+        serde_json::to_string(value).unwrap_or_default()
+    } else {
+        yaml_scalar(value)
+    }
+}
+
+/// Splits `value` at `separators` outside of quoted items, so `"Smith, John"`
+/// stays one item.
+fn split_unquoted<'a>(value: &'a str, separators: &[char]) -> Vec<&'a str> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if separators.contains(&c) => {
+                items.push(&value[start..index]);
+                start = index + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+    items.push(&value[start..]);
+    items
+}
+
+fn prefix_newline(content: &str) -> String {
+    if content.is_empty() {
+        String::new()
+    } else {
+        format!("\n{}", content)
+    }
+}
+
+fn clean_value(key: &str, value: &str) -> String {
+    let value = unquote(value.trim());
+    if key == "tags" {
+        value.trim_start_matches('#').to_string()
+    } else {
+        value
+    }
+}
+
+/// The text of a quoted YAML scalar; other values are returned as they are.
+fn unquote(value: &str) -> String {
+    let quoted =
+        |quote: char| value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote);
+    if quoted('"') {
+        // JSON-Strings sind gültige YAML-Skalare in doppelten Anführungszeichen
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else if quoted('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn adds_fields_to_existing_or_new_frontmatter() {
+        assert_eq!(
+            add_fields("---\ntags: inbox\n---\nBody", &[("source", "Safari")]),
+            "---\ntags: inbox\nsource: \"Safari\"\n---\nBody"
+        );
+        assert_eq!(
+            add_fields("", &[("source", "Mail")]),
+            "---\nsource: \"Mail\"\n---"
+        );
+        assert_eq!(
+            add_fields("Body", &[("source", "Mail")]),
+            "---\nsource: \"Mail\"\n---\nBody"
+        );
+    }
+
+    #[test]
+    fn merges_tags_into_scalar_and_inline_values() {
+        assert_eq!(
+            merge_tags(
+                "---\ntags: inbox\ntype: inbox\n---",
+                &tags(&["dev", "Inbox"])
+            ),
+            "---\ntags: [inbox, dev]\ntype: inbox\n---"
+        );
+        assert_eq!(
+            merge_tags("---\ntags: [\"#a\", b]\n---\nText", &tags(&["b"])),
+            "---\ntags: [\"#a\", b]\n---\nText"
+        );
+    }
+
+    #[test]
+    fn merges_tags_into_block_lists() {
+        assert_eq!(
+            merge_tags(
+                "---\ntags:\n  - inbox\ncreated: today\n---\n",
+                &tags(&["dev"])
+            ),
+            "---\ntags:\n  - inbox\n  - dev\ncreated: today\n---\n"
+        );
+    }
+
+    #[test]
+    fn creates_tags_property_when_missing() {
+        assert_eq!(
+            merge_tags("---\ntype: inbox\n---", &tags(&["dev"])),
+            "---\ntype: inbox\ntags: [dev]\n---"
+        );
+        assert_eq!(merge_tags("", &tags(&["dev"])), "---\ntags: [dev]\n---");
+        assert_eq!(
+            merge_tags("# Title", &tags(&["a", "b"])),
+            "---\ntags: [a, b]\n---\n# Title"
+        );
+    }
+
+    #[test]
+    fn fences_are_whole_lines_with_any_line_ending() {
+        assert_eq!(
+            merge_tags("---\r\ntags: inbox\r\n---\r\nText", &tags(&["dev"])),
+            "---\r\ntags: [inbox, dev]\r\n---\r\nText"
+        );
+        assert_eq!(
+            add_fields("---\r\ntags: inbox\r\n---\r\nBody", &[("source", "Mail")]),
+            "---\r\ntags: inbox\r\nsource: \"Mail\"\r\n---\r\nBody"
+        );
+        assert_eq!(
+            compose_note("", "---\r\ntitle: x\r\n---\r\nBody", &[]),
+            "---\r\ntitle: x\r\n---\r\nBody"
+        );
+        // Trennlinien und "---foo" beenden die Frontmatter nicht
+        assert_eq!(
+            merge_tags("---\ntitle: x\n----\n---foo\n", &tags(&["dev"])),
+            "---\ntags: [dev]\n---\n---\ntitle: x\n----\n---foo\n"
+        );
+        assert_eq!(
+            merge_tags("---\ntitle: x\n----\n---\nText", &tags(&["dev"])),
+            "---\ntitle: x\n----\ntags: [dev]\n---\nText"
+        );
+    }
+
+    #[test]
+    fn quotes_yaml_scalars_only_where_needed() {
+        assert_eq!(yaml_scalar("Project X"), "Project X");
+        assert_eq!(yaml_scalar("2026-10-17T09:30"), "2026-10-17T09:30");
+        assert_eq!(yaml_scalar("2026-10-17"), "2026-10-17");
+        assert_eq!(yaml_scalar("0"), "0");
+        for value in [
+            "0x1F",
+            "-0123",
+            "1_000",
+            "09:30",
+            "1e3",
+            ".inf",
+            "+1",
+            "2026-13-01",
+            "3 apples",
+        ] {
+            assert_eq!(yaml_scalar(value), format!("\"{}\"", value));
+        }
+        assert_eq!(yaml_scalar("42"), "42");
+        assert_eq!(yaml_scalar("-1.5"), "-1.5");
+        assert_eq!(yaml_scalar("true"), "true");
+        assert_eq!(yaml_scalar("0123"), "\"0123\"");
+        assert_eq!(yaml_scalar("no"), "\"no\"");
+        assert_eq!(yaml_scalar("[[Daily]]"), "\"[[Daily]]\"");
+        assert_eq!(yaml_scalar("#idea"), "\"#idea\"");
+        assert_eq!(yaml_scalar("Re: hello"), "\"Re: hello\"");
+        assert_eq!(yaml_scalar("a \"b\"\nc"), "\"a \\\"b\\\"\\nc\"");
+        assert_eq!(yaml_scalar(""), "\"\"");
+    }
+
+    #[test]
+    fn keeps_quoted_list_items_whole() {
+        let generated = [(
+            "aliases",
+            PropertyValue::List(vec!["Smith, John".to_string(), "Jane".to_string()]),
+        )];
+        assert_eq!(
+            compose_note(
+                "---\naliases: [\"Smith, John\", 'O''Brien']\n---",
+                "Body",
+                &generated
+            ),
+            "---\naliases: [\"Smith, John\", O'Brien, Jane]\n---\n\nBody"
+        );
+        assert_eq!(
+            compose_note("---\naliases: Smith, John\n---", "Body", &generated),
+            "---\naliases: [\"Smith, John\", Jane]\n---\n\nBody"
+        );
+    }
+
+    #[test]
+    fn composes_note_from_template_capture_and_generated_properties() {
+        let template =
+            "---\ncreated: <% tp.date.now() %>\ntags: inbox\ntype: inbox\n---\n# {{title}}";
+        let content = "---\ntype: idea\ntags: [work]\nstatus: open\n---\nBody";
+        let generated = [
+            (
+                "created",
+                PropertyValue::Text("2026-10-17T09:30".to_string()),
+            ),
+            (
+                "tags",
+                PropertyValue::List(vec!["inbox".to_string(), "dev".to_string()]),
+            ),
+            (
+                "aliases",
+                PropertyValue::List(vec!["Project X".to_string()]),
+            ),
+            ("source", PropertyValue::Text("Mail: Inbox".to_string())),
+        ];
+
+        assert_eq!(
+            compose_note(template, content, &generated),
+            "---\ncreated: <% tp.date.now() %>\ntags: [inbox, work, dev]\ntype: idea\n\
+             status: open\naliases: [Project X]\nsource: \"Mail: Inbox\"\n---\n# {{title}}\n\nBody"
+        );
+    }
+
+    #[test]
+    fn composes_note_without_frontmatter_like_before() {
+        assert_eq!(compose_note("", "Body", &[]), "Body");
+        assert_eq!(
+            compose_note("---\ntags: inbox\n---", "Body", &[]),
+            "---\ntags: inbox\n---\n\nBody"
+        );
+        assert_eq!(
+            compose_note("", "---\ntitle: x\n---\nBody", &[]),
+            "---\ntitle: x\n---\nBody"
+        );
+        // Trennlinien sind keine Frontmatter
+        assert_eq!(
+            compose_note(
+                "",
+                "---\nquote\n---",
+                &[("created", PropertyValue::Text("today".into()))]
+            ),
+            "---\ncreated: today\n---\n---\nquote\n---"
+        );
+        assert_eq!(
+            compose_note(
+                "From {{app}}",
+                "Body",
+                &[("a", PropertyValue::List(vec![]))]
+            ),
+            "From {{app}}\n\nBody"
+        );
+    }
+}
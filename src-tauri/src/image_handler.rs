@@ -7,9 +7,11 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::atomic_write;
 use crate::build_image_data_url;
 use crate::log_safety::{redact_path, summarize_bytes};
 use crate::settings::Settings;
+use crate::template;
 
 /// Result of saving an image
 #[derive(Debug)]
@@ -115,7 +117,8 @@ fn compress_and_save(
 
         // If PNG is small enough, save it
         if buffer.len() <= max_size_bytes {
-            fs::write(output_path, &buffer).map_err(|e| format!("Failed to write image: {}", e))?;
+            atomic_write::write_file(output_path, &buffer)
+                .map_err(|e| format!("Failed to write image: {}", e))?;
             return Ok(output_path.to_path_buf());
         }
         // Otherwise, fall through to JPEG compression
@@ -148,7 +151,8 @@ fn compress_and_save(
             // Change extension to .jpg since we're saving as JPEG
             let jpg_path = output_path.with_extension("jpg");
 
-            fs::write(&jpg_path, &buffer).map_err(|e| format!("Failed to write image: {}", e))?;
+            atomic_write::write_file(&jpg_path, &buffer)
+                .map_err(|e| format!("Failed to write image: {}", e))?;
 
             return Ok(jpg_path);
         }
@@ -159,17 +163,9 @@ fn compress_and_save(
 }
 
 /// Generate filename from template
-/// Supports: YYYY, MM, DD, HH, mm, ss
+/// Supports the Moment.js tokens from `template`, literal text in `[...]`
 fn generate_filename(template: &str) -> String {
-    let now = Local::now();
-
-    let filename = template
-        .replace("YYYY", &now.format("%Y").to_string())
-        .replace("MM", &now.format("%m").to_string())
-        .replace("DD", &now.format("%d").to_string())
-        .replace("HH", &now.format("%H").to_string())
-        .replace("mm", &now.format("%M").to_string())
-        .replace("ss", &now.format("%S").to_string());
+    let filename = template::format_date(template, &Local::now());
 
     // Add extension if not present
     if !filename.contains('.') {
@@ -329,7 +325,7 @@ mod tests {
 
     #[test]
     fn test_generate_filename() {
-        let filename = generate_filename("screenshot-YYYY-MM-DD-HHmmss");
+        let filename = generate_filename("[screenshot]-YYYY-MM-DD-HHmmss");
         assert!(filename.contains("-"));
         assert!(filename.ends_with(".jpg"));
     }
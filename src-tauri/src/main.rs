@@ -4,16 +4,37 @@
 )]
 #![allow(unexpected_cfgs)]
 
+mod atomic_write;
 mod capture;
+mod capture_history;
+mod capture_queue;
+mod capture_undo;
+mod duplicates;
 mod edge_detect;
+mod fan_out;
+mod frontmatter;
 mod image_handler;
 mod log_safety;
+mod merge;
+mod note_encoding;
+mod note_version;
+mod obsidian_config;
+mod rich_text;
+mod routing;
+mod sections;
 mod selected_text;
 mod settings;
 mod shortcuts;
+mod source_app;
+mod tagging;
+mod task_dates;
+mod template;
+#[cfg(test)]
+mod test_support;
 mod updater;
 mod vault_index;
 
+use chrono::{DateTime, FixedOffset, Local};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri::{
@@ -22,6 +43,7 @@ use tauri::{
     AppHandle, Emitter, Listener, LogicalPosition, LogicalSize, Manager,
 };
 use tauri_plugin_autostart::ManagerExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tokio::sync::RwLock;
 use tokio::time::{timeout, Duration};
 
@@ -30,10 +52,15 @@ use cocoa::base::{id, BOOL, YES};
 #[cfg(target_os = "macos")]
 use objc::{class, msg_send, sel, sel_impl};
 
+use crate::capture_history::{CaptureHistory, HistoryEntry};
+use crate::capture_queue::{CaptureQueue, CaptureTarget, Delivery, Replayed};
+use crate::capture_undo::WrittenCapture;
+use crate::duplicates::RecentCaptures;
 use crate::edge_detect::EdgeDetector;
 use crate::image_handler::ProcessedImage;
-use crate::settings::Settings;
+use crate::settings::{DuplicateAction, EntryStyle, Period, Settings};
 use crate::shortcuts::ShortcutManager;
+use crate::source_app::{SourceApp, SourceAppProvider};
 use std::fs;
 use std::path::Component;
 
@@ -42,6 +69,8 @@ const WINDOW_INIT_DELAY_MS: u64 = 100;
 const SETTINGS_WINDOW_WIDTH: f64 = 980.0;
 const SETTINGS_WINDOW_HEIGHT: f64 = 720.0;
 const MAX_IMAGE_PAYLOAD_BYTES: usize = 20 * 1024 * 1024;
+const CAPTURE_QUEUE_RETRY_SECS: u64 = 60;
+const CAPTURE_HISTORY_PAGE_SIZE: usize = 100;
 
 struct AppState {
     settings: Arc<RwLock<Settings>>,
@@ -51,6 +80,16 @@ struct AppState {
     capture_text_shortcut_manager: Arc<ShortcutManager>,
     save_as_note_shortcut_manager: Arc<ShortcutManager>,
     vault_index: Arc<RwLock<Option<vault_index::VaultIndex>>>,
+    capture_queue: Arc<RwLock<CaptureQueue>>,
+    /// Held while queued captures are written, so two replays never overlap.
+    capture_queue_replay: Arc<tokio::sync::Mutex<()>>,
+    last_capture: Arc<RwLock<Option<WrittenCapture>>>,
+    capture_history: Arc<CaptureHistory>,
+    source_app: Arc<dyn SourceAppProvider>,
+    /// The app that was frontmost when the capture window opened, until a
+    /// capture used it.
+    capture_source: Arc<RwLock<Option<SourceApp>>>,
+    recent_captures: Arc<RwLock<RecentCaptures>>,
 }
 
 fn warn_if_failed<T, E: std::fmt::Display>(result: Result<T, E>, context: &str) {
@@ -91,7 +130,9 @@ fn ensure_markdown_path(path: &Path) -> Result<(), String> {
     }
 }
 
-fn resolve_vault_read_path(settings: &Settings, requested_path: &str) -> Result<PathBuf, String> {
+/// Resolves `requested_path` to a Markdown file inside the vault. Only looks at
+/// the path: nothing is created, so targets can be checked before writing.
+fn resolve_vault_path(settings: &Settings, requested_path: &str) -> Result<PathBuf, String> {
     let vault_root = canonical_vault_root(settings)?;
     let normalized = normalize_path(Path::new(requested_path))?;
     let candidate = if normalized.is_absolute() {
@@ -110,30 +151,35 @@ fn resolve_vault_read_path(settings: &Settings, requested_path: &str) -> Result<
 }
 
 fn resolve_vault_write_path(settings: &Settings, requested_path: &str) -> Result<PathBuf, String> {
-    let vault_root = canonical_vault_root(settings)?;
-    let normalized = normalize_path(Path::new(requested_path))?;
-    let candidate = if normalized.is_absolute() {
-        normalized
-    } else {
-        vault_root.join(normalized)
-    };
-
-    ensure_markdown_path(&candidate)?;
-
-    if !candidate.starts_with(&vault_root) {
-        return Err("Requested file is outside the vault".to_string());
-    }
+    let path = resolve_vault_path(settings, requested_path)?;
+    create_note_dir(&path)?;
+    Ok(path)
+}
 
-    let parent = candidate
+fn create_note_dir(path: &Path) -> Result<(), String> {
+    let parent = path
         .parent()
         .ok_or_else(|| "Invalid note path".to_string())?;
-    fs::create_dir_all(parent).map_err(|e| format!("Failed to create note directory: {}", e))?;
+    fs::create_dir_all(parent).map_err(|e| format!("Failed to create note directory: {}", e))
+}
 
-    let filename = candidate
-        .file_name()
-        .ok_or_else(|| "Invalid note filename".to_string())?;
+/// Reads a note template from the vault. Like Obsidian, the `.md` extension
+/// may be omitted. An empty path means "no template".
+fn read_vault_template(settings: &Settings, template_path: &str) -> Result<Option<String>, String> {
+    let template_path = template_path.trim();
+    if template_path.is_empty() {
+        return Ok(None);
+    }
 
-    Ok(parent.join(filename))
+    let template_path = if template_path.ends_with(".md") {
+        template_path.to_string()
+    } else {
+        format!("{}.md", template_path)
+    };
+    let resolved = resolve_vault_path(settings, &template_path)?;
+    fs::read_to_string(&resolved)
+        .map(Some)
+        .map_err(|e| format!("Cannot read template {}: {}", template_path, e))
 }
 
 pub(crate) fn build_image_data_url(path: &Path) -> Result<String, String> {
@@ -209,6 +255,8 @@ async fn save_settings(
 ) -> Result<(), String> {
     new_settings.normalize_pinned_note_paths();
     new_settings.normalize_screenshot_path();
+    // Die Oberfläche zeigt und speichert Templates immer in der aktuellen Syntax
+    new_settings.template_syntax = settings::TEMPLATE_SYNTAX;
     new_settings.validate().map_err(|e| {
         log::error!("Settings validation failed: {}", e);
         e
@@ -226,11 +274,25 @@ async fn save_settings(
         }
     }
 
-    let old_vault_path = {
+    let (old_vault_path, history_settings_changed) = {
         let old = state.settings.read().await;
-        old.vault_path.clone()
+        (
+            old.vault_path.clone(),
+            old.capture_history_store_text != new_settings.capture_history_store_text
+                || old.capture_history_retention_days
+                    != new_settings.capture_history_retention_days,
+        )
     };
 
+    if history_settings_changed {
+        warn_if_failed(
+            state
+                .capture_history
+                .prune(&new_settings, Local::now().timestamp_millis()),
+            "Failed to prune capture history",
+        );
+    }
+
     if old_vault_path != new_settings.vault_path {
         *state.vault_index.write().await = None;
         log::info!("Vault index invalidated: vault path changed");
@@ -309,78 +371,728 @@ async fn save_settings(
     Ok(())
 }
 
+/// Resolves the file `save_as_note` writes to, applying the collision policy.
+/// Returns the requested path too, for `capture::save_new_note`.
+fn prepare_new_note(
+    settings: &Settings,
+    title: Option<&str>,
+    captured_at: &DateTime<Local>,
+) -> Result<(PathBuf, capture::NoteTarget), String> {
+    let (requested, target) = resolve_new_note(settings, title, captured_at)?;
+    create_note_dir(&requested)?;
+    Ok((requested, target))
+}
+
+/// The file a new note goes into, like [`prepare_new_note`] but without
+/// creating its folder.
+fn resolve_new_note(
+    settings: &Settings,
+    title: Option<&str>,
+    captured_at: &DateTime<Local>,
+) -> Result<(PathBuf, capture::NoteTarget), String> {
+    if capture_queue::vault_unreachable(&settings.vault_path) {
+        return Err("Vault folder not found".to_string());
+    }
+
+    let relative_path = match title.filter(|t| !t.trim().is_empty()) {
+        Some(t) => capture::build_note_relative_path_from_title(t, settings, captured_at),
+        None => capture::build_note_relative_path(settings, captured_at),
+    };
+
+    let resolved = resolve_vault_path(settings, &relative_path)?;
+    let target = capture::resolve_note_collision(&resolved, settings.note_collision_policy)?;
+    Ok((resolved, target))
+}
+
+/// Writes one capture to its target. Used by the capture commands and by the
+/// offline queue, which passes the original capture time and source app.
+fn write_capture(
+    settings: &Settings,
+    target: &CaptureTarget,
+    text: &str,
+    captured_at: &DateTime<Local>,
+    source: Option<&SourceApp>,
+) -> Result<WrittenCapture, String> {
+    let new_note = matches!(target, CaptureTarget::NewNote { .. });
+    let (settings, text) = decorate_capture(settings, new_note, text, source);
+    write_to_target(&settings, target, &text, captured_at)
+}
+
+/// The settings and text a capture is written with: source app placeholders
+/// filled in, and auto tags added to the text or, for new notes, the frontmatter.
+fn decorate_capture(
+    settings: &Settings,
+    new_note: bool,
+    text: &str,
+    source: Option<&SourceApp>,
+) -> (Settings, String) {
+    let mut settings = source_app::settings_for_source(settings, source);
+    let tags = tagging::tags_for(&settings.auto_tag_rules, text, source);
+    let text = if new_note {
+        settings.note_template = frontmatter::merge_tags(&settings.note_template, &tags);
+        text.to_string()
+    } else {
+        tagging::text_with_tags(text, &tags)
+    };
+
+    let text = source_app::text_with_source(&text, &settings, source);
+    (settings, text)
+}
+
+fn write_to_target(
+    settings: &Settings,
+    target: &CaptureTarget,
+    text: &str,
+    captured_at: &DateTime<Local>,
+) -> Result<WrittenCapture, String> {
+    // Vor dem Anlegen von Ordnern prüfen: sonst entstünde ein Geister-Vault am Mount-Punkt
+    if capture_queue::vault_unreachable(&settings.vault_path) {
+        return Err("Vault folder not found".to_string());
+    }
+
+    match target {
+        CaptureTarget::DailyNote { date } => {
+            let (settings, note_time, daily_path) =
+                daily_note_target(settings, date.as_deref(), captured_at)?;
+            let resolved = resolve_vault_write_path(&settings, &daily_path)?;
+
+            let mut created = false;
+            if settings.daily_note_auto_create && !resolved.exists() {
+                let template = read_vault_template(&settings, &settings.daily_note_template)?;
+                created =
+                    capture::create_note_from_template(&resolved, template.as_deref(), &note_time)?;
+            }
+
+            let written = capture::append_to_daily_note(text, &resolved, &settings, &note_time)?;
+            Ok(if created {
+                written.into_created()
+            } else {
+                written
+            })
+        }
+        CaptureTarget::PeriodicNote { period } => {
+            settings.validate()?;
+            let note_time = capture::daily_note_time(settings, captured_at);
+            let note_path = capture::build_periodic_note_path(*period, settings, &note_time);
+            let resolved = resolve_vault_write_path(settings, &note_path)?;
+
+            let mut created = false;
+            if settings.daily_note_auto_create && !resolved.exists() {
+                let template =
+                    read_vault_template(settings, &settings.periodic_note(*period).template)?;
+                created =
+                    capture::create_note_from_template(&resolved, template.as_deref(), &note_time)?;
+            }
+
+            let written =
+                capture::append_to_periodic_note(text, &resolved, *period, settings, &note_time)?;
+            Ok(if created {
+                written.into_created()
+            } else {
+                written
+            })
+        }
+        CaptureTarget::Note { path, heading } => {
+            settings.validate()?;
+            let (path, anchor) = capture::note_anchor(path, heading.as_deref())?;
+            let resolved = resolve_vault_write_path(settings, &path)?;
+            capture::append_to_note(text, &resolved, anchor.as_ref(), settings, captured_at)
+        }
+        CaptureTarget::NewNote { title } => {
+            settings.validate()?;
+            let (requested, note_target) =
+                prepare_new_note(settings, title.as_deref(), captured_at)?;
+            capture::save_new_note(text, &requested, note_target, settings, captured_at)
+                .map(|result| result.written)
+        }
+        CaptureTarget::Routed {
+            target,
+            heading,
+            entry_style,
+        } => {
+            let settings =
+                routing::routed_settings(settings, target, heading.as_deref(), *entry_style);
+            write_to_target(&settings, target, text, captured_at)
+        }
+    }
+}
+
+/// The settings and time a capture into the daily note, or the note of `date`,
+/// is written with, and the note's vault-relative path.
+fn daily_note_target(
+    settings: &Settings,
+    date: Option<&str>,
+    captured_at: &DateTime<Local>,
+) -> Result<(Settings, DateTime<FixedOffset>, String), String> {
+    let mut settings = settings.clone();
+    settings.apply_obsidian_daily_notes();
+    settings.validate()?;
+    let note_time = match date {
+        Some(date) => capture::at_daily_note_date(&mut settings, date, captured_at)?,
+        None => capture::daily_note_time(&settings, captured_at),
+    };
+    let path = capture::build_daily_note_path(&settings, &note_time);
+    Ok((settings, note_time, path))
+}
+
+/// The file a capture to `target` goes into, resolved the way the write does
+/// but without creating folders. Lets a multi-target capture check all targets
+/// before writing any.
+fn resolve_target(
+    settings: &Settings,
+    target: &CaptureTarget,
+    captured_at: &DateTime<Local>,
+) -> Result<PathBuf, String> {
+    match target {
+        CaptureTarget::DailyNote { date } => {
+            let (settings, _, daily_path) =
+                daily_note_target(settings, date.as_deref(), captured_at)?;
+            resolve_vault_path(&settings, &daily_path)
+        }
+        CaptureTarget::PeriodicNote { period } => {
+            settings.validate()?;
+            let note_time = capture::daily_note_time(settings, captured_at);
+            let note_path = capture::build_periodic_note_path(*period, settings, &note_time);
+            resolve_vault_path(settings, &note_path)
+        }
+        CaptureTarget::Note { path, heading } => {
+            settings.validate()?;
+            let (path, _) = capture::note_anchor(path, heading.as_deref())?;
+            let resolved = resolve_vault_path(settings, &path)?;
+            if !resolved.exists() {
+                return Err(format!("Note not found: {}", path));
+            }
+            Ok(resolved)
+        }
+        CaptureTarget::NewNote { title } => {
+            settings.validate()?;
+            match resolve_new_note(settings, title.as_deref(), captured_at)?.1 {
+                capture::NoteTarget::Create(path) | capture::NoteTarget::Append(path) => Ok(path),
+            }
+        }
+        CaptureTarget::Routed { target, .. } => resolve_target(settings, target, captured_at),
+    }
+}
+
+/// The entry style a capture to `target` is written in; `None` for new notes.
+fn target_entry_style(settings: &Settings, target: &CaptureTarget) -> Option<EntryStyle> {
+    match target {
+        CaptureTarget::DailyNote { .. } => Some(settings.daily_note_entry_style),
+        CaptureTarget::PeriodicNote { period } => Some(settings.periodic_note(*period).entry_style),
+        CaptureTarget::Note { .. } => Some(settings.append_to_note_entry_style),
+        CaptureTarget::NewNote { .. } => None,
+        CaptureTarget::Routed {
+            target,
+            entry_style,
+            ..
+        } => entry_style.or_else(|| target_entry_style(settings, target)),
+    }
+}
+
+/// Adds a delivered capture to the history. A failure only gets logged, the
+/// capture itself is already written.
+fn record_capture(
+    history: &CaptureHistory,
+    settings: &Settings,
+    target: &CaptureTarget,
+    written: &WrittenCapture,
+    text: &str,
+    captured_at: &DateTime<Local>,
+) {
+    let entry_style = target_entry_style(settings, target);
+    let note = canonical_vault_root(settings)
+        .ok()
+        .and_then(|root| written.path().strip_prefix(root).ok())
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|| crate::log_safety::redact_path(written.path()));
+
+    warn_if_failed(
+        history.record(settings, &note, entry_style, text, captured_at),
+        "Failed to record capture history",
+    );
+}
+
+/// Queues a failed capture if the vault is unreachable; any other error is returned as is.
+/// Returns the number of pending captures.
+async fn queue_if_unreachable(
+    app: &AppHandle,
+    settings: &Settings,
+    target: CaptureTarget,
+    text: &str,
+    captured_at: &DateTime<Local>,
+    source: Option<&SourceApp>,
+    error: String,
+) -> Result<usize, String> {
+    if !capture_queue::vault_unreachable(&settings.vault_path) {
+        return Err(error);
+    }
+
+    log::warn!("Vault unreachable, queueing capture: {}", error);
+    let state = app.state::<AppState>();
+    *state.last_capture.write().await = None;
+    let pending = state
+        .capture_queue
+        .write()
+        .await
+        .push(target, text, captured_at, source, &error)
+        .map_err(|e| format!("{} (could not queue capture: {})", error, e))?;
+    warn_if_failed(
+        app.emit("capture_queue_changed", pending),
+        "Failed to emit capture_queue_changed",
+    );
+    Ok(pending)
+}
+
+async fn capture_or_queue(
+    app: &AppHandle,
+    state: &tauri::State<'_, AppState>,
+    target: CaptureTarget,
+    text: &str,
+) -> Result<Delivery, String> {
+    let settings = state.settings.read().await.clone();
+    let source = state.capture_source.read().await.clone();
+    let captured_at = Local::now();
+
+    // Bis die Aufnahme geschrieben ist gesperrt, damit ein doppelter Tastendruck warten muss
+    let mut recent = state.recent_captures.write().await;
+    let duplicate = recent.find(&settings, &target, text, captured_at.timestamp_millis());
+    if let (DuplicateAction::Skip, Some(duplicate)) =
+        (settings.duplicate_capture_action, &duplicate)
+    {
+        log::info!("Skipped duplicate capture");
+        return Ok(Delivery::Skipped {
+            duplicate: duplicate.clone(),
+        });
+    }
+
+    let delivery = match write_capture(&settings, &target, text, &captured_at, source.as_ref()) {
+        Ok(written) => {
+            record_capture(
+                &state.capture_history,
+                &settings,
+                &target,
+                &written,
+                text,
+                &captured_at,
+            );
+            *state.last_capture.write().await = Some(written);
+            Delivery::Written {
+                duplicate,
+                note: None,
+            }
+        }
+        Err(error) => {
+            let pending = queue_if_unreachable(
+                app,
+                &settings,
+                target.clone(),
+                text,
+                &captured_at,
+                source.as_ref(),
+                error,
+            )
+            .await?;
+            Delivery::Queued { pending }
+        }
+    };
+    recent.record(&target, text, captured_at.timestamp_millis());
+    // Die Quelle gilt nur für die Aufnahme, für die das Fenster geöffnet wurde
+    *state.capture_source.write().await = None;
+    Ok(delivery)
+}
+
+/// Writes queued captures once the vault is reachable again. Captures that
+/// fail with the vault reachable are only tried again on `retry_failed`.
+async fn replay_capture_queue(app: &AppHandle, retry_failed: bool) {
+    let state = app.state::<AppState>();
+    if state.capture_queue.read().await.is_empty() {
+        return;
+    }
+    let Ok(_replaying) = state.capture_queue_replay.try_lock() else {
+        return;
+    };
+
+    let due = state.capture_queue.read().await.due(retry_failed);
+    if due.is_empty() {
+        return;
+    }
+
+    let settings = state.settings.read().await.clone();
+    if capture_queue::vault_unreachable(&settings.vault_path) {
+        return;
+    }
+
+    // Ohne Sperre auf die Warteschlange schreiben, damit neue Aufnahmen nicht warten
+    let mut outcomes = Vec::new();
+    for item in &due {
+        let captured_at = item.captured_at();
+        let outcome = match write_capture(
+            &settings,
+            &item.target,
+            &item.text,
+            &captured_at,
+            item.source.as_ref(),
+        ) {
+            Ok(written) => {
+                record_capture(
+                    &state.capture_history,
+                    &settings,
+                    &item.target,
+                    &written,
+                    &item.text,
+                    &captured_at,
+                );
+                Replayed::Delivered
+            }
+            Err(error) if capture_queue::vault_unreachable(&settings.vault_path) => {
+                outcomes.push((item.id, Replayed::Offline(error)));
+                break;
+            }
+            Err(error) => Replayed::Failed(error),
+        };
+        outcomes.push((item.id, outcome));
+    }
+
+    let mut queue = state.capture_queue.write().await;
+    match queue.record(&outcomes) {
+        Ok(0) => {}
+        Ok(delivered) => log::info!("Delivered {} queued captures", delivered),
+        Err(e) => log::error!("Failed to update capture queue: {}", e),
+    }
+    warn_if_failed(
+        app.emit("capture_queue_changed", queue.len()),
+        "Failed to emit capture_queue_changed",
+    );
+}
+
 #[tauri::command]
 async fn save_as_note(
     content: String,
     title: Option<String>,
     app: AppHandle,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<Delivery, String> {
     let settings = state.settings.read().await.clone();
     settings.validate()?;
+    let source = state.capture_source.read().await.clone();
+    let captured_at = Local::now();
+    let content = content.trim();
+    let target = CaptureTarget::NewNote {
+        title: title.clone(),
+    };
 
-    let relative_path = match title.as_deref().filter(|t| !t.trim().is_empty()) {
-        Some(t) => capture::build_note_relative_path_from_title(t, &settings),
-        None => capture::build_note_relative_path(&settings),
+    let mut recent = state.recent_captures.write().await;
+    let duplicate = recent.find(&settings, &target, content, captured_at.timestamp_millis());
+    if let (DuplicateAction::Skip, Some(duplicate)) =
+        (settings.duplicate_capture_action, &duplicate)
+    {
+        log::info!("Skipped duplicate note");
+        return Ok(Delivery::Skipped {
+            duplicate: duplicate.clone(),
+        });
+    }
+
+    let error = match prepare_new_note(&settings, title.as_deref(), &captured_at) {
+        Ok((requested, note_target)) => {
+            state.edge_detector.set_capture_open(false).await;
+
+            if let Some(window) = app.get_webview_window("capture") {
+                warn_if_failed(window.hide(), "Failed to hide capture window");
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(
+                SAVE_AS_NOTE_HIDE_DELAY_MS,
+            ))
+            .await;
+
+            let (note_settings, note_content) =
+                decorate_capture(&settings, true, content, source.as_ref());
+            match capture::save_new_note(
+                &note_content,
+                &requested,
+                note_target,
+                &note_settings,
+                &captured_at,
+            ) {
+                Ok(result) => {
+                    record_capture(
+                        &state.capture_history,
+                        &settings,
+                        &target,
+                        &result.written,
+                        content,
+                        &captured_at,
+                    );
+                    let note = result
+                        .written
+                        .path()
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string());
+                    *state.last_capture.write().await = Some(result.written);
+                    recent.record(&target, content, captured_at.timestamp_millis());
+                    *state.capture_source.write().await = None;
+                    return Ok(Delivery::Written { duplicate, note });
+                }
+                Err(error) => error,
+            }
+        }
+        Err(error) => error,
     };
 
-    let resolved = resolve_vault_write_path(&settings, &relative_path)?;
-    let filename = resolved
-        .file_name()
-        .and_then(|value| value.to_str())
-        .ok_or_else(|| "Invalid note filename".to_string())?
-        .to_string();
+    let pending = queue_if_unreachable(
+        &app,
+        &settings,
+        target.clone(),
+        content,
+        &captured_at,
+        source.as_ref(),
+        error,
+    )
+    .await?;
+    recent.record(&target, content, captured_at.timestamp_millis());
+    *state.capture_source.write().await = None;
+    Ok(Delivery::Queued { pending })
+}
 
-    state.edge_detector.set_capture_open(false).await;
+/// `date` writes into another day's daily note instead of today's:
+/// `YYYY-MM-DD` or relative, like `yesterday` or `-2d`.
+#[tauri::command]
+async fn append_to_daily_note(
+    text: String,
+    date: Option<String>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    let settings = state.settings.read().await.clone();
+    // Relative Angaben jetzt auflösen, damit eine eingereihte Aufnahme den Tag behält
+    let today = capture::daily_note_time(&settings, &Local::now()).date_naive();
+    let date = match date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => Some(capture::parse_daily_note_date(date, today)?),
+        None => None,
+    };
 
-    if let Some(window) = app.get_webview_window("capture") {
-        warn_if_failed(window.hide(), "Failed to hide capture window");
+    let rules = settings.capture_rules;
+    let routed = routing::route(&rules, &text)?;
+    let target = match date {
+        Some(date) => routed
+            .target
+            .on_date(&date.format("%Y-%m-%d").to_string())?,
+        None => routed.target,
+    };
+    capture_or_queue(&app, &state, target, &routed.text).await
+}
+
+/// Writes `text` to every target or to none: all targets are checked first,
+/// and if one of them fails to write, the ones already written are undone.
+#[tauri::command]
+async fn capture_to_targets(
+    text: String,
+    targets: Vec<CaptureTarget>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<fan_out::TargetResult>, String> {
+    if targets.is_empty() {
+        return Err("No capture targets given".to_string());
+    }
+    if text.trim().is_empty() {
+        return Err("Nothing to capture".to_string());
     }
 
-    tokio::time::sleep(tokio::time::Duration::from_millis(
-        SAVE_AS_NOTE_HIDE_DELAY_MS,
-    ))
-    .await;
+    let settings = state.settings.read().await.clone();
+    let source = state.capture_source.read().await.clone();
+    let captured_at = Local::now();
+    if capture_queue::vault_unreachable(&settings.vault_path) {
+        return Err("Vault folder not found".to_string());
+    }
 
-    let result = capture::save_note_at_path(&content.trim(), &resolved, &filename, &settings)?;
+    let mut written = Vec::new();
+    let results = fan_out::capture_all(
+        &targets,
+        |target| resolve_target(&settings, target, &captured_at).map(|_| ()),
+        |target| {
+            let capture = write_capture(&settings, target, &text, &captured_at, source.as_ref())?;
+            written.push((target.clone(), capture.clone()));
+            Ok(capture)
+        },
+    );
 
-    Ok(result.message)
+    let delivered = results
+        .iter()
+        .all(|result| matches!(result.outcome, fan_out::Outcome::Written { .. }));
+    if delivered {
+        for (target, capture) in &written {
+            record_capture(
+                &state.capture_history,
+                &settings,
+                target,
+                capture,
+                &text,
+                &captured_at,
+            );
+        }
+        // Undo nimmt nur eine Datei zurück, eine halb rückgängig gemachte Aufnahme wäre schlimmer
+        *state.last_capture.write().await = None;
+        *state.capture_source.write().await = None;
+    }
+    Ok(results)
 }
 
+/// Dry run of the capture rules for the settings UI: where `text` would go,
+/// without writing anything. Uses `rules` instead of the saved ones if given.
 #[tauri::command]
-async fn append_to_daily_note(
+async fn test_capture_rules(
     text: String,
+    rules: Option<Vec<settings::CaptureRule>>,
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    let settings = state.settings.read().await.clone();
-    settings.validate()?;
-    let daily_path = capture::build_daily_note_path(&settings);
-    let resolved = resolve_vault_write_path(&settings, &daily_path)?;
+) -> Result<routing::RoutedCapture, String> {
+    let rules = match rules {
+        Some(rules) => rules,
+        None => state.settings.read().await.capture_rules.clone(),
+    };
+    routing::route(&rules, &text)
+}
 
-    capture::append_to_daily_note(&text, &resolved, &settings)?;
+async fn append_to_periodic_note(
+    period: Period,
+    text: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    capture_or_queue(&app, &state, CaptureTarget::PeriodicNote { period }, &text).await
+}
 
-    Ok(())
+#[tauri::command]
+async fn append_to_weekly_note(
+    text: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    append_to_periodic_note(Period::Week, text, app, state).await
+}
+
+#[tauri::command]
+async fn append_to_monthly_note(
+    text: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    append_to_periodic_note(Period::Month, text, app, state).await
+}
+
+#[tauri::command]
+async fn append_to_quarterly_note(
+    text: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    append_to_periodic_note(Period::Quarter, text, app, state).await
+}
+
+#[tauri::command]
+async fn append_to_yearly_note(
+    text: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Delivery, String> {
+    append_to_periodic_note(Period::Year, text, app, state).await
 }
 
 #[tauri::command]
 async fn append_to_note(
     path: String,
     text: String,
+    heading: Option<String>,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    let settings = state.settings.read().await.clone();
-    settings.validate()?;
-    let resolved = resolve_vault_write_path(&settings, &path)?;
+) -> Result<Delivery, String> {
+    capture_or_queue(&app, &state, CaptureTarget::Note { path, heading }, &text).await
+}
 
-    capture::append_to_note(&text, &resolved, &settings)?;
+/// Reverts the most recent capture if its note is unchanged since.
+async fn undo_capture(state: &AppState) -> Result<String, String> {
+    let mut last_capture = state.last_capture.write().await;
+    let written = last_capture
+        .take()
+        .ok_or_else(|| "Nothing to undo".to_string())?;
+    let result = capture_undo::undo(&written);
+    if result.is_err() {
+        *last_capture = Some(written);
+    }
+    result
+}
 
-    Ok(())
+#[tauri::command]
+async fn undo_last_capture(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    undo_capture(&state).await
+}
+
+#[tauri::command]
+async fn get_capture_queue(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<capture_queue::QueuedCapture>, String> {
+    Ok(state.capture_queue.read().await.items().to_vec())
+}
+
+#[tauri::command]
+async fn retry_capture_queue(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    replay_capture_queue(&app, true).await;
+    Ok(state.capture_queue.read().await.len())
+}
+
+#[tauri::command]
+async fn discard_queued_capture(
+    id: u64,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let mut queue = state.capture_queue.write().await;
+    if !queue.remove(id)? {
+        return Err(format!("Queued capture not found: {}", id));
+    }
+    warn_if_failed(
+        app.emit("capture_queue_changed", queue.len()),
+        "Failed to emit capture_queue_changed",
+    );
+    Ok(queue.len())
+}
+
+#[tauri::command]
+async fn list_capture_history(
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<HistoryEntry>, String> {
+    state.capture_history.list(
+        offset.unwrap_or(0),
+        limit.unwrap_or(CAPTURE_HISTORY_PAGE_SIZE),
+    )
 }
 
 #[tauri::command]
-async fn read_note_file(path: String, state: tauri::State<'_, AppState>) -> Result<String, String> {
+async fn search_capture_history(
+    query: String,
+    since_ms: Option<i64>,
+    until_ms: Option<i64>,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<HistoryEntry>, String> {
+    state.capture_history.search(
+        &query,
+        since_ms,
+        until_ms,
+        limit.unwrap_or(CAPTURE_HISTORY_PAGE_SIZE),
+    )
+}
+
+#[tauri::command]
+async fn read_note_file(
+    path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<note_version::NoteSnapshot, String> {
     let settings = state.settings.read().await.clone();
-    let resolved = resolve_vault_read_path(&settings, &path)?;
+    let resolved = resolve_vault_path(&settings, &path)?;
     let result = timeout(
         Duration::from_secs(5),
         tokio::fs::read_to_string(&resolved),
@@ -388,7 +1100,13 @@ async fn read_note_file(path: String, state: tauri::State<'_, AppState>) -> Resu
     .await;
 
     match result {
-        Ok(Ok(content)) => Ok(content),
+        Ok(Ok(content)) => {
+            let metadata = tokio::fs::metadata(&resolved).await.ok();
+            Ok(note_version::NoteSnapshot {
+                version: note_version::NoteVersion::new(&content, metadata.as_ref()),
+                content,
+            })
+        }
         Ok(Err(e)) => Err(format!("Failed to read file: {}", e)),
         Err(_) => Err("File read timed out (iCloud download may be pending)".to_string()),
     }
@@ -398,11 +1116,18 @@ async fn read_note_file(path: String, state: tauri::State<'_, AppState>) -> Resu
 async fn write_note_file(
     path: String,
     content: String,
+    expected_version: Option<note_version::NoteVersion>,
+    base_content: Option<String>,
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<note_version::WriteOutcome, note_version::WriteNoteError> {
     let settings = state.settings.read().await.clone();
     let resolved = resolve_vault_write_path(&settings, &path)?;
-    fs::write(&resolved, content).map_err(|e| format!("Failed to write file: {}", e))
+    note_version::write_checked(
+        &resolved,
+        &content,
+        expected_version.as_ref(),
+        base_content.as_deref(),
+    )
 }
 
 #[tauri::command]
@@ -511,15 +1236,31 @@ async fn list_vault_notes(
 
 #[tauri::command]
 async fn get_daily_note_path(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let settings = state.settings.read().await.clone();
+    let mut settings = state.settings.read().await.clone();
+    settings.apply_obsidian_daily_notes();
     settings.validate()?;
-    let daily_path = capture::build_daily_note_path(&settings);
+    let note_time = capture::daily_note_time(&settings, &Local::now());
+    let daily_path = capture::build_daily_note_path(&settings, &note_time);
     let file_path = resolve_vault_write_path(&settings, &daily_path)?;
     Ok(file_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-async fn reindex_vault(state: tauri::State<'_, AppState>) -> Result<usize, String> {
+async fn get_obsidian_daily_notes(
+    state: tauri::State<'_, AppState>,
+) -> Result<obsidian_config::DailyNoteStatus, String> {
+    let settings = state.settings.read().await.clone();
+    let config = obsidian_config::daily_note_config(Path::new(&settings.vault_path));
+    let drift = config
+        .as_ref()
+        .map(|config| settings.daily_note_drift(config))
+        .unwrap_or_default();
+
+    Ok(obsidian_config::DailyNoteStatus { config, drift })
+}
+
+#[tauri::command]
+async fn reindex_vault(app: AppHandle, state: tauri::State<'_, AppState>) -> Result<usize, String> {
     let vault_path = {
         let settings = state.settings.read().await;
         settings.vault_path.clone()
@@ -537,6 +1278,8 @@ async fn reindex_vault(state: tauri::State<'_, AppState>) -> Result<usize, Strin
         build_duration_ms
     );
 
+    replay_capture_queue(&app, false).await;
+
     Ok(count)
 }
 
@@ -628,6 +1371,7 @@ async fn hide_capture(app: AppHandle, state: tauri::State<'_, AppState>) -> Resu
 
 #[tauri::command]
 async fn show_capture(app: AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    remember_capture_source(&app).await;
     if let Some(window) = app.get_webview_window("capture") {
         let settings = state.settings.read().await;
         position_window_logical(&window, &settings)?;
@@ -638,6 +1382,32 @@ async fn show_capture(app: AppHandle, state: tauri::State<'_, AppState>) -> Resu
     Ok(())
 }
 
+/// Records the frontmost app as the source of the next capture, if enabled.
+/// Must run before the capture window takes focus.
+async fn remember_capture_source(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if !state.settings.read().await.record_source_app {
+        *state.capture_source.write().await = None;
+        return;
+    }
+
+    let provider = state.source_app.clone();
+    let own_app = app.package_info().name.clone();
+    let mut source = state.capture_source.read().await.clone();
+    let refreshed = tauri::async_runtime::spawn_blocking(move || {
+        source_app::refresh(&mut source, provider.as_ref(), &own_app);
+        source
+    })
+    .await;
+
+    match refreshed {
+        Ok(source) => *state.capture_source.write().await = source,
+        Err(e) => log::warn!("Failed to look up the frontmost app: {}", e),
+    }
+}
+
+/// Shows the capture window for the `show_capture` event. Whoever emits it
+/// records the capture source first.
 async fn show_capture_internal(app: &AppHandle) -> Result<(), String> {
     let state = app.state::<AppState>();
     if let Some(window) = app.get_webview_window("capture") {
@@ -852,6 +1622,14 @@ fn create_tray_menu(app: &AppHandle) -> Menu<tauri::Wry> {
         None::<String>,
     )
     .unwrap();
+    let undo_capture = MenuItem::with_id(
+        app,
+        "undo_last_capture",
+        "Undo Last Capture",
+        true,
+        None::<String>,
+    )
+    .unwrap();
     let settings = MenuItem::with_id(app, "settings", "Settings...", true, None::<String>).unwrap();
     let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<String>).unwrap();
 
@@ -859,6 +1637,7 @@ fn create_tray_menu(app: &AppHandle) -> Menu<tauri::Wry> {
         app,
         &[
             &quick_capture,
+            &undo_capture,
             &settings,
             &PredefinedMenuItem::separator(app).unwrap(),
             &quit,
@@ -879,6 +1658,7 @@ fn handle_tray_event(app: &AppHandle, event: TrayIconEvent) {
             tauri::async_runtime::spawn(async move {
                 let state = app_handle.state::<AppState>();
                 let settings = state.settings.read().await.clone();
+                remember_capture_source(&app_handle).await;
                 show_capture_window(&app_handle, &settings);
             });
         }
@@ -907,6 +1687,12 @@ fn main() {
         Settings::default()
     });
 
+    let capture_history = CaptureHistory::load();
+    warn_if_failed(
+        capture_history.prune(&settings, Local::now().timestamp_millis()),
+        "Failed to prune capture history",
+    );
+
     let edge_detector = Arc::new(EdgeDetector::new(settings.clone()));
     let shortcut_manager = Arc::new(ShortcutManager::new());
     let reader_shortcut_manager = Arc::new(ShortcutManager::new());
@@ -921,6 +1707,13 @@ fn main() {
         capture_text_shortcut_manager: capture_text_shortcut_manager.clone(),
         save_as_note_shortcut_manager: save_as_note_shortcut_manager.clone(),
         vault_index: Arc::new(RwLock::new(None)),
+        capture_queue: Arc::new(RwLock::new(CaptureQueue::load())),
+        capture_queue_replay: Arc::new(tokio::sync::Mutex::new(())),
+        last_capture: Arc::new(RwLock::new(None)),
+        capture_history: Arc::new(capture_history),
+        source_app: Arc::new(source_app::SystemSourceApp),
+        capture_source: Arc::new(RwLock::new(None)),
+        recent_captures: Arc::new(RwLock::new(RecentCaptures::default())),
     };
 
     tauri::Builder::default()
@@ -967,7 +1760,28 @@ fn main() {
                 .menu(&menu)
                 .on_menu_event(move |app, event| match event.id().as_ref() {
                     "quick_capture" => {
-                        show_capture_window(app, &settings_for_tray);
+                        let app_clone = app.clone();
+                        let settings = settings_for_tray.clone();
+                        tauri::async_runtime::spawn(async move {
+                            remember_capture_source(&app_clone).await;
+                            show_capture_window(&app_clone, &settings);
+                        });
+                    }
+                    "undo_last_capture" => {
+                        let app_clone = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let state = app_clone.state::<AppState>();
+                            let (message, kind) = match undo_capture(&state).await {
+                                Ok(message) => (message, MessageDialogKind::Info),
+                                Err(error) => (error, MessageDialogKind::Warning),
+                            };
+                            app_clone
+                                .dialog()
+                                .message(message)
+                                .title("Undo Last Capture")
+                                .kind(kind)
+                                .show(|_| {});
+                        });
                     }
                     "settings" => {
                         let app_clone = app.clone();
@@ -999,6 +1813,14 @@ fn main() {
                         None::<String>,
                     )
                     .unwrap();
+                    let undo_capture = MenuItem::with_id(
+                        &app_handle_for_update,
+                        "undo_last_capture",
+                        "Undo Last Capture",
+                        true,
+                        None::<String>,
+                    )
+                    .unwrap();
                     let settings = MenuItem::with_id(
                         &app_handle_for_update,
                         "settings",
@@ -1027,6 +1849,7 @@ fn main() {
                         &app_handle_for_update,
                         &[
                             &quick_capture,
+                            &undo_capture,
                             &settings,
                             &PredefinedMenuItem::separator(&app_handle_for_update).unwrap(),
                             &open_update,
@@ -1117,6 +1940,16 @@ fn main() {
                 }
             });
 
+            let app_handle_queue = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(Duration::from_secs(CAPTURE_QUEUE_RETRY_SECS));
+                loop {
+                    interval.tick().await;
+                    replay_capture_queue(&app_handle_queue, false).await;
+                }
+            });
+
             if let Some(window) = app.get_webview_window("capture") {
                 warn_if_failed(
                     position_window_logical(&window, &settings),
@@ -1156,7 +1989,19 @@ fn main() {
             save_settings,
             save_as_note,
             append_to_daily_note,
+            append_to_weekly_note,
+            append_to_monthly_note,
+            append_to_quarterly_note,
+            append_to_yearly_note,
             append_to_note,
+            capture_to_targets,
+            test_capture_rules,
+            undo_last_capture,
+            get_capture_queue,
+            retry_capture_queue,
+            discard_queued_capture,
+            list_capture_history,
+            search_capture_history,
             read_note_file,
             write_note_file,
             open_external_url,
@@ -1165,6 +2010,7 @@ fn main() {
             load_images_batch,
             list_vault_notes,
             get_daily_note_path,
+            get_obsidian_daily_notes,
             reindex_vault,
             save_image,
             save_image_from_bytes,
@@ -1186,16 +2032,9 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn temp_vault_dir() -> PathBuf {
-        let suffix = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let dir = std::env::temp_dir().join(format!("collector-test-{}", suffix));
-        fs::create_dir_all(&dir).unwrap();
-        dir
+        test_support::temp_dir("vault")
     }
 
     #[test]
@@ -1204,7 +2043,7 @@ mod tests {
     }
 
     #[test]
-    fn resolve_vault_read_path_rejects_outside_file() {
+    fn resolve_vault_path_rejects_outside_file() {
         let vault_dir = temp_vault_dir();
         let note_path = vault_dir.join("safe.md");
         fs::write(&note_path, "# Safe").unwrap();
@@ -1214,12 +2053,33 @@ mod tests {
             ..Default::default()
         };
 
-        let result = resolve_vault_read_path(&settings, "../safe.md");
+        let result = resolve_vault_path(&settings, "../safe.md");
         assert!(result.is_err());
 
         let _ = fs::remove_dir_all(vault_dir);
     }
 
+    #[test]
+    fn read_vault_template_adds_markdown_extension() {
+        let vault_dir = temp_vault_dir();
+        fs::create_dir_all(vault_dir.join("Templates")).unwrap();
+        fs::write(vault_dir.join("Templates/Daily.md"), "# {{title}}").unwrap();
+
+        let settings = Settings {
+            vault_path: vault_dir.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            read_vault_template(&settings, "Templates/Daily").unwrap(),
+            Some("# {{title}}".to_string())
+        );
+        assert_eq!(read_vault_template(&settings, "").unwrap(), None);
+        assert!(read_vault_template(&settings, "Templates/Missing").is_err());
+
+        let _ = fs::remove_dir_all(vault_dir);
+    }
+
     #[test]
     fn resolve_vault_write_path_rejects_outside_file() {
         let vault_dir = temp_vault_dir();
@@ -1233,4 +2093,43 @@ mod tests {
 
         let _ = fs::remove_dir_all(vault_dir);
     }
+
+    #[test]
+    fn rejected_multi_target_capture_leaves_the_vault_unchanged() {
+        let vault_dir = temp_vault_dir();
+        let mut settings = Settings {
+            vault_path: vault_dir.to_string_lossy().to_string(),
+            daily_note_folder: "Daily".to_string(),
+            ..Default::default()
+        };
+        settings.weekly_note.folder = "Periodic/Weekly".to_string();
+        let targets = vec![
+            CaptureTarget::DailyNote { date: None },
+            CaptureTarget::PeriodicNote {
+                period: Period::Week,
+            },
+            CaptureTarget::NewNote {
+                title: Some("Idea".to_string()),
+            },
+            CaptureTarget::Note {
+                path: "Projects/Missing.md".to_string(),
+                heading: None,
+            },
+        ];
+        let captured_at = Local::now();
+
+        let results = fan_out::capture_all(
+            &targets,
+            |target| resolve_target(&settings, target, &captured_at).map(|_| ()),
+            |_| unreachable!("a rejected batch writes nothing"),
+        );
+
+        assert!(matches!(
+            results[3].outcome,
+            fan_out::Outcome::Failed { .. }
+        ));
+        assert_eq!(fs::read_dir(&vault_dir).unwrap().count(), 0);
+
+        let _ = fs::remove_dir_all(vault_dir);
+    }
 }
@@ -0,0 +1,168 @@
+//! Line-based three-way merge for notes edited in the Reader while the file
+//! also changed on disk (Obsidian, sync). Only non-overlapping edits merge;
+//! anything else is left to the user.
+
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::ops::Range;
+
+/// One change against the base: replace `base[range]` with `other[replacement]`.
+#[derive(Debug, Clone)]
+struct Hunk {
+    range: Range<usize>,
+    replacement: Range<usize>,
+}
+
+/// Merges `ours` and `theirs`, both derived from `base`.
+/// Returns `None` if the two sides changed the same or adjacent lines differently.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    if ours == theirs || theirs == base {
+        return Some(ours.to_string());
+    }
+    if ours == base {
+        return Some(theirs.to_string());
+    }
+
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let our_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let their_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+
+    let mut our_hunks = hunks(&base_lines, &our_lines).into_iter().peekable();
+    let mut their_hunks = hunks(&base_lines, &their_lines).into_iter().peekable();
+
+    let mut merged = String::with_capacity(ours.len().max(theirs.len()));
+    let mut position = 0;
+
+    loop {
+        let (hunk, lines) = match (our_hunks.peek(), their_hunks.peek()) {
+            (None, None) => break,
+            (Some(ours), Some(theirs)) if touches(&ours.range, &theirs.range) => {
+                let same = ours.range == theirs.range
+                    && our_lines[ours.replacement.clone()]
+                        == their_lines[theirs.replacement.clone()];
+                if !same {
+                    return None;
+                }
+                their_hunks.next();
+                (our_hunks.next()?, &our_lines)
+            }
+            (Some(ours), Some(theirs)) if ours.range.start > theirs.range.start => {
+                (their_hunks.next()?, &their_lines)
+            }
+            (Some(_), _) => (our_hunks.next()?, &our_lines),
+            (None, Some(_)) => (their_hunks.next()?, &their_lines),
+        };
+
+        merged.extend(base_lines[position..hunk.range.start].iter().copied());
+        merged.extend(lines[hunk.replacement].iter().copied());
+        position = hunk.range.end;
+    }
+
+    merged.extend(base_lines[position..].iter().copied());
+    Some(merged)
+}
+
+/// Overlapping, adjacent or same-position changes can't be ordered safely.
+fn touches(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+fn hunks(base: &[&str], other: &[&str]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        let (range, replacement) = match op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete {
+                old_index,
+                old_len,
+                new_index,
+            } => (old_index..old_index + old_len, new_index..new_index),
+            DiffOp::Insert {
+                old_index,
+                new_index,
+                new_len,
+            } => (old_index..old_index, new_index..new_index + new_len),
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => (
+                old_index..old_index + old_len,
+                new_index..new_index + new_len,
+            ),
+        };
+
+        // Direkt aufeinanderfolgende Operationen zu einem Hunk zusammenfassen
+        match hunks.last_mut() {
+            Some(last) if last.range.end == range.start => {
+                last.range.end = range.end;
+                last.replacement.end = replacement.end;
+            }
+            _ => hunks.push(Hunk { range, replacement }),
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# Note\n\nfirst\nsecond\nthird\n\nfooter\n";
+
+    #[test]
+    fn merges_edits_in_different_places() {
+        let ours = "# Note\n\nfirst edited\nsecond\nthird\n\nfooter\n";
+        let theirs = "# Note\n\nfirst\nsecond\nthird\n\nfooter\n- synced line\n";
+
+        assert_eq!(
+            merge3(BASE, ours, theirs).unwrap(),
+            "# Note\n\nfirst edited\nsecond\nthird\n\nfooter\n- synced line\n"
+        );
+    }
+
+    #[test]
+    fn merges_insertions_and_deletions() {
+        let ours = "# Note\n\nintro\nfirst\nsecond\nthird\n\nfooter\n";
+        let theirs = "# Note\n\nfirst\nsecond\n\nfooter\n";
+
+        assert_eq!(
+            merge3(BASE, ours, theirs).unwrap(),
+            "# Note\n\nintro\nfirst\nsecond\n\nfooter\n"
+        );
+    }
+
+    #[test]
+    fn identical_changes_merge_once() {
+        let both = "# Note\n\nfirst\nSECOND\nthird\n\nfooter\n";
+        assert_eq!(merge3(BASE, both, both).unwrap(), both);
+
+        let ours = "# Note\n\nfirst\nSECOND\nthird\n\nfooter!\n";
+        let theirs = "# Note\n\nfirst\nSECOND\nthird\n\nfooter\n";
+        assert_eq!(merge3(BASE, ours, theirs).unwrap(), ours);
+    }
+
+    #[test]
+    fn overlapping_or_adjacent_edits_conflict() {
+        let ours = "# Note\n\nfirst\nmine\nthird\n\nfooter\n";
+        let theirs = "# Note\n\nfirst\ntheirs\nthird\n\nfooter\n";
+        assert_eq!(merge3(BASE, ours, theirs), None);
+
+        let ours = "# Note\n\nfirst\nmine\nthird\n\nfooter\n";
+        let theirs = "# Note\n\nfirst\nsecond\nthird changed\n\nfooter\n";
+        assert_eq!(merge3(BASE, ours, theirs), None);
+
+        let ours = "# Note\n\nfirst\nsecond\nthird\n\nfooter\nmine\n";
+        let theirs = "# Note\n\nfirst\nsecond\nthird\n\nfooter\ntheirs\n";
+        assert_eq!(merge3(BASE, ours, theirs), None);
+    }
+
+    #[test]
+    fn unchanged_side_takes_the_other() {
+        let changed = "something else entirely";
+        assert_eq!(merge3(BASE, BASE, changed).unwrap(), changed);
+        assert_eq!(merge3(BASE, changed, BASE).unwrap(), changed);
+    }
+}
@@ -0,0 +1,203 @@
+//! Encoding and line endings of existing notes.
+//!
+//! Notes from Windows tools may use CRLF, a UTF-8 byte order mark or UTF-16.
+//! Captures are inserted into the decoded text and written back the way the
+//! note was stored; files that are not Unicode text are refused, not mangled.
+
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// The ending most lines of `content` use. LF for notes without line breaks.
+    pub fn detect(content: &str) -> Self {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// `text` with every line break written as `self`.
+    pub fn apply(self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        match self {
+            LineEnding::Lf => text,
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// The decoded text of a note and how it is stored. The byte order mark is
+/// not part of `content`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteText {
+    pub content: String,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+}
+
+impl NoteText {
+    /// Decodes UTF-8 (with or without BOM) and UTF-16 with BOM. Anything else
+    /// (Latin-1, UTF-16 without BOM, binary data) is an error.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let (content, encoding) = if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+            (decode_utf8(rest)?, Encoding::Utf8Bom)
+        } else if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+            (decode_utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le)
+        } else if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+            (decode_utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be)
+        } else {
+            (decode_utf8(bytes)?, Encoding::Utf8)
+        };
+
+        let line_ending = LineEnding::detect(&content);
+        Ok(NoteText {
+            content,
+            encoding,
+            line_ending,
+        })
+    }
+
+    /// `content` as bytes in this note's encoding, including its byte order mark.
+    pub fn encode(&self, content: &str) -> Vec<u8> {
+        match self.encoding {
+            Encoding::Utf8 => content.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [UTF8_BOM, content.as_bytes()].concat(),
+            Encoding::Utf16Le => {
+                let units = content.encode_utf16().flat_map(u16::to_le_bytes);
+                UTF16_LE_BOM.iter().copied().chain(units).collect()
+            }
+            Encoding::Utf16Be => {
+                let units = content.encode_utf16().flat_map(u16::to_be_bytes);
+                UTF16_BE_BOM.iter().copied().chain(units).collect()
+            }
+        }
+    }
+
+    /// Rewrites the line breaks of text just inserted at `range` of `content`
+    /// to this note's line ending. Returns the new content and range.
+    pub fn match_line_endings(&self, content: &str, range: Range<usize>) -> (String, Range<usize>) {
+        let inserted = self.line_ending.apply(&content[range.clone()]);
+        let updated = format!(
+            "{}{}{}",
+            &content[..range.start],
+            inserted,
+            &content[range.end..]
+        );
+        (updated, range.start..range.start + inserted.len())
+    }
+}
+
+/// Reads and decodes the note at `path`.
+pub fn read(path: &Path) -> Result<NoteText, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    NoteText::decode(&bytes)
+}
+
+fn decode_utf8(bytes: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| {
+        format!(
+            "the file is not UTF-8 text (invalid byte at position {}); convert it to UTF-8 first",
+            e.valid_up_to()
+        )
+    })?;
+    // Gültiges UTF-8, aber NUL-Bytes: UTF-16 ohne BOM oder eine Binärdatei
+    if text.contains('\0') {
+        return Err(
+            "the file contains NUL bytes (UTF-16 without byte order mark or not a text file)"
+                .to_string(),
+        );
+    }
+    Ok(text.to_string())
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err("the file is not valid UTF-16 (odd number of bytes)".to_string());
+    }
+
+    let units = pairs.map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| "the file is not valid UTF-16 (unpaired surrogate)".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_dominant_line_ending() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb\r\nc\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("single line"), LineEnding::Lf);
+        assert_eq!(LineEnding::CrLf.apply("a\nb\r\n"), "a\r\nb\r\n");
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn round_trips_bom_and_utf16() {
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            let note = NoteText {
+                content: String::new(),
+                encoding,
+                line_ending: LineEnding::CrLf,
+            };
+            let bytes = note.encode("Grüße 👋\r\n");
+            let decoded = NoteText::decode(&bytes).unwrap();
+            assert_eq!(decoded.content, "Grüße 👋\r\n");
+            assert_eq!(decoded.encoding, encoding);
+            assert_eq!(decoded.line_ending, LineEnding::CrLf);
+        }
+        assert_eq!(
+            NoteText::decode(&[0xFF, 0xFE, b'a', 0]).unwrap().content,
+            "a"
+        );
+    }
+
+    #[test]
+    fn refuses_text_that_is_not_unicode() {
+        // "Grüße" in Latin-1
+        let error = NoteText::decode(b"Gr\xfc\xdfe\n").unwrap_err();
+        assert!(error.contains("not UTF-8"), "{}", error);
+        assert!(NoteText::decode(b"a\0b\0").unwrap_err().contains("NUL"));
+        assert!(NoteText::decode(&[0xFF, 0xFE, b'a']).is_err());
+        assert!(NoteText::decode(&[0xFF, 0xFE, 0x00, 0xD8]).is_err());
+    }
+
+    #[test]
+    fn converts_only_the_inserted_range() {
+        let note = NoteText::decode(b"a\r\nmixed\nb\r\n").unwrap();
+        let content = "a\r\nmixed\nentry\nline\nb\r\n";
+        let (updated, range) = note.match_line_endings(content, 9..20);
+        assert_eq!(updated, "a\r\nmixed\nentry\r\nline\r\nb\r\n");
+        assert_eq!(&updated[range], "entry\r\nline\r\n");
+    }
+}
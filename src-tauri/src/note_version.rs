@@ -0,0 +1,215 @@
+//! Optimistic concurrency for whole-note writes from the Reader.
+//!
+//! `read_note_file` hands out a [`NoteVersion`] with the content; `write_note_file`
+//! only replaces the note if it still has that version. Otherwise the edit is
+//! three-way merged when possible, or rejected with a [`WriteNoteError::Conflict`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::atomic_write;
+use crate::merge;
+
+/// Identifies one state of a note. The hash decides; the mtime is informational
+/// (a touched but unchanged file is not a conflict).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteVersion {
+    pub hash: String,
+    pub mtime_ms: u64,
+}
+
+impl NoteVersion {
+    pub fn new(content: &str, metadata: Option<&fs::Metadata>) -> Self {
+        let mtime_ms = metadata
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+
+        Self {
+            hash: content_hash(content),
+            mtime_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteSnapshot {
+    pub content: String,
+    pub version: NoteVersion,
+}
+
+/// Result of a successful write. `merged_content` is set when the note had
+/// changed on disk and the edit was merged into it.
+#[derive(Debug, Serialize)]
+pub struct WriteOutcome {
+    pub version: NoteVersion,
+    pub merged_content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WriteNoteError {
+    /// The note changed since `expected` was read and the edit couldn't be merged.
+    Conflict {
+        message: String,
+        expected: NoteVersion,
+        current: NoteSnapshot,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl From<String> for WriteNoteError {
+    fn from(message: String) -> Self {
+        WriteNoteError::Failed { message }
+    }
+}
+
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn read_snapshot(path: &Path) -> Result<NoteSnapshot, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let metadata = fs::metadata(path).ok();
+    Ok(NoteSnapshot {
+        version: NoteVersion::new(&content, metadata.as_ref()),
+        content,
+    })
+}
+
+/// Writes `content` if the note is still at `expected` (or `expected` is `None`).
+/// With a `base` (the content `expected` refers to), concurrent edits that
+/// don't overlap are merged instead of rejected.
+pub fn write_checked(
+    path: &Path,
+    content: &str,
+    expected: Option<&NoteVersion>,
+    base: Option<&str>,
+) -> Result<WriteOutcome, WriteNoteError> {
+    let mut to_write = content.to_string();
+    let mut merged_content = None;
+
+    if let Some(expected) = expected {
+        let current = match fs::symlink_metadata(path) {
+            Ok(_) => Some(read_snapshot(path)?),
+            Err(_) => None,
+        };
+
+        if let Some(current) = current.filter(|c| c.version.hash != expected.hash) {
+            let merged = base
+                .filter(|base| content_hash(base) == expected.hash)
+                .and_then(|base| merge::merge3(base, content, &current.content));
+
+            match merged {
+                Some(merged) => {
+                    log::info!("Merged concurrent note changes");
+                    to_write = merged.clone();
+                    merged_content = Some(merged);
+                }
+                None => {
+                    return Err(WriteNoteError::Conflict {
+                        message: "The note was changed outside the Reader".to_string(),
+                        expected: expected.clone(),
+                        current,
+                    });
+                }
+            }
+        }
+    }
+
+    atomic_write::write_file(path, to_write.as_bytes())
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    let metadata = fs::metadata(path).ok();
+    Ok(WriteOutcome {
+        version: NoteVersion::new(&to_write, metadata.as_ref()),
+        merged_content,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_note(content: &str) -> std::path::PathBuf {
+        let dir = crate::test_support::temp_dir("version");
+        let path = dir.join("note.md");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn writes_when_version_matches() {
+        let path = temp_note("a\n");
+        let snapshot = read_snapshot(&path).unwrap();
+
+        let outcome = write_checked(&path, "b\n", Some(&snapshot.version), None).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+        assert_eq!(outcome.version.hash, content_hash("b\n"));
+        assert!(outcome.merged_content.is_none());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rejects_stale_version_with_current_snapshot() {
+        let path = temp_note("line\n");
+        let snapshot = read_snapshot(&path).unwrap();
+        fs::write(&path, "changed by sync\n").unwrap();
+
+        let err = write_checked(&path, "reader edit\n", Some(&snapshot.version), None).unwrap_err();
+
+        match err {
+            WriteNoteError::Conflict {
+                expected, current, ..
+            } => {
+                assert_eq!(expected, snapshot.version);
+                assert_eq!(current.content, "changed by sync\n");
+                assert_eq!(current.version.hash, content_hash("changed by sync\n"));
+            }
+            other => panic!("expected conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "changed by sync\n");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn merges_non_overlapping_changes_with_base() {
+        let base = "# Day\n\nmorning\n\n## Log\n";
+        let path = temp_note(base);
+        let snapshot = read_snapshot(&path).unwrap();
+        fs::write(&path, "# Day\n\nmorning\n\n## Log\n- 09:00 synced\n").unwrap();
+
+        let outcome = write_checked(
+            &path,
+            "# Day\n\nmorning coffee\n\n## Log\n",
+            Some(&snapshot.version),
+            Some(base),
+        )
+        .unwrap();
+
+        let expected = "# Day\n\nmorning coffee\n\n## Log\n- 09:00 synced\n";
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        assert_eq!(outcome.merged_content.as_deref(), Some(expected));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn touched_but_unchanged_file_is_not_a_conflict() {
+        let path = temp_note("same\n");
+        let mut version = read_snapshot(&path).unwrap().version;
+        version.mtime_ms = 1;
+
+        assert!(write_checked(&path, "new\n", Some(&version), None).is_ok());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
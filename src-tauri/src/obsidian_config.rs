@@ -0,0 +1,259 @@
+//! Reads note settings from the vault's `.obsidian` folder so Collector can
+//! follow what is configured in Obsidian instead of duplicating it.
+//!
+//! Sources, in order of precedence:
+//! - `.obsidian/plugins/periodic-notes/data.json` (Periodic Notes, when enabled)
+//! - `.obsidian/daily-notes.json` (core Daily Notes plugin)
+
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_DAILY_FORMAT: &str = "YYYY-MM-DD";
+const PERIODIC_NOTES_ID: &str = "periodic-notes";
+const DAILY_NOTES_ID: &str = "daily-notes";
+
+/// Where a configuration was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    DailyNotes,
+    PeriodicNotes,
+}
+
+/// Folder, filename format and template of one kind of periodic note, as
+/// configured in Obsidian. Empty Obsidian values are replaced by Obsidian's
+/// own defaults (format) or kept empty (vault root, no template).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct NoteConfig {
+    pub folder: String,
+    pub format: String,
+    pub template: String,
+    pub source: ConfigSource,
+}
+
+/// The vault's daily note config and the Collector settings that differ from it.
+#[derive(Debug, serde::Serialize)]
+pub struct DailyNoteStatus {
+    pub config: Option<NoteConfig>,
+    pub drift: Vec<&'static str>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawNoteConfig {
+    folder: Option<String>,
+    format: Option<String>,
+    template: Option<String>,
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawPeriodicNotes {
+    daily: Option<RawNoteConfig>,
+}
+
+/// `core-plugins.json` is a list of enabled ids in older Obsidian versions and
+/// an `{ id: enabled }` map in newer ones.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PluginList {
+    List(Vec<String>),
+    Map(serde_json::Map<String, serde_json::Value>),
+}
+
+impl PluginList {
+    fn contains(&self, id: &str) -> bool {
+        match self {
+            PluginList::List(ids) => ids.iter().any(|entry| entry == id),
+            PluginList::Map(map) => map.get(id).and_then(|v| v.as_bool()).unwrap_or(false),
+        }
+    }
+}
+
+/// The daily note configuration of the vault, or `None` if neither Periodic
+/// Notes nor the core Daily Notes plugin is active.
+pub fn daily_note_config(vault: &Path) -> Option<NoteConfig> {
+    let obsidian = vault.join(".obsidian");
+    if !obsidian.is_dir() {
+        return None;
+    }
+
+    if plugin_enabled(
+        &obsidian.join("community-plugins.json"),
+        PERIODIC_NOTES_ID,
+        false,
+    ) {
+        let periodic = read_json::<RawPeriodicNotes>(
+            &obsidian
+                .join("plugins")
+                .join(PERIODIC_NOTES_ID)
+                .join("data.json"),
+        );
+        if let Some(daily) = periodic.and_then(|p| p.daily) {
+            if daily.enabled.unwrap_or(false) {
+                return Some(note_config(daily, ConfigSource::PeriodicNotes));
+            }
+        }
+    }
+
+    // Ohne core-plugins.json gilt Obsidians Standard: Daily Notes ist aktiv.
+    if !plugin_enabled(&obsidian.join("core-plugins.json"), DAILY_NOTES_ID, true) {
+        return None;
+    }
+
+    let raw = read_json::<RawNoteConfig>(&obsidian.join("daily-notes.json")).unwrap_or_default();
+    Some(note_config(raw, ConfigSource::DailyNotes))
+}
+
+fn note_config(raw: RawNoteConfig, source: ConfigSource) -> NoteConfig {
+    let format = raw.format.unwrap_or_default().trim().to_string();
+    NoteConfig {
+        folder: raw.folder.unwrap_or_default().trim().to_string(),
+        format: if format.is_empty() {
+            DEFAULT_DAILY_FORMAT.to_string()
+        } else {
+            format
+        },
+        template: raw.template.unwrap_or_default().trim().to_string(),
+        source,
+    }
+}
+
+fn plugin_enabled(list_path: &Path, id: &str, missing_default: bool) -> bool {
+    if !list_path.exists() {
+        return missing_default;
+    }
+    read_json::<PluginList>(list_path)
+        .map(|plugins| plugins.contains(id))
+        .unwrap_or(missing_default)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!(
+                "Ignoring unreadable Obsidian config (file={}): {}",
+                crate::log_safety::redact_path(path),
+                e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_vault(files: &[(&str, &str)]) -> PathBuf {
+        let vault = crate::test_support::temp_dir("obsidian");
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        for (path, content) in files {
+            let path = vault.join(".obsidian").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        vault
+    }
+
+    #[test]
+    fn reads_core_daily_notes_config() {
+        let vault = temp_vault(&[(
+            "daily-notes.json",
+            r#"{"folder":"Journal/Daily","format":"YYYY-MM-DD dddd","template":"Templates/Daily"}"#,
+        )]);
+
+        let config = daily_note_config(&vault).unwrap();
+        assert_eq!(config.folder, "Journal/Daily");
+        assert_eq!(config.format, "YYYY-MM-DD dddd");
+        assert_eq!(config.template, "Templates/Daily");
+        assert_eq!(config.source, ConfigSource::DailyNotes);
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn core_plugin_defaults_when_unconfigured() {
+        let vault = temp_vault(&[("daily-notes.json", r#"{"format":""}"#)]);
+
+        let config = daily_note_config(&vault).unwrap();
+        assert_eq!(config.folder, "");
+        assert_eq!(config.format, "YYYY-MM-DD");
+        assert_eq!(config.template, "");
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn periodic_notes_take_precedence_when_enabled() {
+        let vault = temp_vault(&[
+            ("daily-notes.json", r#"{"folder":"Old"}"#),
+            ("community-plugins.json", r#"["periodic-notes"]"#),
+            (
+                "plugins/periodic-notes/data.json",
+                r#"{"daily":{"enabled":true,"folder":"Periodic/Daily","format":"YYYY/MM/YYYY-MM-DD","template":"T/Day.md"}}"#,
+            ),
+        ]);
+
+        let config = daily_note_config(&vault).unwrap();
+        assert_eq!(config.folder, "Periodic/Daily");
+        assert_eq!(config.format, "YYYY/MM/YYYY-MM-DD");
+        assert_eq!(config.source, ConfigSource::PeriodicNotes);
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn disabled_plugins_are_ignored() {
+        let vault = temp_vault(&[
+            ("daily-notes.json", r#"{"folder":"Journal"}"#),
+            (
+                "core-plugins.json",
+                r#"{"daily-notes":false,"templates":true}"#,
+            ),
+            ("community-plugins.json", r#"[]"#),
+            (
+                "plugins/periodic-notes/data.json",
+                r#"{"daily":{"enabled":true,"folder":"Periodic"}}"#,
+            ),
+        ]);
+
+        assert_eq!(daily_note_config(&vault), None);
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn periodic_notes_without_daily_fall_back_to_core() {
+        let vault = temp_vault(&[
+            ("core-plugins.json", r#"["file-explorer","daily-notes"]"#),
+            ("daily-notes.json", r#"{"folder":"Journal"}"#),
+            ("community-plugins.json", r#"["periodic-notes"]"#),
+            (
+                "plugins/periodic-notes/data.json",
+                r#"{"daily":{"enabled":false,"folder":"Periodic"}}"#,
+            ),
+        ]);
+
+        let config = daily_note_config(&vault).unwrap();
+        assert_eq!(config.folder, "Journal");
+        assert_eq!(config.source, ConfigSource::DailyNotes);
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn no_obsidian_folder_means_no_config() {
+        let vault = temp_vault(&[]);
+        fs::remove_dir_all(vault.join(".obsidian")).unwrap();
+
+        assert_eq!(daily_note_config(&vault), None);
+
+        let _ = fs::remove_dir_all(vault);
+    }
+}
@@ -0,0 +1,1581 @@
+//! Converts rich text from the clipboard (HTML, RTF) to Obsidian Markdown:
+//! headings, links, emphasis, nested lists, tables, code and remote images.
+//!
+//! Both formats are parsed into the same small element tree, which is then
+//! rendered as Markdown. No platform APIs, so it runs (and is tested) anywhere.
+
+// Nur die macOS-Zwischenablage ruft den Konverter auf
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+/// What `Copy Text to Collector` captures: Markdown converted from the HTML or
+/// RTF flavor, or `plain` if the rich text adds no formatting (code editors put
+/// colored but otherwise plain HTML on the clipboard).
+pub fn clipboard_to_markdown(plain: &str, html: Option<&str>, rtf: Option<&str>) -> String {
+    let markdown = match (html, rtf) {
+        (Some(html), _) if !html.trim().is_empty() => html_to_markdown(html),
+        (_, Some(rtf)) if !rtf.trim().is_empty() => rtf_to_markdown(rtf),
+        _ => return plain.to_string(),
+    };
+
+    if markdown.trim().is_empty() || same_words(&unescape(&markdown), plain) {
+        plain.to_string()
+    } else {
+        markdown
+    }
+}
+
+pub fn html_to_markdown(html: &str) -> String {
+    render(&parse_html(html))
+}
+
+pub fn rtf_to_markdown(rtf: &str) -> String {
+    render(&RtfParser::default().parse(rtf))
+}
+
+fn same_words(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+fn unescape(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut chars = markdown.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && next.is_ascii_punctuation() => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// =============================================================================
+// Element tree
+// =============================================================================
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Element(Element),
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Element {
+            name: name.to_string(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn with_children(name: &str, children: Vec<Node>) -> Self {
+        Element {
+            children,
+            ..Element::new(name)
+        }
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// A CSS property from the `style` attribute, lowercased.
+    fn style(&self, property: &str) -> Option<String> {
+        self.attr("style")?.split(';').find_map(|declaration| {
+            let (key, value) = declaration.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case(property)).then(|| value.trim().to_lowercase())
+        })
+    }
+
+    fn has_class(&self, class: &str) -> bool {
+        self.attr("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+}
+
+// =============================================================================
+// HTML parsing
+// =============================================================================
+
+/// Elements without content or end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is never shown as text.
+const SKIPPED: &[&str] = &["script", "style", "title", "noscript", "template", "svg"];
+
+/// How deep elements may nest. Rendering recurses per level, so deeper
+/// elements are flattened into their ancestor at this depth.
+const MAX_DEPTH: usize = 256;
+
+enum Tag {
+    Open {
+        name: String,
+        attrs: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    Close(String),
+    Other,
+}
+
+/// A forgiving parser for clipboard HTML: unknown end tags are ignored, open
+/// elements are closed at the end, and `<li>`, `<p>`, `<tr>` and `<td>` close
+/// the way browsers close them.
+fn parse_html(html: &str) -> Vec<Node> {
+    let mut stack = vec![Element::new("")];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map(|end| &after[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        if rest.starts_with('<') {
+            if let Some((tag, after)) = parse_tag(rest) {
+                rest = after;
+                match tag {
+                    Tag::Open { name, .. } if SKIPPED.contains(&name.as_str()) => {
+                        rest = skip_element(rest, &name);
+                    }
+                    Tag::Open {
+                        name,
+                        attrs,
+                        self_closing,
+                    } => {
+                        close_implied(&mut stack, &name);
+                        let element = Element {
+                            name,
+                            attrs,
+                            children: Vec::new(),
+                        };
+                        if self_closing || VOID.contains(&element.name.as_str()) {
+                            push_node(&mut stack, Node::Element(element));
+                        } else if stack.len() <= MAX_DEPTH {
+                            stack.push(element);
+                        }
+                    }
+                    Tag::Close(name) => {
+                        if let Some(index) = stack.iter().rposition(|e| e.name == name) {
+                            while index > 0 && stack.len() > index {
+                                close_top(&mut stack);
+                            }
+                        }
+                    }
+                    Tag::Other => {}
+                }
+                continue;
+            }
+        }
+
+        let first = rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        let end = rest[first..]
+            .find('<')
+            .map(|i| i + first)
+            .unwrap_or(rest.len());
+        push_text(&mut stack, &decode_entities(&rest[..end]));
+        rest = &rest[end..];
+    }
+
+    while stack.len() > 1 {
+        close_top(&mut stack);
+    }
+    stack.pop().map(|root| root.children).unwrap_or_default()
+}
+
+/// Parses the tag at the start of `input`. `None` if it isn't one, so the `<`
+/// is kept as text.
+fn parse_tag(input: &str) -> Option<(Tag, &str)> {
+    let rest = &input[1..];
+
+    if rest.starts_with(['!', '?']) {
+        let end = rest.find('>')?;
+        return Some((Tag::Other, &rest[end + 1..]));
+    }
+    if let Some(rest) = rest.strip_prefix('/') {
+        let end = rest.find('>')?;
+        let name = rest[..end].trim().to_ascii_lowercase();
+        return Some((Tag::Close(name), &rest[end + 1..]));
+    }
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+        .unwrap_or(rest.len());
+    let name = rest[..name_len].to_ascii_lowercase();
+    let mut rest = &rest[name_len..];
+    let mut attrs = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return Some((
+                Tag::Open {
+                    name,
+                    attrs,
+                    self_closing: true,
+                },
+                after,
+            ));
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return Some((
+                Tag::Open {
+                    name,
+                    attrs,
+                    self_closing: false,
+                },
+                after,
+            ));
+        }
+        if rest.is_empty() {
+            return None;
+        }
+
+        let key_len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len())
+            .max(1);
+        let key = rest[..key_len].to_ascii_lowercase();
+        rest = rest[key_len..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote)? + 1;
+                    (&after[1..end], &after[end + 1..])
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining;
+        }
+        attrs.push((key, value));
+    }
+}
+
+/// Skips to the end tag of `name`, e.g. past a `<style>` block.
+fn skip_element<'a>(rest: &'a str, name: &str) -> &'a str {
+    let Some(start) = rest.to_ascii_lowercase().find(&format!("</{}", name)) else {
+        return "";
+    };
+    match rest[start..].find('>') {
+        Some(end) => &rest[start + end + 1..],
+        None => "",
+    }
+}
+
+fn close_implied(stack: &mut Vec<Element>, name: &str) {
+    let (closes, boundaries): (&[&str], &[&str]) = match name {
+        "li" => (&["li"], &["ul", "ol"]),
+        "dt" | "dd" => (&["dt", "dd"], &["dl"]),
+        "tr" => (&["tr"], &["table"]),
+        "td" | "th" => (&["td", "th"], &["tr", "table"]),
+        "thead" | "tbody" | "tfoot" => (&["thead", "tbody", "tfoot"], &["table"]),
+        _ if is_block(name) => (&["p"], &[]),
+        _ => return,
+    };
+
+    let open = stack
+        .iter()
+        .rposition(|e| closes.contains(&e.name.as_str()) || boundaries.contains(&e.name.as_str()));
+    if let Some(index) = open.filter(|&i| i > 0 && closes.contains(&stack[i].name.as_str())) {
+        // <p> schließt nur, wenn es das innerste Element ist
+        if boundaries.is_empty() && index != stack.len() - 1 {
+            return;
+        }
+        while stack.len() > index {
+            close_top(stack);
+        }
+    }
+}
+
+fn close_top(stack: &mut Vec<Element>) {
+    if let Some(element) = stack.pop() {
+        push_node(stack, Node::Element(element));
+    }
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    let Some(parent) = stack.last_mut() else {
+        return;
+    };
+    match parent.children.last_mut() {
+        Some(Node::Text(existing)) => existing.push_str(text),
+        _ => parent.children.push(Node::Text(text.to_string())),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 32)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ensp" | "emsp" | "thinsp" => ' ',
+        "shy" | "zwj" | "zwnj" => '\u{ad}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "euro" => '€',
+        "times" => '×',
+        "rarr" => '→',
+        "larr" => '←',
+        "auml" => 'ä',
+        "ouml" => 'ö',
+        "uuml" => 'ü',
+        "Auml" => 'Ä',
+        "Ouml" => 'Ö',
+        "Uuml" => 'Ü',
+        "szlig" => 'ß',
+        "eacute" => 'é',
+        "egrave" => 'è',
+        "agrave" => 'à',
+        _ => return None,
+    })
+}
+
+// =============================================================================
+// RTF parsing
+// =============================================================================
+
+/// Destinations whose text is never shown.
+const RTF_SKIPPED: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "headerl",
+    "headerr",
+    "footer",
+    "footerl",
+    "footerr",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "pn",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RtfFormat {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RtfDest {
+    Text,
+    Skip,
+    FieldInstruction,
+    ListText,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RtfGroup {
+    format: RtfFormat,
+    dest: RtfDest,
+    /// Fallback characters after `\uN`.
+    uc: usize,
+}
+
+impl Default for RtfGroup {
+    fn default() -> Self {
+        RtfGroup {
+            format: RtfFormat::default(),
+            dest: RtfDest::Text,
+            uc: 1,
+        }
+    }
+}
+
+struct RtfField {
+    depth: usize,
+    instruction: String,
+    result_start: Option<usize>,
+}
+
+struct RtfListItem {
+    ordered: bool,
+    level: usize,
+    start: Option<u32>,
+    nodes: Vec<Node>,
+}
+
+/// Turns RTF into the element tree: paragraphs (one line each, like TextEdit
+/// shows them), bold/italic/strikethrough runs, `HYPERLINK` fields, lists from
+/// `\listtext` markers and tables from `\cell` / `\row`.
+#[derive(Default)]
+struct RtfParser {
+    groups: Vec<RtfGroup>,
+    nodes: Vec<Node>,
+    paragraph: Vec<Node>,
+    run: String,
+    run_format: RtfFormat,
+    skip_chars: usize,
+    pending_surrogate: Option<u16>,
+    fields: Vec<RtfField>,
+    list_text: String,
+    list_marker: Option<String>,
+    list_level: usize,
+    list_items: Vec<RtfListItem>,
+    in_table: bool,
+    cell: Vec<Vec<Node>>,
+    row: Vec<Node>,
+    rows: Vec<Node>,
+}
+
+impl RtfParser {
+    fn parse(mut self, rtf: &str) -> Vec<Node> {
+        self.groups.push(RtfGroup::default());
+        let mut chars = rtf.chars().peekable();
+        let mut ignorable = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let group = *self.group();
+                    self.groups.push(group);
+                }
+                '}' => self.close_group(),
+                '\\' => match chars.peek().copied() {
+                    Some(letter) if letter.is_ascii_alphabetic() => {
+                        let mut word = String::new();
+                        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                            word.push(c);
+                            chars.next();
+                        }
+                        let mut param = String::new();
+                        if chars.peek() == Some(&'-') {
+                            param.push('-');
+                            chars.next();
+                        }
+                        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                            param.push(c);
+                            chars.next();
+                        }
+                        if chars.peek() == Some(&' ') {
+                            chars.next();
+                        }
+                        let param = param.parse::<i32>().ok();
+                        self.control_word(&word, param, ignorable);
+                        ignorable = false;
+                    }
+                    Some('\'') => {
+                        chars.next();
+                        let hex: String = chars.by_ref().take(2).collect();
+                        if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                            self.text_char(decode_cp1252(byte));
+                        }
+                    }
+                    Some('*') => {
+                        chars.next();
+                        ignorable = true;
+                    }
+                    Some('\n' | '\r') => {
+                        chars.next();
+                        self.end_paragraph();
+                    }
+                    Some(symbol) => {
+                        chars.next();
+                        match symbol {
+                            '~' => self.text_char('\u{a0}'),
+                            '_' => self.text_char('-'),
+                            '\\' | '{' | '}' => self.text_char(symbol),
+                            _ => {}
+                        }
+                    }
+                    None => {}
+                },
+                '\n' | '\r' => {}
+                _ => self.text_char(c),
+            }
+        }
+
+        if !self.run.is_empty() || !self.paragraph.is_empty() {
+            self.end_paragraph();
+        }
+        self.flush_table();
+        self.flush_list();
+        self.nodes
+    }
+
+    fn group(&mut self) -> &mut RtfGroup {
+        if self.groups.is_empty() {
+            self.groups.push(RtfGroup::default());
+        }
+        self.groups.last_mut().expect("group stack is never empty")
+    }
+
+    fn close_group(&mut self) {
+        let closed = self.groups.pop().unwrap_or_default();
+        let depth = self.groups.len();
+
+        if closed.dest == RtfDest::ListText && self.group().dest != RtfDest::ListText {
+            self.list_marker = Some(std::mem::take(&mut self.list_text));
+        }
+
+        while self.fields.last().is_some_and(|field| field.depth > depth) {
+            let field = self.fields.pop().expect("checked above");
+            self.flush_run();
+            let (Some(start), Some(href)) = (field.result_start, hyperlink(&field.instruction))
+            else {
+                continue;
+            };
+            let start = start.min(self.paragraph.len());
+            let children = self.paragraph.split_off(start);
+            let mut link = Element::with_children("a", children);
+            link.attrs.push(("href".to_string(), href));
+            self.paragraph.push(Node::Element(link));
+        }
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>, ignorable: bool) {
+        let on = param != Some(0);
+        match word {
+            "par" | "sect" => self.end_paragraph(),
+            "line" => {
+                self.flush_run();
+                self.paragraph.push(Node::Element(Element::new("br")));
+            }
+            "tab" => self.text_char('\t'),
+            "emdash" => self.text_char('—'),
+            "endash" => self.text_char('–'),
+            "bullet" => self.text_char('•'),
+            "lquote" => self.text_char('‘'),
+            "rquote" => self.text_char('’'),
+            "ldblquote" => self.text_char('“'),
+            "rdblquote" => self.text_char('”'),
+            "u" => {
+                if let Some(code) = param {
+                    self.unicode_char(if code < 0 { code + 65536 } else { code } as u32);
+                }
+                self.skip_chars = self.group().uc;
+            }
+            "uc" => self.group().uc = param.unwrap_or(1).max(0) as usize,
+            "b" => self.group().format.bold = on,
+            "i" => self.group().format.italic = on,
+            "strike" | "striked" => self.group().format.strike = on,
+            "plain" => self.group().format = RtfFormat::default(),
+            "pard" => {
+                self.in_table = false;
+                self.list_level = 0;
+            }
+            "intbl" => self.in_table = true,
+            // RTF kennt nur die Ebenen 0 bis 8
+            "ilvl" => self.list_level = param.unwrap_or(0).clamp(0, 8) as usize,
+            "cell" => self.end_cell(),
+            "row" => self.end_row(),
+            // Verschachtelte Links rendern rekursiv, daher wie HTML begrenzen
+            "field" if self.fields.len() >= MAX_DEPTH => {}
+            "field" => {
+                self.flush_run();
+                let depth = self.groups.len();
+                self.fields.push(RtfField {
+                    depth,
+                    instruction: String::new(),
+                    result_start: None,
+                });
+            }
+            "fldinst" => self.group().dest = RtfDest::FieldInstruction,
+            "fldrslt" => {
+                self.flush_run();
+                let start = self.paragraph.len();
+                if let Some(field) = self.fields.last_mut() {
+                    field.result_start = Some(start);
+                }
+            }
+            "listtext" | "pntext" => {
+                self.list_text.clear();
+                self.group().dest = RtfDest::ListText;
+            }
+            _ if ignorable || RTF_SKIPPED.contains(&word) => self.group().dest = RtfDest::Skip,
+            _ => {}
+        }
+    }
+
+    fn unicode_char(&mut self, code: u32) {
+        match code {
+            0xD800..=0xDBFF => self.pending_surrogate = Some(code as u16),
+            0xDC00..=0xDFFF => {
+                if let Some(high) = self.pending_surrogate.take() {
+                    let units = [high, code as u16];
+                    if let Some(Ok(c)) = char::decode_utf16(units).next() {
+                        self.push_char(c);
+                    }
+                }
+            }
+            _ => {
+                if let Some(c) = char::from_u32(code) {
+                    self.push_char(c);
+                }
+            }
+        }
+    }
+
+    /// A character from the document, unless it is a `\uN` fallback.
+    fn text_char(&mut self, c: char) {
+        if self.skip_chars > 0 {
+            self.skip_chars -= 1;
+            return;
+        }
+        self.push_char(c);
+    }
+
+    fn push_char(&mut self, c: char) {
+        let group = *self.group();
+        match group.dest {
+            RtfDest::Skip => {}
+            RtfDest::FieldInstruction => {
+                if let Some(field) = self.fields.last_mut() {
+                    field.instruction.push(c);
+                }
+            }
+            RtfDest::ListText => self.list_text.push(c),
+            RtfDest::Text => {
+                if !self.run.is_empty() && group.format != self.run_format {
+                    self.flush_run();
+                }
+                self.run_format = group.format;
+                self.run.push(c);
+            }
+        }
+    }
+
+    fn flush_run(&mut self) {
+        if self.run.is_empty() {
+            return;
+        }
+
+        let mut node = Node::Text(std::mem::take(&mut self.run));
+        let format = self.run_format;
+        for (enabled, name) in [
+            (format.strike, "s"),
+            (format.italic, "i"),
+            (format.bold, "b"),
+        ] {
+            if enabled {
+                node = Node::Element(Element::with_children(name, vec![node]));
+            }
+        }
+        self.paragraph.push(node);
+    }
+
+    fn end_paragraph(&mut self) {
+        self.flush_run();
+        let nodes = std::mem::take(&mut self.paragraph);
+
+        if self.in_table {
+            self.cell.push(nodes);
+            return;
+        }
+        self.flush_table();
+
+        match self.list_marker.take() {
+            Some(marker) => {
+                let marker = marker.trim();
+                let number: String = marker.chars().filter(char::is_ascii_digit).collect();
+                self.list_items.push(RtfListItem {
+                    ordered: !number.is_empty(),
+                    level: self.list_level,
+                    start: number.parse().ok(),
+                    nodes,
+                });
+            }
+            None => {
+                self.flush_list();
+                // Jeder Absatz ist eine Zeile; leere Absätze sind Leerzeilen
+                let nodes = if nodes.is_empty() {
+                    vec![Node::Element(Element::new("br"))]
+                } else {
+                    nodes
+                };
+                self.nodes
+                    .push(Node::Element(Element::with_children("div", nodes)));
+            }
+        }
+    }
+
+    fn end_cell(&mut self) {
+        self.flush_run();
+        if !self.paragraph.is_empty() {
+            let nodes = std::mem::take(&mut self.paragraph);
+            self.cell.push(nodes);
+        }
+
+        let mut children = Vec::new();
+        for (index, paragraph) in std::mem::take(&mut self.cell).into_iter().enumerate() {
+            if index > 0 {
+                children.push(Node::Element(Element::new("br")));
+            }
+            children.extend(paragraph);
+        }
+        self.row
+            .push(Node::Element(Element::with_children("td", children)));
+    }
+
+    fn end_row(&mut self) {
+        let cells = std::mem::take(&mut self.row);
+        self.rows
+            .push(Node::Element(Element::with_children("tr", cells)));
+    }
+
+    fn flush_table(&mut self) {
+        if !self.rows.is_empty() {
+            self.flush_list();
+            let rows = std::mem::take(&mut self.rows);
+            self.nodes
+                .push(Node::Element(Element::with_children("table", rows)));
+        }
+    }
+
+    fn flush_list(&mut self) {
+        if !self.list_items.is_empty() {
+            let items = std::mem::take(&mut self.list_items);
+            self.nodes.push(Node::Element(nest_list(&items)));
+        }
+    }
+}
+
+/// Builds a (nested) `ul` / `ol` from list paragraphs with their levels.
+fn nest_list(items: &[RtfListItem]) -> Element {
+    let first = &items[0];
+    let mut list = Element::new(if first.ordered { "ol" } else { "ul" });
+    if let Some(start) = first.start.filter(|&n| first.ordered && n != 1) {
+        list.attrs.push(("start".to_string(), start.to_string()));
+    }
+
+    let mut index = 0;
+    while index < items.len() {
+        let mut item = Element::with_children("li", items[index].nodes.clone());
+        let nested_end = items[index + 1..]
+            .iter()
+            .position(|nested| nested.level <= first.level)
+            .map(|offset| index + 1 + offset)
+            .unwrap_or(items.len());
+        if nested_end > index + 1 {
+            let nested = nest_list(&items[index + 1..nested_end]);
+            item.children.push(Node::Element(nested));
+        }
+        list.children.push(Node::Element(item));
+        index = nested_end;
+    }
+
+    list
+}
+
+/// The URL of a `HYPERLINK "https://…"` field instruction.
+fn hyperlink(instruction: &str) -> Option<String> {
+    let rest = instruction.trim().strip_prefix("HYPERLINK")?.trim_start();
+    let url = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    Some(url.to_string()).filter(|url| !url.is_empty())
+}
+
+fn decode_cp1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+// =============================================================================
+// Markdown rendering
+// =============================================================================
+
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+fn is_block(name: &str) -> bool {
+    BLOCKS.contains(&name)
+}
+
+/// A rendered block. Tight blocks are lines (`<div>` in mail, RTF paragraphs)
+/// and follow each other without a blank line.
+struct Block {
+    text: String,
+    tight: bool,
+}
+
+fn render(nodes: &[Node]) -> String {
+    let mut blocks = Vec::new();
+    render_blocks(nodes, false, &mut blocks);
+    join_blocks(&blocks).trim_matches('\n').to_string()
+}
+
+fn join_blocks(blocks: &[Block]) -> String {
+    let is_line = |block: Option<&Block>| block.is_some_and(|b| b.tight && !b.text.is_empty());
+    let mut out = String::new();
+    let mut previous: Option<&Block> = None;
+
+    for (index, block) in blocks.iter().enumerate() {
+        // Leerzeilen nur zwischen zwei Zeilen, nie doppelt
+        if block.text.is_empty() && !(is_line(previous) && is_line(blocks.get(index + 1))) {
+            continue;
+        }
+        if let Some(previous) = previous {
+            out.push_str(if previous.tight && block.tight {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        out.push_str(&block.text);
+        previous = Some(block);
+    }
+
+    out
+}
+
+fn render_blocks(nodes: &[Node], tight: bool, blocks: &mut Vec<Block>) {
+    let mut start = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        if is_block(&element.name) || contains_block(element) {
+            push_paragraph(&nodes[start..index], tight, blocks);
+            render_block(element, blocks);
+            start = index + 1;
+        }
+    }
+    push_paragraph(&nodes[start..], tight, blocks);
+}
+
+/// Inline elements around blocks (`<b>` around a whole Google Docs document)
+/// are rendered like containers.
+fn contains_block(element: &Element) -> bool {
+    element.children.iter().any(|child| match child {
+        Node::Element(child) => is_block(&child.name) || contains_block(child),
+        Node::Text(_) => false,
+    })
+}
+
+fn push_paragraph(nodes: &[Node], tight: bool, blocks: &mut Vec<Block>) {
+    let text = clean_lines(&inline_text(nodes));
+    let blank_line = tight
+        && nodes
+            .iter()
+            .any(|node| matches!(node, Node::Element(e) if e.name == "br"));
+    if !text.is_empty() || blank_line {
+        blocks.push(Block { text, tight });
+    }
+}
+
+fn render_block(element: &Element, blocks: &mut Vec<Block>) {
+    let mut push = |text: String| {
+        if !text.is_empty() {
+            blocks.push(Block { text, tight: false });
+        }
+    };
+
+    match element.name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = element.name[1..].parse().unwrap_or(1);
+            let text = clean_lines(&inline_text(&element.children)).replace('\n', " ");
+            if !text.is_empty() {
+                push(format!("{} {}", "#".repeat(level), text));
+            }
+        }
+        "ul" | "ol" => push(render_list(element)),
+        "li" => push(list_item(&element.children, "- ")),
+        "pre" => push(render_code_block(element)),
+        "table" => render_table(element, blocks),
+        "blockquote" => {
+            let quoted: Vec<String> = render(&element.children)
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {}", line)
+                    }
+                })
+                .collect();
+            push(quoted.join("\n"));
+        }
+        "hr" => push("---".to_string()),
+        "div" | "dt" | "dd" => render_blocks(&element.children, true, blocks),
+        _ => render_blocks(&element.children, false, blocks),
+    }
+}
+
+fn render_list(list: &Element) -> String {
+    let ordered = list.name == "ol";
+    let mut number: u32 = list.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+    let mut items: Vec<String> = Vec::new();
+    let mut last_marker_width = 2;
+
+    for child in &list.children {
+        match child {
+            Node::Element(item) if item.name == "li" => {
+                let marker = if ordered {
+                    number += 1;
+                    format!("{}. ", number - 1)
+                } else {
+                    "- ".to_string()
+                };
+                last_marker_width = marker.len();
+                items.push(list_item(&item.children, &marker));
+            }
+            // Google Docs und Word setzen verschachtelte Listen neben das <li>
+            Node::Element(nested) if nested.name == "ul" || nested.name == "ol" => {
+                let nested = indent(&render_list(nested), last_marker_width);
+                match items.last_mut() {
+                    Some(last) => {
+                        last.push('\n');
+                        last.push_str(&nested);
+                    }
+                    None => items.push(nested),
+                }
+            }
+            other => {
+                let text = clean_lines(&inline_text(std::slice::from_ref(other)));
+                if !text.is_empty() {
+                    items.push(list_item(std::slice::from_ref(other), "- "));
+                }
+            }
+        }
+    }
+
+    items.join("\n")
+}
+
+fn list_item(children: &[Node], marker: &str) -> String {
+    let mut blocks = Vec::new();
+    render_blocks(children, true, &mut blocks);
+    let content: Vec<&str> = blocks
+        .iter()
+        .map(|block| block.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    if content.is_empty() {
+        return marker.trim_end().to_string();
+    }
+    format!(
+        "{}{}",
+        marker,
+        indent(&content.join("\n"), marker.len()).trim_start()
+    )
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let padding = " ".repeat(width);
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", padding, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_code_block(pre: &Element) -> String {
+    let mut code = raw_text(&pre.children);
+    if code.starts_with('\n') {
+        code.remove(0);
+    }
+    let code = code.trim_end_matches(['\n', ' ']);
+
+    let language = std::iter::once(pre)
+        .chain(pre.children.iter().filter_map(|child| match child {
+            Node::Element(e) if e.name == "code" => Some(e),
+            _ => None,
+        }))
+        .find_map(|element| {
+            element.attr("class")?.split_whitespace().find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+                    .map(str::to_string)
+            })
+        })
+        .unwrap_or_default();
+
+    let fence = "`".repeat((longest_run(code, '`') + 1).max(3));
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn render_table(table: &Element, blocks: &mut Vec<Block>) {
+    let mut rows: Vec<Vec<&Element>> = Vec::new();
+    collect_rows(table, &mut rows);
+
+    let colspan = |cell: &Element| -> usize {
+        cell.attr("colspan")
+            .and_then(|span| span.parse().ok())
+            .unwrap_or(1usize)
+            .clamp(1, 50)
+    };
+    let width = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| colspan(cell)).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+
+    // Einspaltige Tabellen sind Layout (Newsletter), keine Daten
+    if width <= 1 {
+        for cell in rows.iter().flatten() {
+            render_blocks(&cell.children, true, blocks);
+        }
+        return;
+    }
+
+    let mut lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let mut cells: Vec<String> = Vec::new();
+            for cell in row {
+                cells.push(table_cell(cell));
+                cells.extend((1..colspan(cell)).map(|_| String::new()));
+            }
+            cells.resize(width, String::new());
+            format!("| {} |", cells.join(" | "))
+        })
+        .collect();
+    lines.insert(1, format!("|{}|", vec![" --- "; width].join("|")));
+
+    blocks.push(Block {
+        text: lines.join("\n"),
+        tight: false,
+    });
+}
+
+fn collect_rows<'a>(element: &'a Element, rows: &mut Vec<Vec<&'a Element>>) {
+    for child in &element.children {
+        let Node::Element(child) = child else {
+            continue;
+        };
+        match child.name.as_str() {
+            "tr" => rows.push(
+                child
+                    .children
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        Node::Element(cell) if cell.name == "td" || cell.name == "th" => Some(cell),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+fn table_cell(cell: &Element) -> String {
+    let mut blocks = Vec::new();
+    render_blocks(&cell.children, true, &mut blocks);
+    join_blocks(&blocks)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("<br>")
+        .replace('|', "\\|")
+}
+
+// =============================================================================
+// Inline rendering
+// =============================================================================
+
+fn inline_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        inline_node(node, &mut out);
+    }
+    out
+}
+
+fn inline_node(node: &Node, out: &mut String) {
+    let element = match node {
+        Node::Text(text) => {
+            out.push_str(&escape(&collapse_whitespace(text)));
+            return;
+        }
+        Node::Element(element) => element,
+    };
+    let children = || inline_text(&element.children);
+
+    match element.name.as_str() {
+        "br" if element.has_class("Apple-interchange-newline") => {}
+        "br" => out.push('\n'),
+        // Google Docs packt alles in <b style="font-weight:normal">
+        "b" | "strong" if element.style("font-weight").as_deref() == Some("normal") => {
+            out.push_str(&children())
+        }
+        "b" | "strong" => out.push_str(&emphasize(&children(), "**")),
+        "i" | "em" | "cite" | "dfn" => out.push_str(&emphasize(&children(), "*")),
+        "s" | "del" | "strike" => out.push_str(&emphasize(&children(), "~~")),
+        "mark" => out.push_str(&emphasize(&children(), "==")),
+        "code" | "kbd" | "samp" | "tt" => out.push_str(&code_span(&raw_text(&element.children))),
+        "a" => out.push_str(&link(element, &children())),
+        "img" => out.push_str(&image(element)),
+        "input" if element.attr("type") == Some("checkbox") => {
+            out.push_str(if element.attr("checked").is_some() {
+                "[x] "
+            } else {
+                "[ ] "
+            });
+        }
+        "span" => {
+            let mut text = children();
+            if element
+                .style("text-decoration")
+                .or_else(|| element.style("text-decoration-line"))
+                .is_some_and(|value| value.contains("line-through"))
+            {
+                text = emphasize(&text, "~~");
+            }
+            if element.style("font-style").as_deref() == Some("italic") {
+                text = emphasize(&text, "*");
+            }
+            if element.style("font-weight").is_some_and(|weight| {
+                matches!(
+                    weight.as_str(),
+                    "bold" | "bolder" | "600" | "700" | "800" | "900"
+                )
+            }) {
+                text = emphasize(&text, "**");
+            }
+            out.push_str(&text);
+        }
+        _ => out.push_str(&children()),
+    }
+}
+
+/// Wraps every line of `inner` in `marker`, keeping surrounding spaces outside:
+/// `**bold** text`, never `**bold **text`.
+fn emphasize(inner: &str, marker: &str) -> String {
+    inner
+        .split('\n')
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                return line.to_string();
+            }
+            let lead = if line.starts_with(char::is_whitespace) {
+                " "
+            } else {
+                ""
+            };
+            let trail = if line.ends_with(char::is_whitespace) {
+                " "
+            } else {
+                ""
+            };
+            format!("{}{}{}{}{}", lead, marker, trimmed, marker, trail)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn link(element: &Element, text: &str) -> String {
+    let href = element.attr("href").unwrap_or("").trim();
+    let label = text.trim().replace('\n', " ");
+    if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+        return text.to_string();
+    }
+    if label.is_empty() {
+        return String::new();
+    }
+
+    let lead = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trail = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    // Angezeigte URL = Ziel: Obsidian verlinkt nackte URLs selbst
+    if unescape(&label) == href {
+        return format!("{}{}{}", lead, href, trail);
+    }
+    format!("{}[{}]({}){}", lead, label, destination(href), trail)
+}
+
+/// Remote images become embeds; data URIs, relative paths and tracking pixels
+/// are dropped.
+fn image(element: &Element) -> String {
+    let src = element.attr("src").unwrap_or("").trim();
+    let remote = src.starts_with("https://") || src.starts_with("http://");
+    let pixel = ["width", "height"]
+        .iter()
+        .any(|size| matches!(element.attr(size), Some("0" | "1")));
+    if !remote || pixel {
+        return String::new();
+    }
+
+    let alt = escape(&collapse_whitespace(element.attr("alt").unwrap_or("")));
+    format!("![{}]({})", alt.trim(), destination(src))
+}
+
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+fn code_span(code: &str) -> String {
+    let code = collapse_whitespace(code);
+    if code.trim().is_empty() {
+        return code;
+    }
+
+    let fence = "`".repeat(longest_run(&code, '`') + 1);
+    let pad = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", fence, pad, code, pad, fence)
+}
+
+/// Text content with whitespace kept, `<br>` as line breaks.
+fn raw_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(e) if e.name == "br" => out.push('\n'),
+            Node::Element(e) => {
+                out.push_str(&raw_text(&e.children));
+                // Zeilen als <div> (Editoren) enden mit einem Umbruch
+                if is_block(&e.name) && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars().filter(|&c| c != '\u{ad}') {
+        if c.is_whitespace() {
+            if !space {
+                out.push(' ');
+            }
+            space = true;
+        } else {
+            out.push(c);
+            space = false;
+        }
+    }
+    out
+}
+
+/// Escapes characters that would turn plain text into Markdown syntax.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (index, &c) in chars.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|i| chars.get(i));
+        let next = chars.get(index + 1);
+        let escaped = match c {
+            '\\' | '*' | '`' | '[' | ']' => true,
+            // snake_case bleibt lesbar
+            '_' => {
+                !(previous.is_some_and(|c| c.is_alphanumeric())
+                    && next.is_some_and(|c| c.is_alphanumeric()))
+            }
+            '<' => next.is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!')),
+            _ => false,
+        };
+        if escaped {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+/// Trims every line and collapses the spaces left where elements met.
+fn clean_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            line.split(' ')
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clipboard contents saved from real apps and the Markdown they become.
+    const HTML_FIXTURES: &[(&str, &str, &str)] = &[
+        (
+            "safari_article",
+            include_str!("../tests/fixtures/rich_text/safari_article.html"),
+            include_str!("../tests/fixtures/rich_text/safari_article.md"),
+        ),
+        (
+            "mail_message",
+            include_str!("../tests/fixtures/rich_text/mail_message.html"),
+            include_str!("../tests/fixtures/rich_text/mail_message.md"),
+        ),
+        (
+            "google_docs",
+            include_str!("../tests/fixtures/rich_text/google_docs.html"),
+            include_str!("../tests/fixtures/rich_text/google_docs.md"),
+        ),
+        (
+            "table",
+            include_str!("../tests/fixtures/rich_text/table.html"),
+            include_str!("../tests/fixtures/rich_text/table.md"),
+        ),
+    ];
+
+    const RTF_FIXTURES: &[(&str, &str, &str)] = &[
+        (
+            "textedit",
+            include_str!("../tests/fixtures/rich_text/textedit.rtf"),
+            include_str!("../tests/fixtures/rich_text/textedit.md"),
+        ),
+        (
+            "word_table",
+            include_str!("../tests/fixtures/rich_text/word_table.rtf"),
+            include_str!("../tests/fixtures/rich_text/word_table.md"),
+        ),
+    ];
+
+    #[test]
+    fn converts_html_fixtures() {
+        for (name, html, expected) in HTML_FIXTURES {
+            assert_eq!(
+                html_to_markdown(html),
+                expected.trim_end(),
+                "fixture {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn converts_rtf_fixtures() {
+        for (name, rtf, expected) in RTF_FIXTURES {
+            assert_eq!(
+                rtf_to_markdown(rtf),
+                expected.trim_end(),
+                "fixture {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_text_that_looks_like_markdown() {
+        assert_eq!(
+            html_to_markdown("<p>2 * 3 = [six] in my_var and _this_ &lt;div&gt;</p>"),
+            r"2 \* 3 = \[six\] in my_var and \_this\_ \<div>"
+        );
+        assert_eq!(
+            html_to_markdown("<p>Use <code>a `b` c</code> and <code>`x</code></p>"),
+            "Use ``a `b` c`` and `` `x ``"
+        );
+    }
+
+    #[test]
+    fn keeps_plain_text_when_rich_text_adds_nothing() {
+        let plain = "fn main() {\n    let a_b = 2 * 3;\n}";
+        let html =
+            "<div style=\"color:#333\"><div><span style=\"color:red\">fn</span> main() {</div>\
+                    <div>    let a_b = 2 * 3;</div><div>}</div></div>";
+        assert_eq!(clipboard_to_markdown(plain, Some(html), None), plain);
+
+        let html = "<pre><div>fn main() {</div><div>    let a_b = 2 * 3;</div><div>}</div></pre>";
+        assert_eq!(
+            clipboard_to_markdown(plain, Some(html), None),
+            format!("```\n{}\n```", plain)
+        );
+
+        let html = "<div><span>fn</span> main() <b>{</b></div>";
+        assert_eq!(
+            clipboard_to_markdown("fn main() {", Some(html), None),
+            "fn main() **{**"
+        );
+        assert_eq!(clipboard_to_markdown("plain", None, None), "plain");
+    }
+
+    #[test]
+    fn flattens_deeply_nested_markup() {
+        let nestings = [
+            ("<span>", "</span>"),
+            ("<b><i>", "</i></b>"),
+            ("<ul><li>", "</li></ul>"),
+            ("<blockquote>", "</blockquote>"),
+        ];
+        for (open, close) in nestings {
+            let html = format!("{}deep{}", open.repeat(5000), close.repeat(5000));
+            assert!(html_to_markdown(&html).contains("deep"), "{}", open);
+        }
+
+        let rtf = format!(
+            "{{\\rtf1 {}{}}}",
+            "{\\field{\\*\\fldinst HYPERLINK \"https://a.example\"}{\\fldrslt ".repeat(5000),
+            "deep}}".repeat(5000)
+        );
+        assert!(rtf_to_markdown(&rtf).contains("deep"));
+    }
+
+    #[test]
+    fn tolerates_broken_html() {
+        assert_eq!(
+            html_to_markdown("<ul><li>one<li>two <b>bold</ul><p>a < b & c"),
+            "- one\n- two **bold**\n\na < b & c"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Rule-based routing for captures from the capture window: `todo: call Bob`
+//! can go to `Tasks.md` under `## Inbox`, `#idea …` to `Ideas.md`, and
+//! everything no rule matches to the daily note.
+
+use regex::Regex;
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::capture_queue::CaptureTarget;
+use crate::settings::{CaptureRule, EntryStyle, Period, RuleMatch, RuleTarget, Settings};
+
+/// Where a capture goes and what is written there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoutedCapture {
+    /// Index of the matching rule; `None` means the daily note fallback.
+    pub rule: Option<usize>,
+    pub target: CaptureTarget,
+    pub text: String,
+}
+
+/// Checks `rules` in order against `text`. The first match decides the target.
+pub fn route(rules: &[CaptureRule], text: &str) -> Result<RoutedCapture, String> {
+    for (index, rule) in rules.iter().enumerate() {
+        let Some(matched) = find_match(rule.match_type, &rule.pattern, text)? else {
+            continue;
+        };
+
+        let text = if rule.strip_prefix {
+            strip_match(text, matched)
+        } else {
+            text.trim().to_string()
+        };
+        if text.is_empty() {
+            return Err(format!(
+                "Nothing left to capture after removing '{}'",
+                rule.pattern.trim()
+            ));
+        }
+
+        let heading = Some(rule.heading.trim().to_string()).filter(|h| !h.is_empty());
+        let (target, heading) = match &rule.target {
+            RuleTarget::DailyNote => (CaptureTarget::DailyNote { date: None }, heading),
+            RuleTarget::PeriodicNote { period } => {
+                (CaptureTarget::PeriodicNote { period: *period }, heading)
+            }
+            RuleTarget::Note { path } => (
+                CaptureTarget::Note {
+                    path: path.trim().to_string(),
+                    heading,
+                },
+                None,
+            ),
+        };
+
+        return Ok(RoutedCapture {
+            rule: Some(index),
+            target: CaptureTarget::Routed {
+                target: Box::new(target),
+                heading,
+                entry_style: rule.entry_style,
+            },
+            text,
+        });
+    }
+
+    Ok(RoutedCapture {
+        rule: None,
+        target: CaptureTarget::DailyNote { date: None },
+        text: text.trim().to_string(),
+    })
+}
+
+/// The settings a routed capture is written with: the rule's entry style and
+/// heading replace the ones configured for the daily or periodic note.
+pub fn routed_settings(
+    settings: &Settings,
+    target: &CaptureTarget,
+    heading: Option<&str>,
+    entry_style: Option<EntryStyle>,
+) -> Settings {
+    let mut settings = settings.clone();
+
+    if let Some(heading) = heading {
+        match target {
+            CaptureTarget::DailyNote { .. } => settings.daily_note_heading = heading.to_string(),
+            CaptureTarget::PeriodicNote { period } => {
+                periodic_note_mut(&mut settings, *period).heading = heading.to_string()
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(style) = entry_style {
+        match target {
+            CaptureTarget::DailyNote { .. } => settings.daily_note_entry_style = style,
+            CaptureTarget::PeriodicNote { period } => {
+                periodic_note_mut(&mut settings, *period).entry_style = style
+            }
+            CaptureTarget::Note { .. } => settings.append_to_note_entry_style = style,
+            CaptureTarget::NewNote { .. } | CaptureTarget::Routed { .. } => {}
+        }
+    }
+
+    settings
+}
+
+fn periodic_note_mut(
+    settings: &mut Settings,
+    period: Period,
+) -> &mut crate::settings::PeriodicNote {
+    match period {
+        Period::Week => &mut settings.weekly_note,
+        Period::Month => &mut settings.monthly_note,
+        Period::Quarter => &mut settings.quarterly_note,
+        Period::Year => &mut settings.yearly_note,
+    }
+}
+
+pub fn validate_pattern(match_type: RuleMatch, pattern: &str) -> Result<(), String> {
+    if pattern.trim().is_empty() {
+        return Err("capture_rules entries must include a pattern".to_string());
+    }
+
+    match match_type {
+        RuleMatch::Prefix => Ok(()),
+        RuleMatch::Keyword if pattern.trim().contains(char::is_whitespace) => {
+            Err("capture_rules keywords must be a single word".to_string())
+        }
+        RuleMatch::Keyword => Ok(()),
+        RuleMatch::Regex => Regex::new(pattern)
+            .map(|_| ())
+            .map_err(|e| format!("capture_rules pattern is not a valid regex: {}", e)),
+    }
+}
+
+/// Byte range of the match in `text`. Prefixes and keywords ignore case.
+fn find_match(
+    match_type: RuleMatch,
+    pattern: &str,
+    text: &str,
+) -> Result<Option<Range<usize>>, String> {
+    validate_pattern(match_type, pattern)?;
+    let needle = pattern.trim().to_lowercase();
+
+    Ok(match match_type {
+        RuleMatch::Prefix => {
+            let start = text.len() - text.trim_start().len();
+            let end = start + pattern.trim().len();
+            text.get(start..end)
+                .filter(|head| head.to_lowercase() == needle)
+                .map(|_| start..end)
+        }
+        RuleMatch::Keyword => words(text).find_map(|(start, word)| {
+            let word = word.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            (word.to_lowercase() == needle).then(|| start..start + word.len())
+        }),
+        RuleMatch::Regex => Regex::new(pattern)
+            .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?
+            .find(text)
+            .map(|found| found.range()),
+    })
+}
+
+/// Whitespace-separated words with their byte offsets. Separators can be
+/// longer than one byte (a no-break space is two).
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Whitespace within a line.
+fn is_blank(c: char) -> bool {
+    c.is_whitespace() && !matches!(c, '\n' | '\r')
+}
+
+/// Removes `range` from `text` without leaving doubled spaces behind.
+fn strip_match(text: &str, range: Range<usize>) -> String {
+    let mut before = &text[..range.start];
+    let mut after = &text[range.end..];
+
+    if before.is_empty() || before.ends_with(is_blank) {
+        after = after.trim_start_matches(is_blank);
+    }
+    if after.is_empty() || after.starts_with(['\n', '\r', '.', ',', ';', ':', '!', '?']) {
+        before = before.trim_end_matches(is_blank);
+    }
+
+    format!("{}{}", before, after).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(match_type: RuleMatch, pattern: &str, path: &str) -> CaptureRule {
+        CaptureRule {
+            match_type,
+            pattern: pattern.to_string(),
+            target: RuleTarget::Note {
+                path: path.to_string(),
+            },
+            heading: String::new(),
+            entry_style: None,
+            strip_prefix: true,
+        }
+    }
+
+    fn routed_note(routed: &RoutedCapture) -> (&str, Option<&str>) {
+        match &routed.target {
+            CaptureTarget::Routed { target, .. } => match target.as_ref() {
+                CaptureTarget::Note { path, heading } => (path, heading.as_deref()),
+                other => panic!("expected a note target, got {:?}", other),
+            },
+            other => panic!("expected a routed target, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_strips_the_prefix() {
+        let mut tasks = rule(RuleMatch::Prefix, "todo:", "Tasks.md");
+        tasks.heading = "## Inbox".to_string();
+        tasks.entry_style = Some(EntryStyle::Task);
+        let rules = vec![
+            tasks,
+            rule(RuleMatch::Keyword, "#idea", "Ideas.md"),
+            rule(RuleMatch::Prefix, "todo", "Never.md"),
+        ];
+
+        let routed = route(&rules, "  TODO: call Bob").unwrap();
+        assert_eq!(routed.rule, Some(0));
+        assert_eq!(routed.text, "call Bob");
+        assert_eq!(routed_note(&routed), ("Tasks.md", Some("## Inbox")));
+        assert!(matches!(
+            routed.target,
+            CaptureTarget::Routed {
+                entry_style: Some(EntryStyle::Task),
+                ..
+            }
+        ));
+
+        let routed = route(&rules, "what if #Idea, notes ran themselves").unwrap();
+        assert_eq!(routed.rule, Some(1));
+        assert_eq!(routed.text, "what if, notes ran themselves");
+        assert_eq!(routed_note(&routed).0, "Ideas.md");
+    }
+
+    #[test]
+    fn keywords_after_non_ascii_whitespace() {
+        let rules = vec![rule(RuleMatch::Keyword, "#idea", "Ideas.md")];
+
+        let routed = route(&rules, "a\u{a0}#idea b").unwrap();
+        assert_eq!(routed.rule, Some(0));
+        assert_eq!(routed.text, "a\u{a0}b");
+
+        let routed = route(&rules, "メモ\u{3000}#Idea\u{2003}明日").unwrap();
+        assert_eq!(routed.text, "メモ\u{3000}明日");
+    }
+
+    #[test]
+    fn unmatched_captures_go_to_the_daily_note() {
+        let rules = vec![
+            rule(RuleMatch::Prefix, "todo:", "Tasks.md"),
+            rule(RuleMatch::Keyword, "idea", "Ideas.md"),
+        ];
+
+        let routed = route(&rules, "a todo: later, ideas are not a keyword\n").unwrap();
+        assert_eq!(routed.rule, None);
+        assert_eq!(routed.target, CaptureTarget::DailyNote { date: None });
+        assert_eq!(routed.text, "a todo: later, ideas are not a keyword");
+    }
+
+    #[test]
+    fn regex_rules_strip_only_the_match_when_asked() {
+        let mut meeting = rule(RuleMatch::Regex, r"(?i)\bmtg\s+\w+", "Meetings.md");
+        let routed = route(std::slice::from_ref(&meeting), "Mtg Alice\nbudget").unwrap();
+        assert_eq!(routed.text, "budget");
+
+        meeting.strip_prefix = false;
+        let routed = route(&[meeting], "Mtg Alice\nbudget").unwrap();
+        assert_eq!(routed.text, "Mtg Alice\nbudget");
+    }
+
+    #[test]
+    fn rejects_captures_that_are_only_the_prefix() {
+        let rules = vec![rule(RuleMatch::Prefix, "todo:", "Tasks.md")];
+        assert!(route(&rules, "todo:  ").is_err());
+    }
+
+    #[test]
+    fn routed_settings_override_heading_and_style() {
+        let settings = Settings::default();
+        let routed = routed_settings(
+            &settings,
+            &CaptureTarget::DailyNote { date: None },
+            Some("## Ideas"),
+            Some(EntryStyle::Bullet),
+        );
+        assert_eq!(routed.daily_note_heading, "## Ideas");
+        assert_eq!(routed.daily_note_entry_style, EntryStyle::Bullet);
+
+        let routed = routed_settings(
+            &settings,
+            &CaptureTarget::PeriodicNote {
+                period: Period::Week,
+            },
+            Some("## Ideas"),
+            Some(EntryStyle::Quote),
+        );
+        assert_eq!(routed.weekly_note.heading, "## Ideas");
+        assert_eq!(routed.weekly_note.entry_style, EntryStyle::Quote);
+        assert_eq!(routed.daily_note_heading, settings.daily_note_heading);
+        assert_eq!(
+            routed.daily_note_entry_style,
+            settings.daily_note_entry_style
+        );
+    }
+
+    #[test]
+    fn periodic_rules_keep_their_heading() {
+        let mut weekly = rule(RuleMatch::Prefix, "review:", "");
+        weekly.target = RuleTarget::PeriodicNote {
+            period: Period::Week,
+        };
+        weekly.heading = "## Review".to_string();
+
+        let routed = route(&[weekly], "review: ship it").unwrap();
+        assert_eq!(
+            routed.target,
+            CaptureTarget::Routed {
+                target: Box::new(CaptureTarget::PeriodicNote {
+                    period: Period::Week,
+                }),
+                heading: Some("## Review".to_string()),
+                entry_style: None,
+            }
+        );
+    }
+
+    #[test]
+    fn validates_patterns() {
+        assert!(validate_pattern(RuleMatch::Prefix, " ").is_err());
+        assert!(validate_pattern(RuleMatch::Keyword, "two words").is_err());
+        assert!(validate_pattern(RuleMatch::Regex, "(unclosed").is_err());
+        assert!(validate_pattern(RuleMatch::Regex, r"^\d+").is_ok());
+    }
+}
@@ -0,0 +1,743 @@
+//! Locating headings, their sections and block references inside Markdown notes.
+
+/// A heading as configured by the user, e.g. `## Log` or just `Log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingSpec {
+    /// Required level; `None` matches the text at any level.
+    pub level: Option<usize>,
+    pub text: String,
+}
+
+impl HeadingSpec {
+    pub fn parse(value: &str) -> Option<Self> {
+        let trimmed = value.trim();
+        if trimmed.is_empty() || trimmed.contains('\n') {
+            return None;
+        }
+
+        if let Some((level, text)) = parse_atx_heading(trimmed) {
+            return Some(Self {
+                level: Some(level),
+                text,
+            });
+        }
+
+        if trimmed.starts_with('#') {
+            return None;
+        }
+
+        Some(Self {
+            level: None,
+            text: trimmed.to_string(),
+        })
+    }
+
+    /// The Markdown line used when the heading has to be created.
+    pub fn to_markdown(&self) -> String {
+        format!("{} {}", "#".repeat(self.level.unwrap_or(2)), self.text)
+    }
+
+    fn matches(&self, level: usize, text: &str) -> bool {
+        self.level.map(|wanted| wanted == level).unwrap_or(true) && self.text == text
+    }
+}
+
+/// A place inside a note, given after the note path: `Project.md#Log` or
+/// `Project.md#^inbox`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    Heading(HeadingSpec),
+    /// A block ID, without the `^`.
+    Block(String),
+}
+
+impl Anchor {
+    /// `^id` is a block reference. Of nested headings (`Project#Log`) the last
+    /// one is used, at any level.
+    pub fn parse(fragment: &str) -> Option<Self> {
+        let fragment = fragment.trim();
+        if let Some(id) = fragment.strip_prefix('^') {
+            return is_block_id(id).then(|| Anchor::Block(id.to_string()));
+        }
+
+        let text = fragment.rsplit('#').next()?.trim();
+        if text.is_empty() || text.contains('\n') {
+            return None;
+        }
+        Some(Anchor::Heading(HeadingSpec {
+            level: None,
+            text: text.to_string(),
+        }))
+    }
+}
+
+/// Splits a note path like `Project X.md#^inbox` into the path and its anchor.
+pub fn split_anchor(path: &str) -> Result<(&str, Option<Anchor>), String> {
+    match path.split_once('#') {
+        None => Ok((path, None)),
+        Some((note, fragment)) => Anchor::parse(fragment)
+            .map(|anchor| (note, Some(anchor)))
+            .ok_or_else(|| format!("Invalid anchor: #{}", fragment.trim())),
+    }
+}
+
+fn is_block_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// The block ID a line ends with: `text ^id`, or `^id` on a line of its own.
+fn line_block_id(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    let caret = line.rfind('^')?;
+    let before = &line[..caret];
+    let id = &line[caret + 1..];
+    (is_block_id(id) && (before.is_empty() || before.ends_with([' ', '\t']))).then_some(id)
+}
+
+/// Where a capture for a block reference goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTarget {
+    /// Before the existing children (or items) of the block, newest first.
+    pub prepend_offset: usize,
+    /// After the end of the block.
+    pub append_offset: usize,
+    /// Indentation of a new list item if the block is a list item (the capture
+    /// becomes its child) or a whole list. `None` for other blocks.
+    pub list_indent: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ListItem<'a> {
+    indent: &'a str,
+    /// Marker and the space after it, e.g. 2 for `- `, 3 for `1. `.
+    marker_width: usize,
+}
+
+fn list_item(line: &str) -> Option<ListItem<'_>> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - rest.len()];
+
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let marker = match digits {
+        0 if rest.starts_with(['-', '*', '+']) => 1,
+        1..=9 if rest[digits..].starts_with(['.', ')']) => digits + 1,
+        _ => return None,
+    };
+
+    let after = &rest[marker..];
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some(ListItem {
+        indent,
+        marker_width: marker + 1,
+    })
+}
+
+/// Width of the leading whitespace, a tab counting as four columns.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Finds the block carrying `^id`, outside of code and frontmatter.
+///
+/// For a list item, new entries become its children, indented like existing
+/// children or, without any, like the note's other nested items. A `^id` line
+/// of its own refers to the block before it; if that is a list, new entries
+/// become items of that list. Returns `None` if no block has the ID.
+pub fn find_block(content: &str, id: &str) -> Option<BlockTarget> {
+    let lines = lines(content);
+    let kinds = classify(&lines);
+    let is_text = |index: usize| kinds[index] == LineKind::Text;
+
+    let index = (0..lines.len()).find(|index| {
+        matches!(kinds[*index], LineKind::Text | LineKind::Heading(..))
+            && line_block_id(lines[*index].text) == Some(id)
+    })?;
+    let line_start = |index: usize| if index == 0 { 0 } else { lines[index - 1].end };
+
+    if lines[index].text.trim().len() == id.len() + 1 {
+        // `^id` allein: gemeint ist der Block davor
+        let last = (0..index)
+            .rev()
+            .find(|index| kinds[*index] != LineKind::Blank)?;
+        let first = (0..last)
+            .rev()
+            .take_while(|index| is_text(*index))
+            .last()
+            .unwrap_or(last);
+
+        return Some(
+            match list_item(lines[first].text).filter(|_| is_text(first)) {
+                Some(item) => BlockTarget {
+                    prepend_offset: line_start(first),
+                    append_offset: lines[last].end,
+                    list_indent: Some(item.indent.to_string()),
+                },
+                None => BlockTarget {
+                    prepend_offset: lines[last].end,
+                    append_offset: lines[last].end,
+                    list_indent: None,
+                },
+            },
+        );
+    }
+
+    let Some(item) = list_item(lines[index].text).filter(|_| is_text(index)) else {
+        return Some(BlockTarget {
+            prepend_offset: lines[index].end,
+            append_offset: lines[index].end,
+            list_indent: None,
+        });
+    };
+
+    // Kinder: alle folgenden Zeilen, die tiefer eingerückt (oder leer) sind
+    let width = indent_width(item.indent);
+    let children = (index + 1..lines.len())
+        .take_while(|child| {
+            kinds[*child] == LineKind::Blank
+                || (!matches!(kinds[*child], LineKind::Heading(..))
+                    && indent_width(lines[*child].text) > width)
+        })
+        .filter(|child| kinds[*child] != LineKind::Blank)
+        .collect::<Vec<_>>();
+
+    let child_indent = children
+        .iter()
+        .filter_map(|child| list_item(lines[*child].text))
+        .min_by_key(|child| indent_width(child.indent))
+        .map(|child| child.indent.to_string())
+        .unwrap_or_else(|| {
+            let uses_tabs = lines.iter().any(|line| {
+                list_item(line.text)
+                    .map(|other| other.indent.starts_with('\t'))
+                    .unwrap_or(false)
+            });
+            if uses_tabs || item.indent.contains('\t') {
+                format!("{}\t", item.indent)
+            } else {
+                format!("{}{}", item.indent, " ".repeat(item.marker_width))
+            }
+        });
+
+    Some(BlockTarget {
+        prepend_offset: lines[index].end,
+        append_offset: children
+            .last()
+            .map(|child| lines[*child].end)
+            .unwrap_or(lines[index].end),
+        list_indent: Some(child_indent),
+    })
+}
+
+/// A line of the note and the byte offset where it ends (after the line break).
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    end: usize,
+    text: &'a str,
+}
+
+/// The Markdown structure of a line, with frontmatter and fenced code already
+/// taken into account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LineKind {
+    Frontmatter,
+    Code,
+    Heading(usize, String),
+    Blank,
+    Text,
+}
+
+fn lines(content: &str) -> Vec<Line<'_>> {
+    let mut end = 0;
+    content
+        .split_inclusive('\n')
+        .map(|raw| {
+            end += raw.len();
+            Line {
+                end,
+                text: raw.trim_end_matches(['\n', '\r']),
+            }
+        })
+        .collect()
+}
+
+fn classify(lines: &[Line<'_>]) -> Vec<LineKind> {
+    let mut kinds = Vec::with_capacity(lines.len());
+    let frontmatter_end = frontmatter_end(lines);
+    let mut fence: Option<(char, usize)> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if frontmatter_end.map(|end| index <= end).unwrap_or(false) {
+            kinds.push(LineKind::Frontmatter);
+            continue;
+        }
+
+        if let Some((fence_char, fence_len)) = fence {
+            kinds.push(LineKind::Code);
+            let closing = line.text.trim();
+            if closing.len() >= fence_len && closing.chars().all(|c| c == fence_char) {
+                fence = None;
+            }
+            continue;
+        }
+
+        if let Some(opening) = parse_fence_opening(line.text) {
+            fence = Some(opening);
+            kinds.push(LineKind::Code);
+            continue;
+        }
+
+        kinds.push(match parse_atx_heading(line.text) {
+            Some((level, text)) => LineKind::Heading(level, text),
+            None if line.text.trim().is_empty() => LineKind::Blank,
+            None => LineKind::Text,
+        });
+    }
+
+    kinds
+}
+
+/// Index of the line closing a leading YAML block; `None` without (closed) frontmatter.
+fn frontmatter_end(lines: &[Line<'_>]) -> Option<usize> {
+    if lines.first()?.text != "---" {
+        return None;
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, line)| line.text == "---" || line.text == "...")
+        .map(|(index, _)| index)
+}
+
+fn parse_fence_opening(line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+
+    let rest = &line[indent..];
+    let fence_char = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let fence_len = rest.chars().take_while(|c| *c == fence_char).count();
+
+    if fence_len >= 3 {
+        Some((fence_char, fence_len))
+    } else {
+        None
+    }
+}
+
+/// Parses an ATX heading (`## Title ##`) into its level and text.
+pub fn parse_atx_heading(line: &str) -> Option<(usize, String)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+
+    let rest = &line[indent..];
+    let level = rest.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let after = &rest[level..];
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let text = after.trim();
+    let text = text
+        .strip_suffix(|c| c == '#')
+        .map(|without| without.trim_end_matches('#'))
+        .filter(|without| without.is_empty() || without.ends_with([' ', '\t']))
+        .unwrap_or(text)
+        .trim();
+
+    Some((level, text.to_string()))
+}
+
+/// Where a new entry goes inside the section of `heading`: right after the last
+/// non-blank line before the next heading of the same or a higher level.
+/// Returns `None` if the heading does not exist in `content`.
+pub fn section_insert_offset(content: &str, heading: &HeadingSpec) -> Option<usize> {
+    let lines = lines(content);
+    let kinds = classify(&lines);
+
+    let (heading_index, level) = kinds
+        .iter()
+        .enumerate()
+        .find_map(|(index, kind)| match kind {
+            LineKind::Heading(level, text) if heading.matches(*level, text) => {
+                Some((index, *level))
+            }
+            _ => None,
+        })?;
+
+    let section_end = kinds
+        .iter()
+        .enumerate()
+        .skip(heading_index + 1)
+        .find(|(_, kind)| matches!(kind, LineKind::Heading(other, _) if *other <= level))
+        .map(|(index, _)| index)
+        .unwrap_or(lines.len());
+
+    let last_content = (heading_index..section_end)
+        .rev()
+        .find(|index| kinds[*index] != LineKind::Blank)
+        .unwrap_or(heading_index);
+
+    Some(lines[last_content].end)
+}
+
+/// Offset right after the heading line itself, for newest-first insertion.
+pub fn heading_body_offset(content: &str, heading: &HeadingSpec) -> Option<usize> {
+    let lines = lines(content);
+    let kinds = classify(&lines);
+
+    kinds
+        .iter()
+        .zip(lines.iter())
+        .find_map(|(kind, line)| match kind {
+            LineKind::Heading(level, text) if heading.matches(*level, text) => Some(line.end),
+            _ => None,
+        })
+}
+
+/// Offset where the note body starts, i.e. after a leading YAML frontmatter block.
+pub fn body_start_offset(content: &str) -> usize {
+    let lines = lines(content);
+    frontmatter_end(&lines)
+        .map(|index| lines[index].end)
+        .unwrap_or(0)
+}
+
+/// Inserts `entry` (which must end with a line break) at `offset`, adding a
+/// line break first if the preceding line is unterminated.
+/// Returns the new content and the byte range the entry now occupies.
+pub fn insert_at(content: &str, offset: usize, entry: &str) -> (String, std::ops::Range<usize>) {
+    let mut inserted = String::new();
+    if offset > 0 && !content[..offset].ends_with('\n') {
+        inserted.push('\n');
+    }
+    inserted.push_str(entry);
+
+    let mut result = String::with_capacity(content.len() + inserted.len());
+    result.push_str(&content[..offset]);
+    result.push_str(&inserted);
+    result.push_str(&content[offset..]);
+
+    (result, offset..offset + inserted.len())
+}
+
+/// Like `insert_at`, but keeps `entry` apart from the surrounding text with blank
+/// lines, so quote and callout blocks don't merge with their neighbours.
+pub fn insert_block_at(
+    content: &str,
+    offset: usize,
+    entry: &str,
+) -> (String, std::ops::Range<usize>) {
+    let before = &content[..offset];
+    let after = &content[offset..];
+
+    let mut inserted = String::new();
+    if !before.is_empty() && !before.ends_with('\n') {
+        inserted.push_str("\n\n");
+    } else if !before.trim_end_matches(['\n', '\r']).is_empty() && !ends_with_blank_line(before) {
+        inserted.push('\n');
+    }
+    inserted.push_str(entry);
+    if !after.is_empty() && !after.starts_with('\n') && !after.starts_with("\r\n") {
+        inserted.push('\n');
+    }
+
+    let mut result = String::with_capacity(content.len() + inserted.len());
+    result.push_str(before);
+    result.push_str(&inserted);
+    result.push_str(after);
+
+    (result, offset..offset + inserted.len())
+}
+
+/// Whether `text` ends with an empty line (LF or CRLF).
+pub fn ends_with_blank_line(text: &str) -> bool {
+    let without_break = text
+        .strip_suffix('\n')
+        .map(|rest| rest.strip_suffix('\r').unwrap_or(rest))
+        .unwrap_or(text);
+    without_break.is_empty() || without_break.ends_with('\n')
+}
+
+/// Inserts `entry` at the end of the section of `heading`, creating the heading
+/// at the end of the note if it is missing.
+pub fn insert_under_heading(
+    content: &str,
+    heading: &HeadingSpec,
+    entry: &str,
+) -> (String, std::ops::Range<usize>) {
+    if let Some(offset) = section_insert_offset(content, heading) {
+        return insert_at(content, offset, entry);
+    }
+
+    let mut block = String::new();
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            block.push('\n');
+        }
+        if !content.trim_end_matches(['\n', '\r']).is_empty() && !ends_with_blank_line(content) {
+            block.push('\n');
+        }
+    }
+    block.push_str(&heading.to_markdown());
+    block.push('\n');
+    block.push_str(entry);
+
+    let offset = content.len();
+    let mut result = content.to_string();
+    result.push_str(&block);
+    (result, offset..offset + block.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(value: &str) -> HeadingSpec {
+        HeadingSpec::parse(value).unwrap()
+    }
+
+    const DAILY: &str =
+        "# 2024-03-15\n\n## Log\n- 08:00 coffee\n\n## Inbox\n- idea\n\n## Tasks\n- [ ] call\n";
+
+    #[test]
+    fn parses_heading_specs() {
+        assert_eq!(
+            spec("## Log"),
+            HeadingSpec {
+                level: Some(2),
+                text: "Log".to_string()
+            }
+        );
+        assert_eq!(
+            spec("Inbox"),
+            HeadingSpec {
+                level: None,
+                text: "Inbox".to_string()
+            }
+        );
+        assert_eq!(spec("### Done ###").text, "Done");
+        assert!(HeadingSpec::parse("").is_none());
+        assert!(HeadingSpec::parse("#######  Too deep").is_none());
+        assert!(HeadingSpec::parse("#NoSpace").is_none());
+    }
+
+    #[test]
+    fn inserts_at_end_of_section_before_next_heading() {
+        let (result, range) = insert_under_heading(DAILY, &spec("## Log"), "- 09:00 standup\n");
+        assert_eq!(
+            result,
+            "# 2024-03-15\n\n## Log\n- 08:00 coffee\n- 09:00 standup\n\n## Inbox\n- idea\n\n## Tasks\n- [ ] call\n"
+        );
+        assert_eq!(&result[range], "- 09:00 standup\n");
+    }
+
+    #[test]
+    fn section_extends_over_deeper_headings() {
+        let content = "## Log\n### Morning\n- a\n### Evening\n- b\n## Inbox\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- c\n");
+        assert_eq!(
+            result,
+            "## Log\n### Morning\n- a\n### Evening\n- b\n- c\n## Inbox\n"
+        );
+    }
+
+    #[test]
+    fn heading_without_level_matches_any_level() {
+        let (result, _) = insert_under_heading(DAILY, &spec("Tasks"), "- [ ] new\n");
+        assert!(result.ends_with("## Tasks\n- [ ] call\n- [ ] new\n"));
+    }
+
+    #[test]
+    fn level_must_match_when_given() {
+        let content = "### Log\n- deep\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- entry\n");
+        assert_eq!(result, "### Log\n- deep\n\n## Log\n- entry\n");
+    }
+
+    #[test]
+    fn ignores_headings_inside_fenced_code() {
+        let content = "## Log\n```bash\n## not a heading\n```\n- a\n\n## Inbox\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- b\n");
+        assert_eq!(
+            result,
+            "## Log\n```bash\n## not a heading\n```\n- a\n- b\n\n## Inbox\n"
+        );
+
+        let content = "~~~~\n## Log\n~~~\n~~~~\n## Log\n- real\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- b\n");
+        assert_eq!(result, "~~~~\n## Log\n~~~\n~~~~\n## Log\n- real\n- b\n");
+    }
+
+    #[test]
+    fn ignores_headings_inside_frontmatter() {
+        let content = "---\ntitle: x\n# Log\n---\n# Log\n";
+        let (result, _) = insert_under_heading(content, &spec("# Log"), "- b\n");
+        assert_eq!(result, "---\ntitle: x\n# Log\n---\n# Log\n- b\n");
+    }
+
+    #[test]
+    fn keeps_trailing_blank_lines_after_entry() {
+        let content = "## Log\n- a\n\n\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- b\n");
+        assert_eq!(result, "## Log\n- a\n- b\n\n\n");
+    }
+
+    #[test]
+    fn empty_section_inserts_right_after_heading() {
+        let content = "## Log\n\n## Inbox\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- a\n");
+        assert_eq!(result, "## Log\n- a\n\n## Inbox\n");
+    }
+
+    #[test]
+    fn unterminated_last_line_gets_line_break() {
+        let content = "## Log\n- a";
+        let (result, range) = insert_under_heading(content, &spec("## Log"), "- b\n");
+        assert_eq!(result, "## Log\n- a\n- b\n");
+        assert_eq!(&result[range], "\n- b\n");
+    }
+
+    #[test]
+    fn creates_missing_heading_at_end() {
+        let (result, range) = insert_under_heading("# Day\n- x\n", &spec("## Log"), "- a\n");
+        assert_eq!(result, "# Day\n- x\n\n## Log\n- a\n");
+        assert_eq!(&result[range], "\n## Log\n- a\n");
+
+        let (result, _) = insert_under_heading("# Day", &spec("Log"), "- a\n");
+        assert_eq!(result, "# Day\n\n## Log\n- a\n");
+
+        let (result, _) = insert_under_heading("", &spec("## Log"), "- a\n");
+        assert_eq!(result, "## Log\n- a\n");
+
+        let (result, _) = insert_under_heading("# Day\n\n", &spec("## Log"), "- a\n");
+        assert_eq!(result, "# Day\n\n## Log\n- a\n");
+    }
+
+    #[test]
+    fn heading_body_offset_points_after_heading_line() {
+        assert_eq!(
+            heading_body_offset(DAILY, &spec("## Log")),
+            Some("# 2024-03-15\n\n## Log\n".len())
+        );
+        assert_eq!(heading_body_offset(DAILY, &spec("## Missing")), None);
+    }
+
+    #[test]
+    fn body_starts_after_frontmatter() {
+        assert_eq!(body_start_offset("# Title\n"), 0);
+        assert_eq!(body_start_offset("---\ntags: a\n---\n# Title\n"), 16);
+        assert_eq!(body_start_offset("---\ntags: a\n---"), 15);
+        assert_eq!(body_start_offset("---\r\ntags: a\r\n---\r\nbody"), 19);
+        // Ohne schließende Zeile ist es kein Frontmatter
+        assert_eq!(body_start_offset("---\ntags: a\n"), 0);
+        assert_eq!(body_start_offset(""), 0);
+    }
+
+    #[test]
+    fn unclosed_frontmatter_is_a_thematic_break() {
+        let content = "---\n## Log\n- a\n";
+        let (result, _) = insert_under_heading(content, &spec("## Log"), "- b\n");
+        assert_eq!(result, "---\n## Log\n- a\n- b\n");
+    }
+
+    #[test]
+    fn block_insert_keeps_blank_lines_around_entry() {
+        let quote = "> [!note] 09:00\n> b\n";
+        let previous = "> [!note] 08:00\n> a\n";
+
+        let (result, range) = insert_block_at(previous, previous.len(), quote);
+        assert_eq!(result, "> [!note] 08:00\n> a\n\n> [!note] 09:00\n> b\n");
+        assert_eq!(&result[range], "\n> [!note] 09:00\n> b\n");
+
+        let content = "## Log\n> a\n";
+        let (result, _) = insert_block_at(content, 7, quote);
+        assert_eq!(result, "## Log\n\n> [!note] 09:00\n> b\n\n> a\n");
+
+        let (result, _) = insert_block_at("text", 4, quote);
+        assert_eq!(result, "text\n\n> [!note] 09:00\n> b\n");
+
+        let (result, _) = insert_block_at("a\n\nb\n", 3, quote);
+        assert_eq!(result, "a\n\n> [!note] 09:00\n> b\n\nb\n");
+
+        let (result, _) = insert_block_at("", 0, quote);
+        assert_eq!(result, quote);
+    }
+
+    #[test]
+    fn parses_note_anchors() {
+        assert_eq!(
+            split_anchor("Project X.md#^inbox").unwrap(),
+            ("Project X.md", Some(Anchor::Block("inbox".to_string())))
+        );
+        assert_eq!(
+            split_anchor("Project.md#Plan#Log").unwrap().1,
+            Some(Anchor::Heading(spec("Log")))
+        );
+        assert_eq!(split_anchor("Project.md").unwrap(), ("Project.md", None));
+        assert!(split_anchor("Project.md#^in box").is_err());
+        assert!(split_anchor("Project.md#").is_err());
+    }
+
+    #[test]
+    fn finds_list_item_with_block_id() {
+        let content = "# Project\n- Inbox ^inbox\n  - first\n    - nested\n\n  - second\n- Later\n";
+        let block = find_block(content, "inbox").unwrap();
+        assert_eq!(block.list_indent.as_deref(), Some("  "));
+        assert_eq!(&content[block.append_offset..], "- Later\n");
+        assert_eq!(
+            &content[block.prepend_offset..],
+            "  - first\n    - nested\n\n  - second\n- Later\n"
+        );
+
+        // Ohne Kinder: eingerückt wie die anderen verschachtelten Einträge der Note
+        let content = "- a\n\t- b\n1. Inbox ^inbox\n";
+        let block = find_block(content, "inbox").unwrap();
+        assert_eq!(block.list_indent.as_deref(), Some("\t"));
+        let block = find_block("1. Inbox ^inbox", "inbox").unwrap();
+        assert_eq!(block.list_indent.as_deref(), Some("   "));
+
+        assert!(find_block("```\n- x ^inbox\n```\n", "inbox").is_none());
+        assert!(find_block("- x ^inboxes\n", "inbox").is_none());
+        assert!(find_block("- x^inbox\n", "inbox").is_none());
+    }
+
+    #[test]
+    fn block_id_on_own_line_refers_to_block_before() {
+        let content = "## Tasks\n- a\n- b\n\n^tasks\n";
+        let block = find_block(content, "tasks").unwrap();
+        assert_eq!(block.list_indent.as_deref(), Some(""));
+        assert_eq!(block.append_offset, "## Tasks\n- a\n- b\n".len());
+        assert_eq!(block.prepend_offset, "## Tasks\n".len());
+
+        let content = "Some paragraph\nsecond line ^para\n\nmore";
+        let block = find_block(content, "para").unwrap();
+        assert_eq!(block.list_indent, None);
+        assert_eq!(
+            block.append_offset,
+            "Some paragraph\nsecond line ^para\n".len()
+        );
+    }
+
+    #[test]
+    fn handles_crlf_lines() {
+        let content = "## Log\r\n- a\r\n\r\n## Inbox\r\n";
+        assert_eq!(
+            section_insert_offset(content, &spec("## Log")),
+            Some("## Log\r\n- a\r\n".len())
+        );
+    }
+}
@@ -3,14 +3,14 @@
 /// Implementation strategy:
 /// - Save current clipboard string
 /// - Synthesize Cmd+C to copy selection into clipboard
-/// - Read clipboard string
+/// - Read clipboard string, and the HTML or RTF flavor if `as_markdown` is set
 /// - Restore previous clipboard
 ///
 /// Notes:
 /// - Requires Accessibility permission for the app to send synthetic key events.
 /// - If nothing is selected, many apps keep clipboard unchanged; we treat that as no capture.
 #[cfg(target_os = "macos")]
-pub fn capture_selected_text() -> Option<String> {
+pub fn capture_selected_text(as_markdown: bool) -> Option<String> {
     log::info!("capture_selected_text: Starting...");
 
     let previous = read_clipboard_string();
@@ -35,6 +35,14 @@ pub fn capture_selected_text() -> Option<String> {
     std::thread::sleep(std::time::Duration::from_millis(250));
 
     let captured = read_clipboard_string();
+    let (html, rtf) = if as_markdown {
+        (
+            read_clipboard_data(unsafe { cocoa::appkit::NSPasteboardTypeHTML }),
+            read_clipboard_data(unsafe { cocoa::appkit::NSPasteboardTypeRTF }),
+        )
+    } else {
+        (None, None)
+    };
     log::info!(
         "capture_selected_text: Captured clipboard length={}",
         captured
@@ -56,12 +64,20 @@ pub fn capture_selected_text() -> Option<String> {
         return None;
     }
 
+    let captured = captured?;
+    if html.is_some() || rtf.is_some() {
+        log::info!("capture_selected_text: Converting rich text to Markdown");
+    }
     log::info!("capture_selected_text: Returning captured text");
-    captured
+    Some(crate::rich_text::clipboard_to_markdown(
+        &captured,
+        html.as_deref(),
+        rtf.as_deref(),
+    ))
 }
 
 #[cfg(not(target_os = "macos"))]
-pub fn capture_selected_text() -> Option<String> {
+pub fn capture_selected_text(_as_markdown: bool) -> Option<String> {
     None
 }
 
@@ -93,6 +109,32 @@ fn read_clipboard_string() -> Option<String> {
     }
 }
 
+/// The clipboard content of `pasteboard_type` (HTML, RTF) as text.
+#[cfg(target_os = "macos")]
+fn read_clipboard_data(pasteboard_type: cocoa::base::id) -> Option<String> {
+    use cocoa::appkit::NSPasteboard;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSData;
+
+    unsafe {
+        let pb: id = NSPasteboard::generalPasteboard(nil);
+        if pb == nil {
+            return None;
+        }
+        let data: id = pb.dataForType(pasteboard_type);
+        if data == nil {
+            return None;
+        }
+        let bytes = data.bytes() as *const u8;
+        let len = data.length() as usize;
+        if bytes.is_null() || len == 0 {
+            return None;
+        }
+        let slice = std::slice::from_raw_parts(bytes, len);
+        Some(String::from_utf8_lossy(slice).into_owned())
+    }
+}
+
 #[cfg(target_os = "macos")]
 fn write_clipboard_string(value: &str) {
     use cocoa::appkit::{NSPasteboard, NSPasteboardTypeString};
@@ -1,3 +1,4 @@
+use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fs;
 use std::path::{Component, Path, PathBuf};
@@ -13,6 +14,224 @@ pub struct PinnedNote {
     pub icon: String,
 }
 
+/// Folder, filename format and template of a weekly, monthly, quarterly or
+/// yearly note. Same rules as the daily note settings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeriodicNote {
+    #[serde(default)]
+    pub folder: String,
+    pub format: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub entry_style: EntryStyle,
+    #[serde(default)]
+    pub heading: String,
+    #[serde(default)]
+    pub insert_position: InsertPosition,
+}
+
+impl PeriodicNote {
+    fn with_format(format: &str) -> Self {
+        Self {
+            folder: String::new(),
+            format: format.to_string(),
+            template: String::new(),
+            entry_style: EntryStyle::default(),
+            heading: String::new(),
+            insert_position: InsertPosition::default(),
+        }
+    }
+}
+
+/// Capture targets besides the daily note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    pub const ALL: [Period; 4] = [Period::Week, Period::Month, Period::Quarter, Period::Year];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Week => "weekly",
+            Period::Month => "monthly",
+            Period::Quarter => "quarterly",
+            Period::Year => "yearly",
+        }
+    }
+}
+
+/// How a capture is written into the target note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryStyle {
+    /// The `entry_header` line, then the text.
+    #[default]
+    Header,
+    /// `- HH:mm text`
+    Bullet,
+    /// `- [ ] text`
+    Task,
+    /// `> text`
+    Quote,
+    /// `> [!note] HH:mm`, then the text inside the callout.
+    Callout,
+}
+
+/// What `save_as_note` does when the target file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Save as `Title 1.md`, `Title 2.md`, ...
+    #[default]
+    Suffix,
+    /// Append the capture to the existing note.
+    Append,
+    /// Refuse with an error.
+    Error,
+}
+
+/// How dates recognized in task captures ("due friday") are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskDateFormat {
+    /// Leave the text as typed.
+    #[default]
+    Off,
+    /// Obsidian Tasks emoji: `📅 2026-10-18`, `⏳`, `🔁 every week`
+    Tasks,
+    /// Dataview inline fields: `[due:: 2026-10-18]`
+    Dataview,
+}
+
+/// What happens to a capture that repeats one just written to the same target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Don't write it again.
+    Skip,
+    /// Write it, but tell the user it is a duplicate. The default, so a
+    /// capture repeated on purpose is never lost.
+    #[default]
+    Warn,
+    /// Write it without checking.
+    Allow,
+}
+
+/// Where a capture lands in the target note (or heading section).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertPosition {
+    /// At the end, oldest entry first.
+    #[default]
+    Append,
+    /// At the top of the body (after frontmatter) or right under the heading, newest entry first.
+    Prepend,
+}
+
+/// How a capture rule recognizes a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMatch {
+    /// The text starts with the pattern, e.g. `todo:`.
+    Prefix,
+    /// The pattern appears as a word anywhere in the text, e.g. `#idea`.
+    Keyword,
+    /// The pattern is a regular expression that matches somewhere in the text.
+    Regex,
+}
+
+/// Where a capture rule sends matching captures.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleTarget {
+    DailyNote,
+    PeriodicNote { period: Period },
+    Note { path: String },
+}
+
+/// One entry of `capture_rules`. Rules are checked in order, the first match wins;
+/// captures no rule matches go to the daily note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRule {
+    pub match_type: RuleMatch,
+    pub pattern: String,
+    pub target: RuleTarget,
+    /// Heading to insert under, for note and daily note targets.
+    #[serde(default)]
+    pub heading: String,
+    /// Overrides the target's entry style.
+    #[serde(default)]
+    pub entry_style: Option<EntryStyle>,
+    /// Removes the matched prefix, keyword or regex match from the text.
+    #[serde(default)]
+    pub strip_prefix: bool,
+}
+
+/// What an auto-tagging rule looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// The name of the app the capture came from.
+    App,
+    /// A regular expression matched against the captured text.
+    Regex,
+}
+
+/// One entry of `auto_tag_rules`: captures from the app `pattern`, or whose
+/// text matches it, get `tags`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagRule {
+    pub match_type: TagMatch,
+    pub pattern: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A custom frontmatter property of new notes, e.g. `type: inbox`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteProperty {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Frontmatter properties Save as Note writes into new notes, merged with the
+/// note template's. Aliases and custom values may use `{{title}}`, `{{date}}`
+/// and `{{time}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteProperties {
+    /// Adds `created` with the capture time in `created_format`.
+    #[serde(default = "default_false")]
+    pub created: bool,
+    #[serde(default = "default_created_format")]
+    pub created_format: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub custom: Vec<NoteProperty>,
+}
+
+impl Default for NoteProperties {
+    fn default() -> Self {
+        Self {
+            created: false,
+            created_format: default_created_format(),
+            tags: Vec::new(),
+            aliases: Vec::new(),
+            custom: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum PinnedNoteInput {
@@ -85,11 +304,48 @@ pub struct Settings {
     pub daily_note_format: String,
     #[serde(default)]
     pub daily_note_path: String,
+    #[serde(default)]
+    pub daily_note_heading: String,
+    #[serde(default)]
+    pub daily_note_insert_position: InsertPosition,
+    #[serde(default)]
+    pub daily_note_entry_style: EntryStyle,
+    /// Also creates missing weekly, monthly, quarterly and yearly notes.
+    #[serde(default = "default_true")]
+    pub daily_note_auto_create: bool,
+    #[serde(default)]
+    pub daily_note_template: String,
+    #[serde(default)]
+    pub follow_obsidian_daily_notes: bool,
+    /// Hour (0-23) a new day starts for daily and periodic notes; captures
+    /// before it still go into the previous day's note.
+    #[serde(default)]
+    pub day_starts_at: u32,
+    /// IANA time zone like `Europe/Berlin` for note dates and entry times;
+    /// empty uses the system's.
+    #[serde(default)]
+    pub time_zone: String,
+    #[serde(default = "default_weekly_note")]
+    pub weekly_note: PeriodicNote,
+    #[serde(default = "default_monthly_note")]
+    pub monthly_note: PeriodicNote,
+    #[serde(default = "default_quarterly_note")]
+    pub quarterly_note: PeriodicNote,
+    #[serde(default = "default_yearly_note")]
+    pub yearly_note: PeriodicNote,
     pub image_folder: String,
     pub image_filename: String,
     #[serde(default = "default_image_width")]
     pub default_image_width: String,
     pub entry_header: String,
+    #[serde(default = "default_entry_time_format")]
+    pub entry_time_format: String,
+    /// Shown instead of the time in entries written into another day's daily
+    /// note, e.g. `--:--`. Empty keeps the time of capture.
+    #[serde(default)]
+    pub other_day_entry_time: String,
+    #[serde(default)]
+    pub task_date_format: TaskDateFormat,
     #[serde(default = "default_true")]
     pub show_note_paths: bool,
     #[serde(default = "default_autocomplete_results")]
@@ -101,6 +357,9 @@ pub struct Settings {
     pub global_close_shortcut: String,
     #[serde(default = "default_capture_text_shortcut")]
     pub capture_text_shortcut: String,
+    /// Keep links, emphasis, lists and tables of copied rich text as Markdown.
+    #[serde(default = "default_true")]
+    pub capture_text_as_markdown: bool,
     pub compression_max_kb: u32,
     #[serde(default = "default_edge_enabled")]
     pub edge_detection_enabled: bool,
@@ -122,8 +381,16 @@ pub struct Settings {
     pub save_as_note_shortcut: String,
     #[serde(default = "default_append_to_note_shortcut")]
     pub append_to_note_shortcut: String,
+    #[serde(default)]
+    pub append_to_note_insert_position: InsertPosition,
+    #[serde(default)]
+    pub append_to_note_entry_style: EntryStyle,
     #[serde(default, deserialize_with = "deserialize_pinned_notes")]
     pub pinned_notes: Vec<PinnedNote>,
+    #[serde(default)]
+    pub capture_rules: Vec<CaptureRule>,
+    #[serde(default)]
+    pub auto_tag_rules: Vec<TagRule>,
     #[serde(default = "default_reader_shortcut")]
     pub reader_shortcut: String,
     #[serde(default = "default_false")]
@@ -150,8 +417,38 @@ pub struct Settings {
     pub reader_hide_callouts: bool,
     #[serde(default = "default_note_filename_template")]
     pub note_filename_template: String,
+    /// Template syntax the date templates were written in; configs without
+    /// it predate Moment.js tokens and are migrated on load.
+    #[serde(default)]
+    pub template_syntax: u32,
+    #[serde(default)]
+    pub note_collision_policy: CollisionPolicy,
     #[serde(default = "default_note_template")]
     pub note_template: String,
+    #[serde(default)]
+    pub note_properties: NoteProperties,
+    #[serde(default)]
+    pub duplicate_capture_action: DuplicateAction,
+    /// Seconds a capture counts as a duplicate of an earlier one.
+    #[serde(default = "default_duplicate_capture_window_secs")]
+    pub duplicate_capture_window_secs: u32,
+    /// How many recent captures per target are compared.
+    #[serde(default = "default_duplicate_capture_entries")]
+    pub duplicate_capture_entries: u32,
+    #[serde(default = "default_true")]
+    pub capture_history_enabled: bool,
+    #[serde(default = "default_true")]
+    pub capture_history_store_text: bool,
+    /// Days a capture stays in the history; 0 keeps it forever.
+    #[serde(default = "default_capture_history_retention_days")]
+    pub capture_history_retention_days: u32,
+    /// Look up the frontmost app and window title when the capture window opens.
+    #[serde(default = "default_false")]
+    pub record_source_app: bool,
+    #[serde(default = "default_false")]
+    pub source_app_inline_field: bool,
+    #[serde(default = "default_false")]
+    pub source_app_frontmatter: bool,
     #[serde(default = "default_window_transparency")]
     pub window_transparency: u32,
     #[serde(default = "default_window_blur")]
@@ -234,13 +531,30 @@ fn default_reader_shortcut() -> String {
 }
 
 fn default_note_filename_template() -> String {
-    "note-YYYY-MM-DD-HHmmss".to_string()
+    "[note]-YYYY-MM-DD-HHmmss".to_string()
+}
+
+fn default_image_filename() -> String {
+    "[screenshot]-YYYY-MM-DD-HHmmss".to_string()
 }
 
+/// Current `template_syntax`: Moment.js tokens with `[...]` escaping.
+pub const TEMPLATE_SYNTAX: u32 = 1;
+
+/// Tokens of the engines before Moment.js syntax; every other letter was
+/// literal there.
+const LEGACY_ENTRY_TOKENS: &[&str] = &["YYYY", "MM", "DD", "HH", "hh", "mm", "ss", "h", "a", "A"];
+const LEGACY_IMAGE_TOKENS: &[&str] = &["YYYY", "MM", "DD", "HH", "mm", "ss"];
+const LEGACY_DATE_TOKENS: &[&str] = &["YYYY", "MM", "DD"];
+
 fn default_note_template() -> String {
     "---\ncreated: <% tp.date.now(\"YYYY-MM-DD hh:mm\") %>\nmodified: \ndaily: \"[[<% tp.date.now(\"YYYY-MM-DD\") %>]]\"\ntags: inbox\ntype: inbox\n---".to_string()
 }
 
+fn default_created_format() -> String {
+    "YYYY-MM-DDTHH:mm".to_string()
+}
+
 fn default_image_width() -> String {
     "600".to_string()
 }
@@ -301,6 +615,39 @@ fn default_daily_note_format() -> String {
     "YYYY-MM-DD".to_string()
 }
 
+fn default_entry_time_format() -> String {
+    "HH:mm".to_string()
+}
+
+fn default_duplicate_capture_window_secs() -> u32 {
+    60
+}
+
+fn default_duplicate_capture_entries() -> u32 {
+    5
+}
+
+fn default_capture_history_retention_days() -> u32 {
+    90
+}
+
+// Defaults der Periodic-Notes-Erweiterung
+fn default_weekly_note() -> PeriodicNote {
+    PeriodicNote::with_format("gggg-[W]ww")
+}
+
+fn default_monthly_note() -> PeriodicNote {
+    PeriodicNote::with_format("YYYY-MM")
+}
+
+fn default_quarterly_note() -> PeriodicNote {
+    PeriodicNote::with_format("YYYY-[Q]Q")
+}
+
+fn default_yearly_note() -> PeriodicNote {
+    PeriodicNote::with_format("YYYY")
+}
+
 fn is_safe_relative_path(path: &str) -> bool {
     let candidate = Path::new(path);
     !candidate.is_absolute()
@@ -309,6 +656,14 @@ fn is_safe_relative_path(path: &str) -> bool {
             .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
 }
 
+/// A filename template that may contain subfolders, like Obsidian's formats.
+fn is_safe_path_template(template: &str) -> bool {
+    !template.trim().is_empty()
+        && !template.starts_with('/')
+        && !template.contains('\\')
+        && !template.contains("..")
+}
+
 fn is_safe_filename_template(template: &str) -> bool {
     !template.trim().is_empty()
         && !template.contains('/')
@@ -316,6 +671,35 @@ fn is_safe_filename_template(template: &str) -> bool {
         && !template.contains("..")
 }
 
+/// Collector's folder and format for an Obsidian daily note config. Obsidian's
+/// folder is a literal path, so it is escaped; folders in the format
+/// (`YYYY/MM/YYYY-MM-DD`) stay there and are rendered with all tokens.
+fn obsidian_folder_and_format(folder: &str, format: &str) -> (String, String) {
+    let folder = normalize_relative_vault_path(folder);
+    (
+        crate::template::escape_legacy(&folder, &[]),
+        format.trim().to_string(),
+    )
+}
+
+/// `folder/format` rendered for `dt`, split into folder and filename.
+fn render_daily_note_path(folder: &str, format: &str, dt: &DateTime<Utc>) -> (String, String) {
+    let path = normalize_relative_vault_path(&format!(
+        "{}/{}",
+        crate::template::format_path(folder, dt),
+        crate::template::format_date(format, dt)
+    ));
+    match path.rsplit_once('/') {
+        Some((folder, filename)) => (folder.to_string(), filename.to_string()),
+        None => (String::new(), path),
+    }
+}
+
+fn normalize_template_path(path: &str) -> String {
+    let path = normalize_relative_vault_path(path);
+    path.strip_suffix(".md").unwrap_or(&path).to_string()
+}
+
 fn normalize_relative_vault_path(path: &str) -> String {
     path.trim()
         .replace('\\', "/")
@@ -437,16 +821,32 @@ impl Default for Settings {
             daily_note_folder: default_daily_note_folder(),
             daily_note_format: default_daily_note_format(),
             daily_note_path: String::new(),
+            daily_note_heading: String::new(),
+            daily_note_insert_position: InsertPosition::default(),
+            daily_note_entry_style: EntryStyle::default(),
+            daily_note_auto_create: default_true(),
+            daily_note_template: String::new(),
+            follow_obsidian_daily_notes: false,
+            day_starts_at: 0,
+            time_zone: String::new(),
+            weekly_note: default_weekly_note(),
+            monthly_note: default_monthly_note(),
+            quarterly_note: default_quarterly_note(),
+            yearly_note: default_yearly_note(),
             image_folder: default_screenshot_path(),
-            image_filename: "screenshot-YYYY-MM-DD-HHmmss".to_string(),
+            image_filename: default_image_filename(),
             default_image_width: default_image_width(),
             entry_header: "#### HH:mm".to_string(),
+            entry_time_format: default_entry_time_format(),
+            other_day_entry_time: String::new(),
+            task_date_format: TaskDateFormat::default(),
             show_note_paths: default_true(),
             autocomplete_results: default_autocomplete_results(),
             global_shortcut: "Cmd+Shift+N".to_string(),
             global_shortcut_closes_window: default_false(),
             global_close_shortcut: String::new(),
             capture_text_shortcut: default_capture_text_shortcut(),
+            capture_text_as_markdown: default_true(),
             compression_max_kb: 200,
             edge_detection_enabled: true,
             edge_reaction_time_ms: default_reaction_time_ms(),
@@ -458,7 +858,11 @@ impl Default for Settings {
             save_to_daily_shortcut: default_save_to_daily_shortcut(),
             save_as_note_shortcut: default_save_as_note_shortcut(),
             append_to_note_shortcut: default_append_to_note_shortcut(),
+            append_to_note_insert_position: InsertPosition::default(),
+            append_to_note_entry_style: EntryStyle::default(),
             pinned_notes: Vec::new(),
+            capture_rules: Vec::new(),
+            auto_tag_rules: Vec::new(),
             reader_shortcut: default_reader_shortcut(),
             reader_shortcut_closes_window: default_false(),
             reader_close_shortcut: String::new(),
@@ -472,7 +876,19 @@ impl Default for Settings {
             reader_hide_html: default_true(),
             reader_hide_callouts: default_true(),
             note_filename_template: default_note_filename_template(),
+            template_syntax: TEMPLATE_SYNTAX,
+            note_collision_policy: CollisionPolicy::default(),
             note_template: default_note_template(),
+            note_properties: NoteProperties::default(),
+            duplicate_capture_action: DuplicateAction::default(),
+            duplicate_capture_window_secs: default_duplicate_capture_window_secs(),
+            duplicate_capture_entries: default_duplicate_capture_entries(),
+            capture_history_enabled: default_true(),
+            capture_history_store_text: default_true(),
+            capture_history_retention_days: default_capture_history_retention_days(),
+            record_source_app: false,
+            source_app_inline_field: false,
+            source_app_frontmatter: false,
             window_transparency: default_window_transparency(),
             window_blur: default_window_blur(),
             window_saturation: default_window_saturation(),
@@ -518,6 +934,110 @@ impl Settings {
         changed
     }
 
+    /// Escapes literal text in templates from before Moment.js tokens, so
+    /// e.g. `note-YYYY` keeps its `note` instead of expanding `D` and `e`.
+    pub(crate) fn migrate_legacy_templates(&mut self) -> bool {
+        if self.template_syntax >= TEMPLATE_SYNTAX {
+            return false;
+        }
+
+        let migrations = [
+            (&mut self.note_filename_template, LEGACY_ENTRY_TOKENS),
+            (&mut self.entry_header, LEGACY_ENTRY_TOKENS),
+            (&mut self.image_filename, LEGACY_IMAGE_TOKENS),
+            (&mut self.daily_note_folder, LEGACY_DATE_TOKENS),
+            (&mut self.daily_note_format, LEGACY_DATE_TOKENS),
+        ];
+        for (template, tokens) in migrations {
+            *template = crate::template::escape_legacy(template, tokens);
+        }
+        self.template_syntax = TEMPLATE_SYNTAX;
+        true
+    }
+
+    /// Replaces the daily note folder, format and template with the vault's
+    /// Obsidian configuration when `follow_obsidian_daily_notes` is on.
+    /// Keeps Collector's own values if Obsidian has no daily note config.
+    pub(crate) fn apply_obsidian_daily_notes(&mut self) {
+        if !self.follow_obsidian_daily_notes {
+            return;
+        }
+
+        match crate::obsidian_config::daily_note_config(Path::new(&self.vault_path)) {
+            Some(config) => {
+                let (folder, format) = obsidian_folder_and_format(&config.folder, &config.format);
+                self.daily_note_folder = folder;
+                self.daily_note_format = format;
+                self.daily_note_template = config.template;
+            }
+            None => log::warn!("No Obsidian daily note config found, using Collector settings"),
+        }
+    }
+
+    /// Names of the daily note settings that differ from `config`. Folder and
+    /// format are compared by the paths they produce, so `Journal/YYYY` with
+    /// `YYYY-MM-DD` matches Obsidian's `Journal` with `YYYY/YYYY-MM-DD`.
+    pub fn daily_note_drift(
+        &self,
+        config: &crate::obsidian_config::NoteConfig,
+    ) -> Vec<&'static str> {
+        let (folder, format) = obsidian_folder_and_format(&config.folder, &config.format);
+        let samples = [(2024, 1, 7), (2025, 11, 23), (2026, 6, 30)]
+            .map(|(y, m, d)| Utc.with_ymd_and_hms(y, m, d, 14, 35, 50).unwrap());
+        let mut drift = Vec::new();
+
+        let (ours, theirs): (Vec<_>, Vec<_>) = samples
+            .iter()
+            .map(|dt| {
+                (
+                    render_daily_note_path(&self.daily_note_folder, &self.daily_note_format, dt),
+                    render_daily_note_path(&folder, &format, dt),
+                )
+            })
+            .unzip();
+        if ours.iter().zip(&theirs).any(|(a, b)| a.0 != b.0) {
+            drift.push("folder");
+        }
+        if ours.iter().zip(&theirs).any(|(a, b)| a.1 != b.1) {
+            drift.push("format");
+        }
+        if normalize_template_path(&self.daily_note_template)
+            != normalize_template_path(&config.template)
+        {
+            drift.push("template");
+        }
+
+        drift
+    }
+
+    fn report_obsidian_drift(&self) {
+        if self.follow_obsidian_daily_notes || self.vault_path.is_empty() {
+            return;
+        }
+
+        let Some(config) = crate::obsidian_config::daily_note_config(Path::new(&self.vault_path))
+        else {
+            return;
+        };
+        let drift = self.daily_note_drift(&config);
+        if !drift.is_empty() {
+            log::warn!(
+                "Daily note settings differ from Obsidian ({:?}): {}",
+                config.source,
+                drift.join(", ")
+            );
+        }
+    }
+
+    pub fn periodic_note(&self, period: Period) -> &PeriodicNote {
+        match period {
+            Period::Week => &self.weekly_note,
+            Period::Month => &self.monthly_note,
+            Period::Quarter => &self.quarterly_note,
+            Period::Year => &self.yearly_note,
+        }
+    }
+
     pub fn config_path() -> Result<PathBuf, String> {
         let config_dir =
             dirs::config_dir().ok_or_else(|| "Could not find config directory".to_string())?;
@@ -592,12 +1112,19 @@ impl Settings {
                 needs_save = true;
             }
 
+            if settings.migrate_legacy_templates() {
+                log::info!("Migrated date templates to Moment.js syntax");
+                needs_save = true;
+            }
+
             if needs_save {
                 if let Err(save_error) = settings.save() {
                     log::warn!("Failed to persist migrated settings: {}", save_error);
                 }
             }
 
+            settings.report_obsidian_drift();
+
             Ok(settings)
         } else {
             let settings = Self::default();
@@ -617,7 +1144,7 @@ impl Settings {
         let content = serde_json::to_string_pretty(self)
             .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-        fs::write(&config_path, content)
+        crate::atomic_write::write_file(&config_path, content.as_bytes())
             .map_err(|e| format!("Failed to write config file: {}", e))?;
 
         if !config_path.exists() {
@@ -681,6 +1208,18 @@ impl Settings {
             return Err("compression_max_kb must be between 50 and 2000".to_string());
         }
 
+        if self.duplicate_capture_window_secs < 1 || self.duplicate_capture_window_secs > 86400 {
+            return Err("duplicate_capture_window_secs must be between 1 and 86400".to_string());
+        }
+
+        if self.duplicate_capture_entries < 1 || self.duplicate_capture_entries > 50 {
+            return Err("duplicate_capture_entries must be between 1 and 50".to_string());
+        }
+
+        if self.capture_history_retention_days > 3650 {
+            return Err("capture_history_retention_days must be between 0 and 3650".to_string());
+        }
+
         if !self.default_image_width.trim().is_empty() {
             let width = self
                 .default_image_width
@@ -713,8 +1252,65 @@ impl Settings {
             return Err("daily_note_folder must stay inside the vault".to_string());
         }
 
-        if !is_safe_filename_template(&self.daily_note_format) {
-            return Err("daily_note_format must be a filename without path separators".to_string());
+        if !is_safe_path_template(&self.daily_note_format) {
+            return Err("daily_note_format must stay inside the daily note folder".to_string());
+        }
+
+        if !self.daily_note_heading.trim().is_empty()
+            && crate::sections::HeadingSpec::parse(&self.daily_note_heading).is_none()
+        {
+            return Err("daily_note_heading must be a single heading like '## Log'".to_string());
+        }
+
+        if !is_safe_relative_path(&self.daily_note_template) {
+            return Err("daily_note_template must be a file inside the vault".to_string());
+        }
+
+        if self.day_starts_at > 23 {
+            return Err("day_starts_at must be an hour between 0 and 23".to_string());
+        }
+
+        if !self.time_zone.is_empty() && self.time_zone.parse::<chrono_tz::Tz>().is_err() {
+            return Err(format!(
+                "time_zone '{}' is not an IANA time zone like Europe/Berlin",
+                self.time_zone
+            ));
+        }
+
+        if self.entry_time_format.contains(['\n', '\r']) {
+            return Err("entry_time_format must be a single line".to_string());
+        }
+
+        if self.other_day_entry_time.contains(['\n', '\r', '[', ']']) {
+            return Err("other_day_entry_time must be a single line without [ or ]".to_string());
+        }
+
+        for period in Period::ALL {
+            let note = self.periodic_note(period);
+            let name = period.name();
+            if !is_safe_relative_path(&note.folder) {
+                return Err(format!("{} note folder must stay inside the vault", name));
+            }
+            if !is_safe_filename_template(&note.format) {
+                return Err(format!(
+                    "{} note format must be a filename without path separators",
+                    name
+                ));
+            }
+            if !is_safe_relative_path(&note.template) {
+                return Err(format!(
+                    "{} note template must be a file inside the vault",
+                    name
+                ));
+            }
+            if !note.heading.trim().is_empty()
+                && crate::sections::HeadingSpec::parse(&note.heading).is_none()
+            {
+                return Err(format!(
+                    "{} note heading must be a single heading like '## Log'",
+                    name
+                ));
+            }
         }
 
         if !is_safe_filename_template(&self.note_filename_template) {
@@ -817,6 +1413,75 @@ impl Settings {
             }
         }
 
+        for rule in &self.capture_rules {
+            crate::routing::validate_pattern(rule.match_type, &rule.pattern)?;
+
+            if let RuleTarget::Note { path } = &rule.target {
+                let note = crate::sections::split_anchor(path.trim())
+                    .map(|(note, _)| note)
+                    .unwrap_or_default();
+                if !note.ends_with(".md") || !is_safe_relative_path(note) {
+                    return Err(
+                        "capture_rules note targets must be Markdown files inside the vault"
+                            .to_string(),
+                    );
+                }
+            }
+
+            if !rule.heading.trim().is_empty() {
+                if matches!(&rule.target, RuleTarget::Note { path } if path.contains('#')) {
+                    return Err(
+                        "capture_rules note targets with an anchor (#...) can't have a heading too"
+                            .to_string(),
+                    );
+                }
+                if crate::sections::HeadingSpec::parse(&rule.heading).is_none() {
+                    return Err(
+                        "capture_rules heading must be a single heading like '## Inbox'"
+                            .to_string(),
+                    );
+                }
+            }
+        }
+
+        for rule in &self.auto_tag_rules {
+            crate::tagging::validate_rule(rule)?;
+        }
+
+        let properties = &self.note_properties;
+        if properties.created && properties.created_format.trim().is_empty() {
+            return Err("note_properties created_format must not be empty".to_string());
+        }
+        if properties
+            .tags
+            .iter()
+            .any(|tag| crate::tagging::normalize_tag(tag).is_none())
+        {
+            return Err("note_properties tags must be valid tags".to_string());
+        }
+        if properties
+            .aliases
+            .iter()
+            .any(|alias| alias.contains(['\n', '\r']))
+        {
+            return Err("note_properties aliases must be one line".to_string());
+        }
+        for property in &properties.custom {
+            let key = property.key.trim();
+            if key.is_empty()
+                || key.starts_with(['-', '#', '"', '\''])
+                || key.contains([':', '\n', '\r'])
+            {
+                return Err(format!("note_properties key '{}' is not valid", key));
+            }
+            if property.value.contains(['\n', '\r']) {
+                return Err(format!(
+                    "note_properties value of '{}' must be one line",
+                    key
+                ));
+            }
+        }
+
         if self.window_transparency > 100 {
             return Err("window_transparency must be between 0 and 100".to_string());
         }
@@ -852,6 +1517,27 @@ mod tests {
         assert!(settings.validate().is_err());
     }
 
+    #[test]
+    fn validates_day_start_and_time_zone() {
+        let settings = Settings {
+            day_starts_at: 4,
+            time_zone: "America/New_York".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+
+        let late = Settings {
+            day_starts_at: 24,
+            ..settings.clone()
+        };
+        assert!(late.validate().is_err());
+        let unknown = Settings {
+            time_zone: "CEST".to_string(),
+            ..settings
+        };
+        assert!(unknown.validate().is_err());
+    }
+
     #[test]
     fn rejects_image_filename_with_separator() {
         let settings = Settings {
@@ -863,6 +1549,66 @@ mod tests {
         assert!(settings.validate().is_err());
     }
 
+    #[test]
+    fn validates_capture_rules() {
+        let rule = CaptureRule {
+            match_type: RuleMatch::Prefix,
+            pattern: "todo:".to_string(),
+            target: RuleTarget::Note {
+                path: "Tasks.md".to_string(),
+            },
+            heading: "## Inbox".to_string(),
+            entry_style: Some(EntryStyle::Task),
+            strip_prefix: true,
+        };
+        let with_rule = |rule: CaptureRule| Settings {
+            vault_path: "/tmp/vault".to_string(),
+            capture_rules: vec![rule],
+            ..Default::default()
+        };
+
+        assert!(with_rule(rule.clone()).validate().is_ok());
+        assert!(with_rule(CaptureRule {
+            target: RuleTarget::Note {
+                path: "../Tasks.md".to_string(),
+            },
+            ..rule.clone()
+        })
+        .validate()
+        .is_err());
+        assert!(with_rule(CaptureRule {
+            target: RuleTarget::PeriodicNote {
+                period: Period::Week,
+            },
+            ..rule.clone()
+        })
+        .validate()
+        .is_ok());
+        let block_target = RuleTarget::Note {
+            path: "Tasks.md#^inbox".to_string(),
+        };
+        assert!(with_rule(CaptureRule {
+            target: block_target.clone(),
+            heading: String::new(),
+            ..rule.clone()
+        })
+        .validate()
+        .is_ok());
+        assert!(with_rule(CaptureRule {
+            target: block_target,
+            ..rule.clone()
+        })
+        .validate()
+        .is_err());
+        assert!(with_rule(CaptureRule {
+            match_type: RuleMatch::Regex,
+            pattern: "[".to_string(),
+            ..rule
+        })
+        .validate()
+        .is_err());
+    }
+
     #[test]
     fn rejects_daily_note_format_with_separator() {
         let settings = Settings {
@@ -874,6 +1620,108 @@ mod tests {
         assert!(settings.validate().is_err());
     }
 
+    #[test]
+    fn rejects_multiline_daily_note_heading() {
+        let settings = Settings {
+            daily_note_heading: "## Log\n## Inbox".to_string(),
+            ..Default::default()
+        };
+
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn reports_daily_note_drift_from_obsidian() {
+        use crate::obsidian_config::{ConfigSource, NoteConfig};
+
+        let settings = Settings {
+            daily_note_folder: "Journal/YYYY/".to_string(),
+            daily_note_format: "YYYY-MM-DD".to_string(),
+            daily_note_template: "Templates/Daily.md".to_string(),
+            ..Default::default()
+        };
+        let mut config = NoteConfig {
+            folder: "Journal".to_string(),
+            format: "YYYY/YYYY-MM-DD".to_string(),
+            template: "Templates/Daily".to_string(),
+            source: ConfigSource::DailyNotes,
+        };
+        assert!(settings.daily_note_drift(&config).is_empty());
+
+        config.format = "YYYY/DD.MM.YYYY".to_string();
+        config.template = String::new();
+        assert_eq!(
+            settings.daily_note_drift(&config),
+            vec!["format", "template"]
+        );
+    }
+
+    #[test]
+    fn follows_obsidian_daily_note_config() {
+        let vault = crate::test_support::temp_dir("settings");
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        fs::write(
+            vault.join(".obsidian/daily-notes.json"),
+            r#"{"folder":"Daily/","format":"YYYY/MM/YYYY-MM-DD","template":"Templates/Day"}"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings {
+            vault_path: vault.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        settings.apply_obsidian_daily_notes();
+        assert_eq!(settings.daily_note_folder, default_daily_note_folder());
+
+        settings.follow_obsidian_daily_notes = true;
+        settings.apply_obsidian_daily_notes();
+        assert_eq!(settings.daily_note_folder, "[Daily]");
+        assert_eq!(settings.daily_note_format, "YYYY/MM/YYYY-MM-DD");
+        assert_eq!(settings.daily_note_template, "Templates/Day");
+        assert!(settings.validate().is_ok());
+
+        let _ = fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn obsidian_daily_note_folder_stays_literal() {
+        let (folder, format) = obsidian_folder_and_format("Daily MM/", "YYYY/Q/YYYY-MM-DD");
+        assert_eq!(folder, "[Daily MM]");
+
+        let dt = Utc.with_ymd_and_hms(2026, 10, 17, 9, 0, 0).unwrap();
+        assert_eq!(
+            render_daily_note_path(&folder, &format, &dt),
+            ("Daily MM/2026/4".to_string(), "2026-10-17".to_string())
+        );
+    }
+
+    #[test]
+    fn periodic_notes_default_to_periodic_notes_formats() {
+        let settings = Settings::default();
+        assert_eq!(settings.periodic_note(Period::Week).format, "gggg-[W]ww");
+        assert_eq!(settings.periodic_note(Period::Quarter).format, "YYYY-[Q]Q");
+        assert!(Settings::default().validate().is_ok());
+
+        let settings = Settings {
+            monthly_note: PeriodicNote::with_format("YYYY/MM"),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.validate().unwrap_err(),
+            "monthly note format must be a filename without path separators"
+        );
+    }
+
+    #[test]
+    fn rejects_daily_note_template_outside_vault() {
+        let settings = Settings {
+            daily_note_template: "../Templates/Daily.md".to_string(),
+            ..Default::default()
+        };
+
+        assert!(settings.validate().is_err());
+    }
+
     #[test]
     fn rejects_note_edge_delay_below_minimum() {
         let settings = Settings {
@@ -1009,6 +1857,53 @@ mod tests {
         assert_eq!(settings.screenshot_path, "Grafiken/Screenshots");
     }
 
+    #[test]
+    fn migrates_legacy_templates() {
+        let mut settings = Settings {
+            note_filename_template: "note-YYYY-MM-DD-HHmmss".to_string(),
+            image_filename: "screenshot-YYYY-MM-DD-HHmmss".to_string(),
+            entry_header: "#### Meeting HH:mm".to_string(),
+            daily_note_format: "YYYY-MM-DD Daily".to_string(),
+            template_syntax: 0,
+            ..Default::default()
+        };
+
+        assert!(settings.migrate_legacy_templates());
+        assert_eq!(settings.note_filename_template, "[note]-YYYY-MM-DD-HHmmss");
+        assert_eq!(settings.image_filename, "[screenshot]-YYYY-MM-DD-HHmmss");
+        assert_eq!(settings.entry_header, "#### [Meeting] HH:mm");
+        assert_eq!(settings.daily_note_format, "YYYY-MM-DD [Daily]");
+        assert!(!settings.migrate_legacy_templates());
+        assert_eq!(settings.note_filename_template, "[note]-YYYY-MM-DD-HHmmss");
+
+        let mut current = Settings::default();
+        assert!(!current.migrate_legacy_templates());
+    }
+
+    #[test]
+    fn migrates_legacy_daily_note_folder() {
+        let mut settings = Settings {
+            daily_note_folder: "Bookmarks/www/ddd YYYY".to_string(),
+            template_syntax: 0,
+            ..Default::default()
+        };
+
+        assert!(settings.migrate_legacy_templates());
+        assert_eq!(settings.daily_note_folder, "[Bookmarks/www/ddd] YYYY");
+
+        let dt = Utc.with_ymd_and_hms(2026, 3, 12, 9, 0, 0).unwrap();
+        let (folder, _) = render_daily_note_path(&settings.daily_note_folder, "YYYY-MM-DD", &dt);
+        assert_eq!(folder, "Bookmarks/www/ddd 2026");
+    }
+
+    #[test]
+    fn configs_without_template_syntax_are_legacy() {
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        value.as_object_mut().unwrap().remove("template_syntax");
+        let settings: Settings = serde_json::from_value(value).unwrap();
+        assert_eq!(settings.template_syntax, 0);
+    }
+
     #[test]
     fn rejects_absolute_screenshot_path_on_validate() {
         let settings = Settings {
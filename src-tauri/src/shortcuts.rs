@@ -76,6 +76,7 @@ impl ShortcutManager {
                                 }
                             }
 
+                            crate::remember_capture_source(&app_handle2).await;
                             warn_if_failed(
                                 app_handle2.emit("show_capture", ()),
                                 "Failed to emit show_capture",
@@ -163,9 +164,16 @@ impl ShortcutManager {
                 if event.state == ShortcutState::Pressed {
                     let app_handle2 = app_handle.clone();
                     tauri::async_runtime::spawn(async move {
-                        let selected = tauri::async_runtime::spawn_blocking(
-                            crate::selected_text::capture_selected_text,
-                        )
+                        crate::remember_capture_source(&app_handle2).await;
+                        let as_markdown = app_handle2
+                            .state::<crate::AppState>()
+                            .settings
+                            .read()
+                            .await
+                            .capture_text_as_markdown;
+                        let selected = tauri::async_runtime::spawn_blocking(move || {
+                            crate::selected_text::capture_selected_text(as_markdown)
+                        })
                         .await
                         .ok()
                         .flatten()
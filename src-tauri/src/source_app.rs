@@ -0,0 +1,243 @@
+//! The app (and window) a capture came from.
+//!
+//! Looked up when the capture window opens, while the other app is still
+//! frontmost, and written as `{{app}}` / `{{window}}` in the entry header or
+//! note template, as a `source::` inline field, or into new notes' frontmatter.
+
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter;
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceApp {
+    pub app: String,
+    #[serde(default)]
+    pub window: String,
+}
+
+/// Asks the system which app is frontmost.
+pub trait SourceAppProvider: Send + Sync {
+    fn frontmost(&self) -> Option<SourceApp>;
+}
+
+/// The real provider. Only macOS is supported; elsewhere nothing is recorded.
+pub struct SystemSourceApp;
+
+impl SourceAppProvider for SystemSourceApp {
+    #[cfg(target_os = "macos")]
+    fn frontmost(&self) -> Option<SourceApp> {
+        // Fenstertitel braucht die Bedienungshilfen-Freigabe, daher im try-Block
+        const SCRIPT: &str = r#"tell application "System Events"
+    set frontApp to first process whose frontmost is true
+    set appName to name of frontApp
+    set windowName to ""
+    try
+        set windowName to name of front window of frontApp
+    end try
+end tell
+return appName & linefeed & windowName"#;
+
+        let output = std::process::Command::new("osascript")
+            .arg("-e")
+            .arg(SCRIPT)
+            .output()
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (app, window) = stdout.split_once('\n').unwrap_or((&stdout, ""));
+        if app.trim().is_empty() {
+            return None;
+        }
+
+        Some(SourceApp {
+            app: app.trim().to_string(),
+            window: window.trim().to_string(),
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn frontmost(&self) -> Option<SourceApp> {
+        None
+    }
+}
+
+/// Updates `current` with the frontmost app. If that is Collector itself, its
+/// window was already shown and the app recorded before it is kept.
+pub fn refresh(current: &mut Option<SourceApp>, provider: &dyn SourceAppProvider, own_app: &str) {
+    let found = provider.frontmost().map(|source| SourceApp {
+        app: single_line(&source.app),
+        window: single_line(&source.window),
+    });
+
+    match found {
+        Some(source) if source.app.eq_ignore_ascii_case(own_app) => {}
+        Some(source) if !source.app.is_empty() => *current = Some(source),
+        _ => *current = None,
+    }
+}
+
+fn single_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The settings a capture from `source` is written with: `{{app}}` and
+/// `{{window}}` filled in (empty without a source) and, if enabled, the source
+/// added to the frontmatter of new notes.
+pub fn settings_for_source(settings: &Settings, source: Option<&SourceApp>) -> Settings {
+    let mut settings = settings.clone();
+    let app = source.map(|s| s.app.as_str()).unwrap_or_default();
+    let window = source.map(|s| s.window.as_str()).unwrap_or_default();
+
+    // Der Header geht noch durch die Datumsformatierung: Werte dort escapen
+    settings.entry_header = settings
+        .entry_header
+        .replace("{{app}}", &escape_date_literal(app))
+        .replace("{{window}}", &escape_date_literal(window));
+    settings.note_template = settings
+        .note_template
+        .replace("{{app}}", app)
+        .replace("{{window}}", window);
+
+    if let Some(source) = source.filter(|_| settings.source_app_frontmatter) {
+        let mut fields = vec![("source", source.app.as_str())];
+        if !source.window.is_empty() {
+            fields.push(("source_window", source.window.as_str()));
+        }
+        settings.note_template = frontmatter::add_fields(&settings.note_template, &fields);
+    }
+
+    settings
+}
+
+/// `text` with a `source:: App` inline field on its last line, if enabled.
+pub fn text_with_source(text: &str, settings: &Settings, source: Option<&SourceApp>) -> String {
+    match source.filter(|_| settings.source_app_inline_field) {
+        Some(source) => format!("{}\nsource:: {}", text.trim_end(), source.app),
+        None => text.to_string(),
+    }
+}
+
+/// Wraps `value` in `[...]` so date tokens in it (the `a` in "Safari") stay
+/// literal. `]` can't be escaped inside brackets, but is no token itself.
+fn escape_date_literal(value: &str) -> String {
+    value
+        .split(']')
+        .map(|part| {
+            if part.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]", part)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+    use chrono::{FixedOffset, TimeZone};
+
+    struct FakeProvider(Option<SourceApp>);
+
+    impl SourceAppProvider for FakeProvider {
+        fn frontmost(&self) -> Option<SourceApp> {
+            self.0.clone()
+        }
+    }
+
+    fn safari() -> SourceApp {
+        SourceApp {
+            app: "Safari".to_string(),
+            window: "Release notes [draft]".to_string(),
+        }
+    }
+
+    #[test]
+    fn refresh_keeps_the_app_from_before_collector_took_focus() {
+        let mut current = None;
+        let provider = FakeProvider(Some(SourceApp {
+            app: "Safari\n".to_string(),
+            window: "Release  notes\n[draft]".to_string(),
+        }));
+        refresh(&mut current, &provider, "Collector");
+        assert_eq!(current, Some(safari()));
+
+        let collector = FakeProvider(Some(SourceApp {
+            app: "Collector".to_string(),
+            window: String::new(),
+        }));
+        refresh(&mut current, &collector, "Collector");
+        assert_eq!(current, Some(safari()));
+
+        refresh(&mut current, &FakeProvider(None), "Collector");
+        assert_eq!(current, None);
+    }
+
+    #[test]
+    fn header_placeholders_survive_date_formatting() {
+        let settings = Settings {
+            entry_header: "#### HH:mm {{app}} – {{window}}".to_string(),
+            ..Default::default()
+        };
+        let source = safari();
+        let dt = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 15, 14, 30, 0)
+            .unwrap();
+
+        let header = settings_for_source(&settings, Some(&source)).entry_header;
+        assert_eq!(
+            template::format_date(&header, &dt),
+            "#### 14:30 Safari – Release notes [draft]"
+        );
+
+        let header = settings_for_source(&settings, None).entry_header;
+        assert_eq!(template::format_date(&header, &dt), "#### 14:30  – ");
+    }
+
+    #[test]
+    fn adds_source_to_note_frontmatter() {
+        let settings = Settings {
+            note_template: "---\ntags: inbox\n---\nFrom {{app}}".to_string(),
+            source_app_frontmatter: true,
+            ..Default::default()
+        };
+
+        let template = settings_for_source(&settings, Some(&safari())).note_template;
+        assert_eq!(
+            template,
+            "---\ntags: inbox\nsource: \"Safari\"\nsource_window: \"Release notes [draft]\"\n---\nFrom Safari"
+        );
+
+        let settings = Settings {
+            note_template: String::new(),
+            ..settings
+        };
+        let source = SourceApp {
+            app: "Mail".to_string(),
+            window: String::new(),
+        };
+        assert_eq!(
+            settings_for_source(&settings, Some(&source)).note_template,
+            "---\nsource: \"Mail\"\n---"
+        );
+    }
+
+    #[test]
+    fn inline_field_only_when_enabled() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            text_with_source("idea\n", &settings, Some(&safari())),
+            "idea\n"
+        );
+
+        settings.source_app_inline_field = true;
+        assert_eq!(
+            text_with_source("idea\n", &settings, Some(&safari())),
+            "idea\nsource:: Safari"
+        );
+        assert_eq!(text_with_source("idea", &settings, None), "idea");
+    }
+}
@@ -0,0 +1,154 @@
+//! Auto-tagging: tags for a capture from the app it came from (`Xcode` →
+//! `#dev`) or from its content (a JIRA key → `#work`).
+
+use regex::Regex;
+
+use crate::settings::{TagMatch, TagRule};
+use crate::source_app::SourceApp;
+
+/// Tags of all matching rules, normalized and without duplicates or tags the
+/// text already contains. Returned without `#`.
+pub fn tags_for(rules: &[TagRule], text: &str, source: Option<&SourceApp>) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for rule in rules {
+        let matches = match rule.match_type {
+            TagMatch::App => {
+                source.is_some_and(|source| source.app.eq_ignore_ascii_case(rule.pattern.trim()))
+            }
+            // Ungültige Muster fängt validate() ab
+            TagMatch::Regex => Regex::new(&rule.pattern).is_ok_and(|re| re.is_match(text)),
+        };
+        if !matches {
+            continue;
+        }
+
+        for tag in rule.tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+    }
+
+    let present: Vec<String> = text
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .filter_map(normalize_tag)
+        .collect();
+    tags.retain(|tag| !present.iter().any(|p| p.eq_ignore_ascii_case(tag)));
+    tags
+}
+
+/// `text` with `#tags` on a line of their own at the end.
+pub fn text_with_tags(text: &str, tags: &[String]) -> String {
+    if tags.is_empty() {
+        return text.to_string();
+    }
+
+    let line: Vec<String> = tags.iter().map(|tag| format!("#{}", tag)).collect();
+    format!("{}\n{}", text.trim_end(), line.join(" "))
+}
+
+/// Turns `value` into Obsidian tag syntax: no `#`, spaces become `-`, only
+/// letters, digits, `_`, `-` and `/`, and not only digits.
+pub fn normalize_tag(value: &str) -> Option<String> {
+    let tag: String = value
+        .trim()
+        .trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        .collect();
+    let tag = tag.trim_matches('/').to_string();
+
+    if tag.chars().all(|c| c.is_ascii_digit() || c == '/') {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+pub fn validate_rule(rule: &TagRule) -> Result<(), String> {
+    if rule.pattern.trim().is_empty() {
+        return Err("auto_tag_rules entries must include an app or pattern".to_string());
+    }
+    if rule.match_type == TagMatch::Regex {
+        Regex::new(&rule.pattern)
+            .map_err(|e| format!("auto_tag_rules pattern is not a valid regex: {}", e))?;
+    }
+    if !rule.tags.iter().any(|tag| normalize_tag(tag).is_some()) {
+        return Err("auto_tag_rules entries must include at least one valid tag".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(match_type: TagMatch, pattern: &str, tags: &[&str]) -> TagRule {
+        TagRule {
+            match_type,
+            pattern: pattern.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn source(app: &str) -> SourceApp {
+        SourceApp {
+            app: app.to_string(),
+            window: String::new(),
+        }
+    }
+
+    #[test]
+    fn tags_by_app_and_content() {
+        let rules = vec![
+            rule(TagMatch::App, "Xcode", &["#dev"]),
+            rule(TagMatch::App, "Mail", &["email"]),
+            rule(TagMatch::Regex, r"\b[A-Z]+-\d+\b", &["work", "Dev"]),
+        ];
+
+        assert_eq!(
+            tags_for(&rules, "fix PROJ-123 crash", Some(&source("xcode"))),
+            vec!["dev", "work"]
+        );
+        assert_eq!(
+            tags_for(&rules, "lunch?", Some(&source("Mail"))),
+            vec!["email"]
+        );
+        assert!(tags_for(&rules, "lunch?", None).is_empty());
+    }
+
+    #[test]
+    fn skips_tags_already_in_the_text() {
+        let rules = vec![rule(TagMatch::App, "Xcode", &["dev", "ios"])];
+        assert_eq!(
+            tags_for(&rules, "build broke #Dev", Some(&source("Xcode"))),
+            vec!["ios"]
+        );
+    }
+
+    #[test]
+    fn normalizes_to_obsidian_tag_syntax() {
+        assert_eq!(
+            normalize_tag("#Project Alpha").as_deref(),
+            Some("Project-Alpha")
+        );
+        assert_eq!(normalize_tag("work/jira!").as_deref(), Some("work/jira"));
+        assert_eq!(normalize_tag("/2024/"), None);
+        assert_eq!(normalize_tag(" # "), None);
+        assert_eq!(normalize_tag("y2024").as_deref(), Some("y2024"));
+    }
+
+    #[test]
+    fn appends_tag_line() {
+        assert_eq!(
+            text_with_tags("idea\n", &["dev".to_string(), "work".to_string()]),
+            "idea\n#dev #work"
+        );
+        assert_eq!(text_with_tags("idea", &[]), "idea");
+    }
+}
@@ -0,0 +1,513 @@
+//! Natural-language dates in task captures: "tomorrow", "next friday 3pm",
+//! "in 2 weeks", "every monday". Parsed offline against the capture time,
+//! removed from the task text and written as Obsidian Tasks emoji fields
+//! (`📅 2026-10-18`) or Dataview inline fields (`[due:: 2026-10-18]`).
+//!
+//! Only phrases at the end of the task or after `due`, `by`, `on` or
+//! `scheduled` are read, so "Email Jan 5 recipients" stays as typed.
+//!
+//! The Tasks plugin has no times, so a time is only kept in Dataview format.
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use std::ops::Range;
+
+use crate::settings::TaskDateFormat;
+
+type When = (NaiveDate, Option<NaiveTime>);
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TaskDates {
+    due: Option<When>,
+    scheduled: Option<When>,
+    /// Tasks recurrence rule, e.g. `every week`.
+    recurrence: Option<String>,
+}
+
+/// A word of the task text, lowercased and without trailing punctuation.
+struct Word {
+    range: Range<usize>,
+    text: String,
+}
+
+enum Found {
+    Due(When),
+    Scheduled(When),
+    Repeat(String),
+}
+
+/// A date phrase in the task text, as a range of words.
+struct Phrase {
+    words: Range<usize>,
+    found: Found,
+    /// Introduced by `due`, `by`, `on` or `scheduled`.
+    keyword: bool,
+}
+
+/// `text` with its date phrases replaced by fields in `format`. Only the first
+/// due date, scheduled date and recurrence are used; `now` is the capture time.
+pub fn apply(text: &str, format: TaskDateFormat, now: NaiveDateTime) -> String {
+    if format == TaskDateFormat::Off {
+        return text.to_string();
+    }
+
+    let (rest, dates) = extract(text, now.date());
+    if dates == TaskDates::default() {
+        return text.to_string();
+    }
+
+    let mut out = rest;
+    for (field, value) in [
+        (Field::Repeat, dates.recurrence),
+        (
+            Field::Scheduled,
+            dates.scheduled.map(|when| format_when(when, format)),
+        ),
+        (Field::Due, dates.due.map(|when| format_when(when, format))),
+    ] {
+        if let Some(value) = value {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(&field.render(&value, format));
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Repeat,
+    Scheduled,
+    Due,
+}
+
+impl Field {
+    fn render(self, value: &str, format: TaskDateFormat) -> String {
+        match (format, self) {
+            (TaskDateFormat::Dataview, Field::Repeat) => format!("[repeat:: {}]", value),
+            (TaskDateFormat::Dataview, Field::Scheduled) => format!("[scheduled:: {}]", value),
+            (TaskDateFormat::Dataview, Field::Due) => format!("[due:: {}]", value),
+            (_, Field::Repeat) => format!("🔁 {}", value),
+            (_, Field::Scheduled) => format!("⏳ {}", value),
+            (_, Field::Due) => format!("📅 {}", value),
+        }
+    }
+}
+
+fn format_when((date, time): When, format: TaskDateFormat) -> String {
+    match time.filter(|_| format == TaskDateFormat::Dataview) {
+        Some(time) => format!("{}T{}", date.format("%Y-%m-%d"), time.format("%H:%M")),
+        None => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+fn extract(text: &str, today: NaiveDate) -> (String, TaskDates) {
+    let words = words(text);
+    let phrases = phrases(&words, today);
+
+    // Ohne Schlüsselwort nur am Ende, und nie mit mehrdeutigen Wörtern wie "may"
+    let mut accepted = vec![false; phrases.len()];
+    let mut tail = words.len();
+    for (index, phrase) in phrases.iter().enumerate().rev() {
+        let clear = !phrase.words.clone().any(|i| ambiguous(&words[i].text));
+        accepted[index] = phrase.keyword || (phrase.words.end == tail && clear);
+        if accepted[index] && phrase.words.end == tail {
+            tail = phrase.words.start;
+        }
+    }
+
+    // Phrasen mit Schlüsselwort gehen vor: in "monday due friday" ist friday fällig
+    let by_keyword = |keyword: bool| {
+        phrases
+            .iter()
+            .zip(&accepted)
+            .filter(move |(phrase, &accepted)| accepted && phrase.keyword == keyword)
+            .map(|(phrase, _)| phrase)
+    };
+    let mut dates = TaskDates::default();
+    let mut removed = Vec::new();
+    for phrase in by_keyword(true).chain(by_keyword(false)) {
+        match &phrase.found {
+            Found::Due(when) if dates.due.is_none() => dates.due = Some(*when),
+            Found::Scheduled(when) if dates.scheduled.is_none() => dates.scheduled = Some(*when),
+            Found::Repeat(rule) if dates.recurrence.is_none() => {
+                dates.recurrence = Some(rule.clone())
+            }
+            _ => continue,
+        }
+        removed.push(words[phrase.words.start].range.start..words[phrase.words.end - 1].range.end);
+    }
+    removed.sort_by_key(|range| range.start);
+
+    (remove_ranges(text, &removed), dates)
+}
+
+/// Every date phrase in `words`, left to right and not overlapping.
+fn phrases(words: &[Word], today: NaiveDate) -> Vec<Phrase> {
+    let mut phrases = Vec::new();
+    let mut index = 0;
+
+    while index < words.len() {
+        let rest: Vec<&str> = words[index..].iter().map(|w| w.text.as_str()).collect();
+        let found = match rest[0] {
+            "every" => parse_recurrence(&rest[1..])
+                .map(|(len, rule)| (len + 1, Found::Repeat(rule), false)),
+            "scheduled" | "sched" => {
+                let skip = 1 + usize::from(matches!(rest.get(1), Some(&"for") | Some(&"on")));
+                parse_when(&rest[skip..], today)
+                    .map(|(len, when)| (len + skip, Found::Scheduled(when), true))
+            }
+            "due" | "by" | "on" => {
+                parse_when(&rest[1..], today).map(|(len, when)| (len + 1, Found::Due(when), true))
+            }
+            _ => parse_when(&rest, today).map(|(len, when)| (len, Found::Due(when), false)),
+        };
+
+        match found {
+            Some((len, found, keyword)) => {
+                phrases.push(Phrase {
+                    words: index..index + len,
+                    found,
+                    keyword,
+                });
+                index += len;
+            }
+            None => index += 1,
+        }
+    }
+    phrases
+}
+
+/// Words that are only sometimes dates ("may 5 items left"); read only after
+/// a keyword.
+fn ambiguous(word: &str) -> bool {
+    matches!(word, "may" | "march" | "mar")
+}
+
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(begin), true) => {
+                let raw = text[begin..index].trim_end_matches([',', '.', ';', '!', '?']);
+                if !raw.is_empty() {
+                    words.push(Word {
+                        range: begin..begin + raw.len(),
+                        text: raw.to_lowercase(),
+                    });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Removes `ranges` (in order, not overlapping) without leaving doubled
+/// spaces or spaces before punctuation behind.
+fn remove_ranges(text: &str, ranges: &[Range<usize>]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for range in ranges {
+        out.push_str(&text[last..range.start]);
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+
+    let joined = out.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut cleaned = String::with_capacity(joined.len());
+    for c in joined.chars() {
+        if matches!(c, ',' | '.' | ';' | '!' | '?') && cleaned.ends_with(' ') {
+            cleaned.pop();
+        }
+        cleaned.push(c);
+    }
+    cleaned
+        .trim_end_matches([',', ';'])
+        .trim_start_matches([',', ';', ' '])
+        .to_string()
+}
+
+/// A date, optionally followed by a time. Returns the number of words used.
+fn parse_when(words: &[&str], today: NaiveDate) -> Option<(usize, When)> {
+    let (len, date) = parse_date(words, today)?;
+    match parse_time(&words[len..]) {
+        Some((time_len, time)) => Some((len + time_len, (date, Some(time)))),
+        None => Some((len, (date, None))),
+    }
+}
+
+fn parse_date(words: &[&str], today: NaiveDate) -> Option<(usize, NaiveDate)> {
+    let first = *words.first()?;
+    let second = words.get(1).copied().unwrap_or_default();
+
+    match first {
+        "today" | "tonight" => return Some((1, today)),
+        "tomorrow" | "tmrw" => return Some((1, today + Days::new(1))),
+        "day" if second == "after" && words.get(2) == Some(&"tomorrow") => {
+            return Some((3, today + Days::new(2)))
+        }
+        "this" | "next" => {
+            if let Some(weekday) = weekday(second) {
+                return Some((2, coming(today, weekday)));
+            }
+            if first == "next" {
+                let date = match second {
+                    "week" => coming(today, Weekday::Mon),
+                    "month" => today.with_day(1)? + Months::new(1),
+                    "year" => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)?,
+                    _ => return None,
+                };
+                return Some((2, date));
+            }
+            return None;
+        }
+        "in" => {
+            let count = number(second)?;
+            let unit = unit(words.get(2)?)?;
+            return Some((3, unit.add(today, count)?));
+        }
+        _ => {}
+    }
+
+    if let Some(weekday) = weekday(first) {
+        return Some((1, coming(today, weekday)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+        return Some((1, date));
+    }
+
+    // "oct 18", "october 18th" oder "18 oct"
+    let (month, day) = match (month(first), month(second)) {
+        (Some(month), _) => (month, day_of_month(second)?),
+        (None, Some(month)) => (month, day_of_month(first)?),
+        (None, None) => return None,
+    };
+    let this_year = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if this_year >= today {
+        Some((2, this_year))
+    } else {
+        Some((2, NaiveDate::from_ymd_opt(today.year() + 1, month, day)?))
+    }
+}
+
+/// `3pm`, `3:30 pm`, `15:00`, `noon`, optionally after `at`. A bare number
+/// only counts as a time after `at`.
+fn parse_time(words: &[&str]) -> Option<(usize, NaiveTime)> {
+    let (skip, after_at) = match words.first() {
+        Some(&"at") => (1, true),
+        _ => (0, false),
+    };
+    let word = *words.get(skip)?;
+    let next = words.get(skip + 1).copied().unwrap_or_default();
+
+    match word {
+        "noon" => return Some((skip + 1, NaiveTime::from_hms_opt(12, 0, 0)?)),
+        "midnight" => return Some((skip + 1, NaiveTime::from_hms_opt(0, 0, 0)?)),
+        _ => {}
+    }
+
+    let (clock, suffix, len) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else if next == "am" || next == "pm" {
+        (word, Some(next == "pm"), 2)
+    } else {
+        (word, None, 1)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => {
+            (hour.parse::<u32>().ok()?, minute.parse().ok()?)
+        }
+        Some(_) => return None,
+        None if suffix.is_some() || after_at => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+    let hour = match suffix {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    Some((skip + len, NaiveTime::from_hms_opt(hour, minute, 0)?))
+}
+
+fn parse_recurrence(words: &[&str]) -> Option<(usize, String)> {
+    let first = *words.first()?;
+    if let Some(weekday) = weekday(first) {
+        return Some((1, format!("every {}", weekday_name(weekday))));
+    }
+    if first == "weekday" {
+        return Some((1, "every weekday".to_string()));
+    }
+    if let Some(unit) = unit(first) {
+        return Some((1, format!("every {}", unit.singular())));
+    }
+
+    let count = if first == "other" { 2 } else { number(first)? };
+    let unit = unit(words.get(1)?)?;
+    let rule = if count == 1 {
+        format!("every {}", unit.singular())
+    } else {
+        format!("every {} {}s", count, unit.singular())
+    };
+    Some((2, rule))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    fn singular(self) -> &'static str {
+        match self {
+            Unit::Day => "day",
+            Unit::Week => "week",
+            Unit::Month => "month",
+            Unit::Year => "year",
+        }
+    }
+
+    fn add(self, date: NaiveDate, count: u32) -> Option<NaiveDate> {
+        match self {
+            Unit::Day => date.checked_add_days(Days::new(count.into())),
+            Unit::Week => date.checked_add_days(Days::new(u64::from(count) * 7)),
+            Unit::Month => date.checked_add_months(Months::new(count)),
+            Unit::Year => date.checked_add_months(Months::new(count.checked_mul(12)?)),
+        }
+    }
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    let unit = match word.trim_end_matches('s') {
+        "day" => Unit::Day,
+        "week" | "wk" => Unit::Week,
+        "month" => Unit::Month,
+        "year" | "yr" => Unit::Year,
+        _ => return None,
+    };
+    Some(unit)
+}
+
+fn number(word: &str) -> Option<u32> {
+    const WORDS: [&str; 12] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+        "twelve",
+    ];
+    match word {
+        "a" | "an" => Some(1),
+        _ => WORDS
+            .iter()
+            .position(|name| *name == word)
+            .map(|index| index as u32 + 1)
+            .or_else(|| word.parse().ok().filter(|n| (1..=999).contains(n))),
+    }
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        // "sat", "sun" und "wed" sind auch gewöhnliche Wörter
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" => Weekday::Wed,
+        "thursday" | "thu" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" => Weekday::Sat,
+        "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// The next `weekday` after `today`, one to seven days ahead.
+fn coming(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Days::new(if ahead == 0 { 7 } else { ahead.into() })
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    MONTHS
+        .iter()
+        .position(|name| word.len() >= 3 && name.starts_with(word))
+        .map(|index| index as u32 + 1)
+}
+
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every line of the fixture: input, Tasks output and Dataview output,
+    /// separated by ` | `, against Saturday 2026-10-17 09:00.
+    #[test]
+    fn parses_fixture_phrases() {
+        let now = NaiveDate::from_ymd_opt(2026, 10, 17)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let cases = include_str!("../tests/fixtures/task_dates.txt");
+
+        for line in cases
+            .lines()
+            .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        {
+            let parts: Vec<&str> = line.split(" | ").collect();
+            assert_eq!(parts.len(), 3, "{}", line);
+            assert_eq!(
+                apply(parts[0], TaskDateFormat::Tasks, now),
+                parts[1],
+                "{}",
+                line
+            );
+            assert_eq!(
+                apply(parts[0], TaskDateFormat::Dataview, now),
+                parts[2],
+                "{}",
+                line
+            );
+            assert_eq!(apply(parts[0], TaskDateFormat::Off, now), parts[0]);
+        }
+    }
+}
@@ -0,0 +1,728 @@
+//! Date/time templates with Moment.js tokens, interpreted the way Obsidian does
+//! (English locale: weeks start on Sunday, week 1 contains January 1st).
+//!
+//! Text wrapped in `[...]` is copied verbatim, e.g. `[Week] ww` → `Week 07`.
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Year4,
+    Year2,
+    Quarter,
+    MonthName,
+    MonthShort,
+    Month2,
+    MonthOrdinal,
+    Month,
+    DayOfYear3,
+    DayOfYearOrdinal,
+    DayOfYear,
+    DayOfMonthOrdinal,
+    DayOfMonth2,
+    DayOfMonth,
+    WeekdayName,
+    WeekdayShort,
+    WeekdayMin,
+    WeekdayOrdinal,
+    Weekday,
+    LocaleWeekday,
+    IsoWeekday,
+    LocaleWeekOrdinal,
+    LocaleWeek2,
+    LocaleWeek,
+    IsoWeekOrdinal,
+    IsoWeek2,
+    IsoWeek,
+    LocaleWeekYear4,
+    LocaleWeekYear2,
+    IsoWeekYear4,
+    IsoWeekYear2,
+    Hour24Pad,
+    Hour24,
+    Hour12Pad,
+    Hour12,
+    Hour1To24Pad,
+    Hour1To24,
+    MinutePad,
+    Minute,
+    SecondPad,
+    Second,
+    Millis,
+    AmPmLower,
+    AmPmUpper,
+    UnixSeconds,
+    UnixMillis,
+    OffsetCompact,
+    Offset,
+}
+
+/// Token table, longest token first so e.g. `MMMM` wins over `MM` and `hh` over `h`.
+/// The flag marks tokens that are also expanded in folder templates (see `format_path`).
+const TOKENS: &[(&str, Token, bool)] = &[
+    ("YYYY", Token::Year4, true),
+    ("MMMM", Token::MonthName, true),
+    ("DDDD", Token::DayOfYear3, true),
+    ("DDDo", Token::DayOfYearOrdinal, false),
+    ("dddd", Token::WeekdayName, true),
+    ("gggg", Token::LocaleWeekYear4, true),
+    ("GGGG", Token::IsoWeekYear4, true),
+    ("MMM", Token::MonthShort, true),
+    ("DDD", Token::DayOfYear, false),
+    ("ddd", Token::WeekdayShort, true),
+    ("SSS", Token::Millis, false),
+    ("YY", Token::Year2, true),
+    ("MM", Token::Month2, true),
+    ("Mo", Token::MonthOrdinal, false),
+    ("DD", Token::DayOfMonth2, true),
+    ("Do", Token::DayOfMonthOrdinal, false),
+    ("dd", Token::WeekdayMin, false),
+    ("do", Token::WeekdayOrdinal, false),
+    ("ww", Token::LocaleWeek2, true),
+    ("wo", Token::LocaleWeekOrdinal, false),
+    ("WW", Token::IsoWeek2, true),
+    ("Wo", Token::IsoWeekOrdinal, false),
+    ("gg", Token::LocaleWeekYear2, false),
+    ("GG", Token::IsoWeekYear2, false),
+    ("HH", Token::Hour24Pad, false),
+    ("hh", Token::Hour12Pad, false),
+    ("kk", Token::Hour1To24Pad, false),
+    ("mm", Token::MinutePad, false),
+    ("ss", Token::SecondPad, false),
+    ("ZZ", Token::OffsetCompact, false),
+    ("Q", Token::Quarter, false),
+    ("M", Token::Month, false),
+    ("D", Token::DayOfMonth, false),
+    ("d", Token::Weekday, false),
+    ("e", Token::LocaleWeekday, false),
+    ("E", Token::IsoWeekday, false),
+    ("w", Token::LocaleWeek, false),
+    ("W", Token::IsoWeek, false),
+    ("H", Token::Hour24, false),
+    ("h", Token::Hour12, false),
+    ("k", Token::Hour1To24, false),
+    ("m", Token::Minute, false),
+    ("s", Token::Second, false),
+    ("a", Token::AmPmLower, false),
+    ("A", Token::AmPmUpper, false),
+    ("X", Token::UnixSeconds, false),
+    ("x", Token::UnixMillis, false),
+    ("Z", Token::Offset, false),
+];
+
+impl Token {
+    fn is_time(self) -> bool {
+        matches!(
+            self,
+            Token::Hour24Pad
+                | Token::Hour24
+                | Token::Hour12Pad
+                | Token::Hour12
+                | Token::Hour1To24Pad
+                | Token::Hour1To24
+                | Token::MinutePad
+                | Token::Minute
+                | Token::SecondPad
+                | Token::Second
+                | Token::Millis
+                | Token::AmPmLower
+                | Token::AmPmUpper
+        )
+    }
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Render a Moment.js format string for `dt`.
+pub fn format_date<Tz: TimeZone>(template: &str, dt: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    render(template, dt, false)
+}
+
+/// Render a folder template. Folder names are literal in Obsidian, so only
+/// unambiguous date tokens (`YYYY`, `MM`, `DD`, `MMMM`, `ww`, `gggg`, ...) are
+/// expanded here; single letters and time tokens stay as typed.
+pub fn format_path<Tz: TimeZone>(template: &str, dt: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    render(template, dt, true)
+}
+
+/// Expand the variables of Obsidian's core Templates plugin in a note template:
+/// `{{title}}`, `{{date}}`, `{{time}}` and `{{date:FORMAT}}` / `{{time:FORMAT}}`.
+/// Names are case-insensitive; anything else in braces is left untouched so
+/// other plugins (e.g. Templater) can still process it.
+pub fn expand_note_template<Tz: TimeZone>(content: &str, title: &str, dt: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        out.push_str(&rest[..start]);
+
+        let (name, format) = match inner.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format.trim())),
+            None => (inner.trim(), None),
+        };
+        match (name.to_ascii_lowercase().as_str(), format) {
+            ("title", None) => out.push_str(title),
+            ("date", format) => out.push_str(&format_date(format.unwrap_or("YYYY-MM-DD"), dt)),
+            ("time", format) => out.push_str(&format_date(format.unwrap_or("HH:mm"), dt)),
+            _ => {
+                out.push_str("{{");
+                out.push_str(inner);
+                out.push_str("}}");
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// `template` with its time of day (`HH:mm`, `h:mm a`, ...) replaced by the
+/// literal text `placeholder`, for entries whose time is not known.
+pub fn replace_time(template: &str, placeholder: &str) -> String {
+    let is_time = |text: &str| {
+        TOKENS
+            .iter()
+            .any(|(name, token, _)| token.is_time() && text.starts_with(name))
+    };
+    let mut result = String::with_capacity(template.len() + placeholder.len());
+    let mut remaining = template;
+    let mut in_time = false;
+
+    while let Some(ch) = remaining.chars().next() {
+        if ch == '[' {
+            if let Some(end) = remaining[1..].find(']') {
+                result.push_str(&remaining[..end + 2]);
+                remaining = &remaining[end + 2..];
+                in_time = false;
+                continue;
+            }
+        }
+
+        if let Some((name, token, _)) = TOKENS
+            .iter()
+            .find(|(name, _, _)| remaining.starts_with(name))
+        {
+            if !token.is_time() {
+                result.push_str(name);
+                in_time = false;
+            } else if !in_time {
+                result.push('[');
+                result.push_str(placeholder);
+                result.push(']');
+                in_time = true;
+            }
+            remaining = &remaining[name.len()..];
+        } else {
+            // Trennzeichen innerhalb der Uhrzeit ("HH:mm", "h:mm a") gehören dazu
+            let rest = &remaining[ch.len_utf8()..];
+            if !(in_time && matches!(ch, ':' | '.' | ' ') && is_time(rest)) {
+                result.push(ch);
+                in_time = false;
+            }
+            remaining = rest;
+        }
+    }
+
+    result
+}
+
+/// Rewrite a template written for the old token engine, which only knew
+/// `tokens`, so it renders the same here: literal words are wrapped in `[...]`
+/// and literal brackets are escaped as `[[]`.
+pub fn escape_legacy(template: &str, tokens: &[&str]) -> String {
+    let mut result = String::with_capacity(template.len() + 8);
+    let mut literal = String::new();
+    let mut remaining = template;
+
+    while let Some(ch) = remaining.chars().next() {
+        if let Some(token) = tokens.iter().find(|token| remaining.starts_with(*token)) {
+            push_literal(&mut result, &literal);
+            literal.clear();
+            result.push_str(token);
+            remaining = &remaining[token.len()..];
+        } else {
+            literal.push(ch);
+            remaining = &remaining[ch.len_utf8()..];
+        }
+    }
+
+    push_literal(&mut result, &literal);
+    result
+}
+
+fn push_literal(result: &mut String, literal: &str) {
+    let mut rest = literal;
+    while !rest.is_empty() {
+        let end = rest.find(['[', ']']).unwrap_or(rest.len());
+        let chunk = &rest[..end];
+        // Nur den Bereich von Buchstabe bis Buchstabe klammern: "note-" → "[note]-"
+        match (
+            chunk.find(char::is_alphabetic),
+            chunk.rfind(char::is_alphabetic),
+        ) {
+            (Some(first), Some(last)) => {
+                let last = last + chunk[last..].chars().next().map_or(0, char::len_utf8);
+                result.push_str(&chunk[..first]);
+                result.push('[');
+                result.push_str(&chunk[first..last]);
+                result.push(']');
+                result.push_str(&chunk[last..]);
+            }
+            _ => result.push_str(chunk),
+        }
+        match rest[end..].chars().next() {
+            Some('[') => result.push_str("[[]"),
+            Some(_) => result.push(']'),
+            None => {}
+        }
+        rest = &rest[(end + 1).min(rest.len())..];
+    }
+}
+
+fn render<Tz: TimeZone>(template: &str, dt: &DateTime<Tz>, path_only: bool) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    // Single-Pass: Tokens werden nur im Original-Template erkannt,
+    // eingesetzte Werte (z.B. "pm" enthält 'm') werden nie erneut ersetzt.
+    let mut result = String::with_capacity(template.len() * 2);
+    let mut remaining = template;
+
+    while let Some(ch) = remaining.chars().next() {
+        if ch == '[' {
+            if let Some(end) = remaining[1..].find(']') {
+                result.push_str(&remaining[1..=end]);
+                remaining = &remaining[end + 2..];
+                continue;
+            }
+        }
+
+        let token = TOKENS
+            .iter()
+            .filter(|(_, _, in_paths)| !path_only || *in_paths)
+            .find(|(name, _, _)| remaining.starts_with(name));
+
+        if let Some((name, token, _)) = token {
+            result.push_str(&expand(*token, dt));
+            remaining = &remaining[name.len()..];
+        } else {
+            result.push(ch);
+            remaining = &remaining[ch.len_utf8()..];
+        }
+    }
+
+    result
+}
+
+fn expand<Tz: TimeZone>(token: Token, dt: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let date = dt.date_naive();
+    let hour12 = match dt.hour() % 12 {
+        0 => 12,
+        h => h,
+    };
+    let weekday = dt.weekday().num_days_from_sunday();
+    let (locale_week_year, locale_week) = locale_week(date);
+    let iso = dt.iso_week();
+
+    match token {
+        Token::Year4 => format!("{:04}", dt.year()),
+        Token::Year2 => format!("{:02}", dt.year().rem_euclid(100)),
+        Token::Quarter => ((dt.month() - 1) / 3 + 1).to_string(),
+        Token::MonthName => MONTH_NAMES[dt.month0() as usize].to_string(),
+        Token::MonthShort => MONTH_NAMES[dt.month0() as usize][..3].to_string(),
+        Token::Month2 => format!("{:02}", dt.month()),
+        Token::MonthOrdinal => ordinal(dt.month()),
+        Token::Month => dt.month().to_string(),
+        Token::DayOfYear3 => format!("{:03}", dt.ordinal()),
+        Token::DayOfYearOrdinal => ordinal(dt.ordinal()),
+        Token::DayOfYear => dt.ordinal().to_string(),
+        Token::DayOfMonthOrdinal => ordinal(dt.day()),
+        Token::DayOfMonth2 => format!("{:02}", dt.day()),
+        Token::DayOfMonth => dt.day().to_string(),
+        Token::WeekdayName => WEEKDAY_NAMES[weekday as usize].to_string(),
+        Token::WeekdayShort => WEEKDAY_NAMES[weekday as usize][..3].to_string(),
+        Token::WeekdayMin => WEEKDAY_NAMES[weekday as usize][..2].to_string(),
+        Token::WeekdayOrdinal => ordinal(weekday),
+        Token::Weekday | Token::LocaleWeekday => weekday.to_string(),
+        Token::IsoWeekday => dt.weekday().number_from_monday().to_string(),
+        Token::LocaleWeekOrdinal => ordinal(locale_week),
+        Token::LocaleWeek2 => format!("{:02}", locale_week),
+        Token::LocaleWeek => locale_week.to_string(),
+        Token::IsoWeekOrdinal => ordinal(iso.week()),
+        Token::IsoWeek2 => format!("{:02}", iso.week()),
+        Token::IsoWeek => iso.week().to_string(),
+        Token::LocaleWeekYear4 => format!("{:04}", locale_week_year),
+        Token::LocaleWeekYear2 => format!("{:02}", locale_week_year.rem_euclid(100)),
+        Token::IsoWeekYear4 => format!("{:04}", iso.year()),
+        Token::IsoWeekYear2 => format!("{:02}", iso.year().rem_euclid(100)),
+        Token::Hour24Pad => format!("{:02}", dt.hour()),
+        Token::Hour24 => dt.hour().to_string(),
+        Token::Hour12Pad => format!("{:02}", hour12),
+        Token::Hour12 => hour12.to_string(),
+        Token::Hour1To24Pad => format!("{:02}", if dt.hour() == 0 { 24 } else { dt.hour() }),
+        Token::Hour1To24 => (if dt.hour() == 0 { 24 } else { dt.hour() }).to_string(),
+        Token::MinutePad => format!("{:02}", dt.minute()),
+        Token::Minute => dt.minute().to_string(),
+        Token::SecondPad => format!("{:02}", dt.second()),
+        Token::Second => dt.second().to_string(),
+        Token::Millis => format!("{:03}", dt.timestamp_subsec_millis().min(999)),
+        Token::AmPmLower => if dt.hour() < 12 { "am" } else { "pm" }.to_string(),
+        Token::AmPmUpper => if dt.hour() < 12 { "AM" } else { "PM" }.to_string(),
+        Token::UnixSeconds => dt.timestamp().to_string(),
+        Token::UnixMillis => dt.timestamp_millis().to_string(),
+        Token::OffsetCompact => dt.format("%z").to_string(),
+        Token::Offset => dt.format("%:z").to_string(),
+    }
+}
+
+fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Week-year and week number with Moment's English locale rules
+/// (week starts Sunday, the week containing January 1st is week 1).
+pub fn locale_week(date: NaiveDate) -> (i32, u32) {
+    week_of_year(date, Weekday::Sun, 6)
+}
+
+/// Moment's `weekOfYear(mom, dow, doy)`; ISO weeks are `(Mon, 4)`.
+fn week_of_year(date: NaiveDate, dow: Weekday, doy: i32) -> (i32, u32) {
+    let year = date.year();
+    let offset = first_week_offset(year, dow, doy);
+    let week = (date.ordinal() as i32 - offset - 1).div_euclid(7) + 1;
+
+    if week < 1 {
+        let previous = year - 1;
+        (previous, (week + weeks_in_year(previous, dow, doy)) as u32)
+    } else if week > weeks_in_year(year, dow, doy) {
+        (year + 1, (week - weeks_in_year(year, dow, doy)) as u32)
+    } else {
+        (year, week as u32)
+    }
+}
+
+fn first_week_offset(year: i32, dow: Weekday, doy: i32) -> i32 {
+    let dow = dow.num_days_from_sunday() as i32;
+    // Der Januar-Tag, der immer in Woche 1 liegt (1 für en, 4 für ISO).
+    let fwd = 7 + dow - doy;
+    let anchor = NaiveDate::from_ymd_opt(year, 1, fwd as u32).expect("valid January date");
+    let fwdlw = (7 + anchor.weekday().num_days_from_sunday() as i32 - dow) % 7;
+    -fwdlw + fwd - 1
+}
+
+fn weeks_in_year(year: i32, dow: Weekday, doy: i32) -> i32 {
+    let days_in_year = if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+        366
+    } else {
+        365
+    };
+    (days_in_year - first_week_offset(year, dow, doy) + first_week_offset(year + 1, dow, doy)) / 7
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn fixed() -> DateTime<FixedOffset> {
+        // Freitag, 15. März 2024, 14:05:09.042 (+01:00)
+        FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 15, 14, 5, 9)
+            .unwrap()
+            + chrono::Duration::milliseconds(42)
+    }
+
+    #[test]
+    fn note_template_variables() {
+        let dt = fixed();
+        let template =
+            "# {{title}}\ncreated: {{date}} {{time}}\n{{date:dddd, MMMM Do}} {{ TIME : HH[h] }}\n";
+        assert_eq!(
+            expand_note_template(template, "2024-03-15", &dt),
+            "# 2024-03-15\ncreated: 2024-03-15 14:05\nFriday, March 15th 14h\n"
+        );
+    }
+
+    #[test]
+    fn note_template_keeps_unknown_and_unclosed_braces() {
+        let dt = fixed();
+        assert_eq!(
+            expand_note_template("{{tp.file.title}} {{date", "x", &dt),
+            "{{tp.file.title}} {{date"
+        );
+        assert_eq!(
+            expand_note_template("{{title:YYYY}}", "x", &dt),
+            "{{title:YYYY}}"
+        );
+        assert_eq!(expand_note_template("", "x", &dt), "");
+    }
+
+    #[test]
+    fn year_month_and_quarter_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("YYYY", &dt), "2024");
+        assert_eq!(format_date("YY", &dt), "24");
+        assert_eq!(format_date("Q", &dt), "1");
+        assert_eq!(format_date("MMMM", &dt), "March");
+        assert_eq!(format_date("MMM", &dt), "Mar");
+        assert_eq!(format_date("MM", &dt), "03");
+        assert_eq!(format_date("Mo", &dt), "3rd");
+        assert_eq!(format_date("M", &dt), "3");
+    }
+
+    #[test]
+    fn day_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("DD", &dt), "15");
+        assert_eq!(format_date("D", &dt), "15");
+        assert_eq!(format_date("Do", &dt), "15th");
+        assert_eq!(format_date("DDDD", &dt), "075");
+        assert_eq!(format_date("DDD", &dt), "75");
+        assert_eq!(format_date("DDDo", &dt), "75th");
+    }
+
+    #[test]
+    fn weekday_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("dddd", &dt), "Friday");
+        assert_eq!(format_date("ddd", &dt), "Fri");
+        assert_eq!(format_date("dd", &dt), "Fr");
+        assert_eq!(format_date("d", &dt), "5");
+        assert_eq!(format_date("do", &dt), "5th");
+        assert_eq!(format_date("e", &dt), "5");
+        assert_eq!(format_date("E", &dt), "5");
+    }
+
+    #[test]
+    fn week_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("ww", &dt), "11");
+        assert_eq!(format_date("w", &dt), "11");
+        assert_eq!(format_date("wo", &dt), "11th");
+        assert_eq!(format_date("WW", &dt), "11");
+        assert_eq!(format_date("W", &dt), "11");
+        assert_eq!(format_date("Wo", &dt), "11th");
+        assert_eq!(format_date("gggg", &dt), "2024");
+        assert_eq!(format_date("gg", &dt), "24");
+        assert_eq!(format_date("GGGG", &dt), "2024");
+        assert_eq!(format_date("GG", &dt), "24");
+    }
+
+    #[test]
+    fn time_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("HH", &dt), "14");
+        assert_eq!(format_date("H", &dt), "14");
+        assert_eq!(format_date("hh", &dt), "02");
+        assert_eq!(format_date("h", &dt), "2");
+        assert_eq!(format_date("kk", &dt), "14");
+        assert_eq!(format_date("k", &dt), "14");
+        assert_eq!(format_date("mm", &dt), "05");
+        assert_eq!(format_date("m", &dt), "5");
+        assert_eq!(format_date("ss", &dt), "09");
+        assert_eq!(format_date("s", &dt), "9");
+        assert_eq!(format_date("SSS", &dt), "042");
+        assert_eq!(format_date("a", &dt), "pm");
+        assert_eq!(format_date("A", &dt), "PM");
+    }
+
+    #[test]
+    fn replaces_time_with_placeholder() {
+        let dt = fixed();
+        assert_eq!(replace_time("#### HH:mm", "--:--"), "#### [--:--]");
+        assert_eq!(
+            format_date(&replace_time("[Log] dddd h:mm a, HH:mm:ss", "?"), &dt),
+            "Log Friday ?, ?"
+        );
+        assert_eq!(
+            format_date(&replace_time("YYYY-MM-DD HH", "later"), &dt),
+            "2024-03-15 later"
+        );
+    }
+
+    #[test]
+    fn timestamp_and_offset_tokens() {
+        let dt = fixed();
+        assert_eq!(format_date("X", &dt), "1710507909");
+        assert_eq!(format_date("x", &dt), "1710507909042");
+        assert_eq!(format_date("Z", &dt), "+01:00");
+        assert_eq!(format_date("ZZ", &dt), "+0100");
+    }
+
+    #[test]
+    fn midnight_uses_24_for_k() {
+        let dt = Utc.with_ymd_and_hms(2024, 3, 15, 0, 30, 0).unwrap();
+        assert_eq!(format_date("k", &dt), "24");
+        assert_eq!(format_date("HH", &dt), "00");
+        assert_eq!(format_date("h A", &dt), "12 AM");
+    }
+
+    #[test]
+    fn ordinals_cover_teens() {
+        assert_eq!(ordinal(1), "1st");
+        assert_eq!(ordinal(2), "2nd");
+        assert_eq!(ordinal(3), "3rd");
+        assert_eq!(ordinal(4), "4th");
+        assert_eq!(ordinal(11), "11th");
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(13), "13th");
+        assert_eq!(ordinal(21), "21st");
+        assert_eq!(ordinal(112), "112th");
+    }
+
+    #[test]
+    fn brackets_escape_literal_text() {
+        let dt = fixed();
+        assert_eq!(format_date("[Week] ww", &dt), "Week 11");
+        assert_eq!(format_date("gggg-[W]ww", &dt), "2024-W11");
+        assert_eq!(format_date("[YYYY]-YYYY", &dt), "YYYY-2024");
+        assert_eq!(format_date("[]YYYY", &dt), "2024");
+        // Unterminated bracket stays literal
+        assert_eq!(format_date("[YYYY", &dt), "[2024");
+    }
+
+    #[test]
+    fn combined_obsidian_formats() {
+        let dt = fixed();
+        assert_eq!(format_date("YYYY-MM-DD dddd", &dt), "2024-03-15 Friday");
+        assert_eq!(
+            format_date("dddd, MMMM Do YYYY", &dt),
+            "Friday, March 15th 2024"
+        );
+        assert_eq!(format_date("YYYY-[Q]Q", &dt), "2024-Q1");
+        assert_eq!(format_date("#### HH:mm", &dt), "#### 14:05");
+    }
+
+    #[test]
+    fn substituted_values_are_not_rescanned() {
+        let dt = fixed();
+        // "March" enthält 'a' und 'M', "pm" enthält 'm'
+        assert_eq!(format_date("MMMM a", &dt), "March pm");
+        assert_eq!(format_date("A a", &dt), "PM pm");
+    }
+
+    #[test]
+    fn path_mode_keeps_words_intact() {
+        let dt = fixed();
+        assert_eq!(format_path("Journal/YYYY/MM", &dt), "Journal/2024/03");
+        assert_eq!(
+            format_path("Tagebuch/YYYY/YYYY-MM-DD.md", &dt),
+            "Tagebuch/2024/2024-03-15.md"
+        );
+        assert_eq!(format_path("Archive/MMMM", &dt), "Archive/March");
+        assert_eq!(format_path("Notes/[MM]/MM", &dt), "Notes/MM/03");
+    }
+
+    #[test]
+    fn locale_week_year_boundaries() {
+        // 2022-01-01 ist ein Samstag: Woche 1 von 2022 (en), aber ISO-Woche 52 von 2021
+        let dt = Utc.with_ymd_and_hms(2022, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(format_date("gggg-ww", &dt), "2022-01");
+        assert_eq!(format_date("GGGG-WW", &dt), "2021-52");
+
+        // 2024-12-29 ist ein Sonntag: en-Woche 1 von 2025, ISO-Woche 52 von 2024
+        let dt = Utc.with_ymd_and_hms(2024, 12, 29, 12, 0, 0).unwrap();
+        assert_eq!(format_date("gggg-ww", &dt), "2025-01");
+        assert_eq!(format_date("GGGG-WW", &dt), "2024-52");
+
+        // 2024-12-30 ist ein Montag: ISO-Woche 1 von 2025
+        let dt = Utc.with_ymd_and_hms(2024, 12, 30, 12, 0, 0).unwrap();
+        assert_eq!(format_date("GGGG-[W]WW", &dt), "2025-W01");
+    }
+
+    #[test]
+    fn escape_legacy_keeps_old_output() {
+        let header = ["YYYY", "MM", "DD", "HH", "hh", "mm", "ss", "h", "a", "A"];
+        assert_eq!(
+            escape_legacy("note-YYYY-MM-DD-HHmmss", &header),
+            "[note]-YYYY-MM-DD-HHmmss"
+        );
+        assert_eq!(
+            escape_legacy("Week of YYYY-MM-DD", &["YYYY", "MM", "DD"]),
+            "[Week of] YYYY-MM-DD"
+        );
+        // Im alten Format war das "a" in "Captured" ein Token
+        assert_eq!(
+            escape_legacy("Captured HH:mm", &header),
+            "[C]a[ptured] HH:mm"
+        );
+        assert_eq!(
+            escape_legacy("#### [[YYYY-MM-DD]] HH:mm", &header),
+            "#### [[][[]YYYY-MM-DD]] HH:mm"
+        );
+
+        let dt = Utc.with_ymd_and_hms(2024, 3, 5, 9, 7, 0).unwrap();
+        assert_eq!(
+            format_date(&escape_legacy("#### [[YYYY-MM-DD]] HH:mm", &header), &dt),
+            "#### [[2024-03-05]] 09:07"
+        );
+        assert_eq!(
+            format_date(&escape_legacy("Daily Dose YYYY", &["YYYY"]), &dt),
+            "Daily Dose 2024"
+        );
+    }
+
+    #[test]
+    fn week_of_year_matches_iso_for_monday_rules() {
+        let mut date = NaiveDate::from_ymd_opt(2019, 12, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2027, 1, 31).unwrap();
+        while date <= end {
+            let iso = date.iso_week();
+            assert_eq!(
+                week_of_year(date, Weekday::Mon, 4),
+                (iso.year(), iso.week()),
+                "mismatch on {}",
+                date
+            );
+            date = date.succ_opt().unwrap();
+        }
+    }
+}
@@ -4,10 +4,14 @@ export async function loadTabContent(path) {
   return invoke("read_note_file", { path });
 }
 
-export async function saveTabContent(path, content) {
+// expectedVersion/baseContent come from the last load or save; without them
+// the note is overwritten unconditionally.
+export async function saveTabContent(path, content, expectedVersion = null, baseContent = null) {
   return invoke("write_note_file", {
     path,
     content,
+    expectedVersion,
+    baseContent,
   });
 }
 
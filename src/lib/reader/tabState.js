@@ -56,6 +56,8 @@ export function createTab({
     label: label.trim() || fallbackLabel,
     icon: icon.trim(),
     content: existingTab?.content ?? "",
+    savedContent: existingTab?.savedContent ?? null,
+    version: existingTab?.version ?? null,
     loaded: existingTab?.loaded ?? false,
     missing: existingTab?.missing ?? false,
     missingMessage: existingTab?.missingMessage ?? "",
@@ -17,16 +17,32 @@ export const defaultSettings = {
   daily_note_folder: 'Journal/',
   daily_note_format: 'YYYY-MM-DD',
   daily_note_path: '',
+  daily_note_heading: '',
+  daily_note_insert_position: 'append',
+  daily_note_entry_style: 'header',
+  daily_note_auto_create: true,
+  daily_note_template: '',
+  follow_obsidian_daily_notes: false,
+  day_starts_at: 0,
+  time_zone: '',
+  weekly_note: { folder: '', format: 'gggg-[W]ww', template: '', entry_style: 'header', heading: '', insert_position: 'append' },
+  monthly_note: { folder: '', format: 'YYYY-MM', template: '', entry_style: 'header', heading: '', insert_position: 'append' },
+  quarterly_note: { folder: '', format: 'YYYY-[Q]Q', template: '', entry_style: 'header', heading: '', insert_position: 'append' },
+  yearly_note: { folder: '', format: 'YYYY', template: '', entry_style: 'header', heading: '', insert_position: 'append' },
   screenshot_path: 'Grafiken/Screenshots',
   image_folder: 'Grafiken/Screenshots',
-  image_filename: 'screenshot-YYYY-MM-DD-HHmmss',
+  image_filename: '[screenshot]-YYYY-MM-DD-HHmmss',
   default_image_width: '600',
   entry_header: '#### HH:mm',
+  entry_time_format: 'HH:mm',
+  other_day_entry_time: '',
+  task_date_format: 'off',
   show_note_paths: true,
   autocomplete_results: 20,
   global_shortcut: 'Cmd+Shift+N',
   global_shortcut_closes_window: false,
   global_close_shortcut: '',
+  capture_text_as_markdown: true,
 
   compression_max_kb: 200,
   edge_detection_enabled: true,
@@ -48,6 +64,8 @@ export const defaultSettings = {
   save_to_daily_shortcut: 'Cmd+Enter',
   save_as_note_shortcut: 'Cmd+Shift+Enter',
   append_to_note_shortcut: 'Cmd+Option+Enter',
+  append_to_note_insert_position: 'append',
+  append_to_note_entry_style: 'header',
   reader_shortcut: 'Cmd+Shift+R',
   reader_shortcut_closes_window: false,
   reader_close_shortcut: '',
@@ -60,8 +78,28 @@ export const defaultSettings = {
   reader_hide_inline_fields: true,
   reader_hide_html: true,
   reader_hide_callouts: true,
-  note_filename_template: 'note-YYYY-MM-DD-HHmmss',
+  note_filename_template: '[note]-YYYY-MM-DD-HHmmss',
+  template_syntax: 1,
+  note_collision_policy: 'suffix',
   note_template: '---\ncreated: <% tp.date.now("YYYY-MM-DD hh:mm") %>\nmodified: \ndaily: "[[<% tp.date.now("YYYY-MM-DD") %>]]"\ntags: inbox\ntype: inbox\n---',
+  note_properties: {
+    created: false,
+    created_format: 'YYYY-MM-DDTHH:mm',
+    tags: [],
+    aliases: [],
+    custom: [],
+  },
+  duplicate_capture_action: 'warn',
+  duplicate_capture_window_secs: 60,
+  duplicate_capture_entries: 5,
+  capture_history_enabled: true,
+  capture_history_store_text: true,
+  capture_history_retention_days: 90,
+  capture_rules: [],
+  auto_tag_rules: [],
+  record_source_app: false,
+  source_app_inline_field: false,
+  source_app_frontmatter: false,
 };
 
 export const settings = writable({